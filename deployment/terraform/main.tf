@@ -83,10 +83,13 @@ resource "aws_lambda_function" "telegram_bot" {
 
   environment {
     variables = {
-      RUST_LOG              = var.log_level
-      TELOXIDE_TOKEN        = var.telegram_token
-      OPENAI_API_KEY        = var.openai_api_key
-      DYNAMODB_TABLE_NAME   = aws_dynamodb_table.user_preferences.name
+      RUST_LOG                = var.log_level
+      TELOXIDE_TOKEN          = var.telegram_token
+      OPENAI_API_KEY          = var.openai_api_key
+      DYNAMODB_TABLE_NAME     = aws_dynamodb_table.user_preferences.name
+      CONVERSATION_TABLE_NAME = aws_dynamodb_table.conversation_history.name
+      INGEST_TABLE_NAME       = aws_dynamodb_table.ingest_bindings.name
+      USAGE_TABLE_NAME        = aws_dynamodb_table.usage_counters.name
       # WEBHOOK_URL will be set after deployment via Lambda update
     }
   }
@@ -141,6 +144,69 @@ resource "aws_dynamodb_table" "user_preferences" {
   }
 }
 
+# DynamoDB table for per-chat AI conversation history
+resource "aws_dynamodb_table" "conversation_history" {
+  name           = "${var.bot_name}-conversation-history"
+  billing_mode   = "PAY_PER_REQUEST"
+  hash_key       = "chat_id"
+
+  attribute {
+    name = "chat_id"
+    type = "S"
+  }
+
+  # TTL drops conversations that have been idle for 30 days
+  ttl {
+    attribute_name = "expires_at"
+    enabled        = true
+  }
+
+  tags = {
+    Name        = "${var.bot_name}-conversation-history"
+    Environment = var.environment
+  }
+}
+
+# DynamoDB table mapping inbound alert tokens to chats
+resource "aws_dynamodb_table" "ingest_bindings" {
+  name           = "${var.bot_name}-ingest-bindings"
+  billing_mode   = "PAY_PER_REQUEST"
+  hash_key       = "token"
+
+  attribute {
+    name = "token"
+    type = "S"
+  }
+
+  tags = {
+    Name        = "${var.bot_name}-ingest-bindings"
+    Environment = var.environment
+  }
+}
+
+# DynamoDB table for per-user usage counters and quotas
+resource "aws_dynamodb_table" "usage_counters" {
+  name           = "${var.bot_name}-usage-counters"
+  billing_mode   = "PAY_PER_REQUEST"
+  hash_key       = "usage_key"
+
+  attribute {
+    name = "usage_key"
+    type = "S"
+  }
+
+  # Counters are scoped to a period and expire after it ends
+  ttl {
+    attribute_name = "expires_at"
+    enabled        = true
+  }
+
+  tags = {
+    Name        = "${var.bot_name}-usage-counters"
+    Environment = var.environment
+  }
+}
+
 # IAM policy for DynamoDB access
 resource "aws_iam_role_policy" "lambda_dynamodb_policy" {
   name = "${var.bot_name}-dynamodb-policy"
@@ -161,7 +227,10 @@ resource "aws_iam_role_policy" "lambda_dynamodb_policy" {
         ]
         Resource = [
           aws_dynamodb_table.user_preferences.arn,
-          "${aws_dynamodb_table.user_preferences.arn}/index/*"
+          "${aws_dynamodb_table.user_preferences.arn}/index/*",
+          aws_dynamodb_table.conversation_history.arn,
+          aws_dynamodb_table.ingest_bindings.arn,
+          aws_dynamodb_table.usage_counters.arn
         ]
       }
     ]
@@ -177,6 +246,26 @@ resource "aws_lambda_permission" "allow_function_url" {
   source_arn    = "${aws_lambda_function.telegram_bot.arn}/*"
 }
 
+# Scheduled invocations run the jobs a long-running process keeps in the background (auto-delete sweep, watchdog)
+resource "aws_cloudwatch_event_rule" "scheduled_jobs" {
+  name                = "${var.bot_name}-scheduled-jobs"
+  description         = "Runs the bot's background jobs"
+  schedule_expression = var.scheduled_jobs_rate
+}
+
+resource "aws_cloudwatch_event_target" "scheduled_jobs" {
+  rule = aws_cloudwatch_event_rule.scheduled_jobs.name
+  arn  = aws_lambda_function.telegram_bot.arn
+}
+
+resource "aws_lambda_permission" "allow_scheduled_jobs" {
+  statement_id  = "AllowEventBridgeInvoke"
+  action        = "lambda:InvokeFunction"
+  function_name = aws_lambda_function.telegram_bot.function_name
+  principal     = "events.amazonaws.com"
+  source_arn    = aws_cloudwatch_event_rule.scheduled_jobs.arn
+}
+
 # Update Lambda function with webhook URL after function URL is created
 resource "null_resource" "update_webhook_url" {
   depends_on = [
@@ -192,7 +281,7 @@ resource "null_resource" "update_webhook_url" {
     command = <<-EOF
       aws lambda update-function-configuration \
         --function-name ${aws_lambda_function.telegram_bot.function_name} \
-        --environment Variables="{RUST_LOG=${var.log_level},TELOXIDE_TOKEN=${var.telegram_token},OPENAI_API_KEY=${var.openai_api_key},DYNAMODB_TABLE_NAME=${aws_dynamodb_table.user_preferences.name},WEBHOOK_URL=${aws_lambda_function_url.telegram_bot_url.function_url}}" \
+        --environment Variables="{RUST_LOG=${var.log_level},TELOXIDE_TOKEN=${var.telegram_token},OPENAI_API_KEY=${var.openai_api_key},DYNAMODB_TABLE_NAME=${aws_dynamodb_table.user_preferences.name},CONVERSATION_TABLE_NAME=${aws_dynamodb_table.conversation_history.name},INGEST_TABLE_NAME=${aws_dynamodb_table.ingest_bindings.name},USAGE_TABLE_NAME=${aws_dynamodb_table.usage_counters.name},WEBHOOK_URL=${aws_lambda_function_url.telegram_bot_url.function_url}}" \
         --region ${var.aws_region}
     EOF
   }
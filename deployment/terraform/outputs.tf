@@ -23,6 +23,21 @@ output "dynamodb_table_name" {
   value       = aws_dynamodb_table.user_preferences.name
 }
 
+output "conversation_table_name" {
+  description = "Name of the DynamoDB table for AI conversation history"
+  value       = aws_dynamodb_table.conversation_history.name
+}
+
+output "ingest_table_name" {
+  description = "Name of the DynamoDB table for inbound alert tokens"
+  value       = aws_dynamodb_table.ingest_bindings.name
+}
+
+output "usage_table_name" {
+  description = "Name of the DynamoDB table for usage counters"
+  value       = aws_dynamodb_table.usage_counters.name
+}
+
 output "telegram_webhook_setup_command" {
   description = "Command to set up Telegram webhook"
   value       = "curl -X POST https://api.telegram.org/bot${var.telegram_token}/setWebhook -d 'url=${aws_lambda_function_url.telegram_bot_url.function_url}'"
@@ -66,4 +66,10 @@ variable "log_retention_days" {
     ], var.log_retention_days)
     error_message = "Log retention days must be a valid CloudWatch retention period."
   }
-}
\ No newline at end of file
+}
+
+variable "scheduled_jobs_rate" {
+  description = "EventBridge schedule for background jobs such as auto-delete and the watchdog"
+  type        = string
+  default     = "rate(1 minute)"
+}
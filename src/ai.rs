@@ -1,20 +1,136 @@
 use async_trait::async_trait;
 use async_openai::{
-    types::{ChatCompletionRequestUserMessageArgs, CreateChatCompletionRequestArgs},
+    error::OpenAIError,
+    types::{
+        ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestMessage,
+        ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs,
+        CreateChatCompletionRequestArgs, CreateImageRequestArgs, CreateSpeechRequestArgs, Image, ImageModel,
+        ImageQuality, ImageResponseFormat, ImageSize, SpeechModel, SpeechResponseFormat, Voice,
+    },
     Client,
 };
 use log::{info, warn};
 use std::error::Error;
-use crate::storage::{create_storage, get_default_model};
+use std::sync::{Arc, OnceLock};
+use crate::setup::get_config;
+use crate::storage::{create_storage, get_default_model, AiSettings, ConversationMessage, ConversationRole};
+
+// Per-request generation settings, e.g. from the chat's active persona
+#[derive(Debug, Clone, Default)]
+pub struct ChatOptions {
+    pub system_prompt: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub top_p: Option<f32>,
+}
+
+impl ChatOptions {
+    pub fn max_tokens(&self) -> u32 {
+        self.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS)
+    }
+}
+
+// Reply length cap when a chat has not configured max_tokens
+pub const DEFAULT_MAX_TOKENS: u32 = 500;
+pub const MAX_TOKENS_LIMIT: u32 = 4096;
+
+// A chat completion and the tokens it consumed, when the provider reports them
+#[derive(Debug, Clone)]
+pub struct ChatReply {
+    pub content: String,
+    pub total_tokens: Option<u64>,
+}
 
 // Extensible AI backend trait
 #[async_trait]
 pub trait AiBackend: Send + Sync {
-    async fn chat(&self, message: &str) -> Result<String, Box<dyn Error + Send + Sync>>;
+    async fn chat(
+        &self,
+        options: &ChatOptions,
+        history: &[ConversationMessage],
+        message: &str,
+    ) -> Result<ChatReply, Box<dyn Error + Send + Sync>>;
     #[allow(dead_code)]
     fn name(&self) -> &'static str;
 }
 
+// Hidden reasoning tokens count against max_completion_tokens, so reasoning models get this on top of
+// the reply length or a short cap leaves them with an empty answer
+const REASONING_TOKEN_ALLOWANCE: u32 = 4096;
+
+// Request parameters a model accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelCapabilities {
+    // o-series models think before answering and are billed for it
+    pub reasoning: bool,
+    // The first o1 releases reject system messages
+    pub system_messages: bool,
+    // Reasoning models reject max_tokens in favour of max_completion_tokens
+    pub max_completion_tokens: bool,
+    // Reasoning models reject temperature and top_p
+    pub sampling: bool,
+}
+
+pub fn model_capabilities(model: &str) -> ModelCapabilities {
+    let model = model.to_lowercase();
+    let reasoning = model
+        .strip_prefix('o')
+        .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()));
+    ModelCapabilities {
+        reasoning,
+        system_messages: !(model.starts_with("o1-preview") || model.starts_with("o1-mini")),
+        max_completion_tokens: reasoning,
+        sampling: !reasoning,
+    }
+}
+
+// Build the OpenAI-style message list: system prompt, stored history, then the new user message.
+// Models without system messages get the system prompt in front of the new message instead
+fn build_chat_messages(
+    options: &ChatOptions,
+    capabilities: ModelCapabilities,
+    history: &[ConversationMessage],
+    message: &str,
+) -> Result<Vec<ChatCompletionRequestMessage>, OpenAIError> {
+    let mut messages = Vec::with_capacity(history.len() + 2);
+    let folded_message;
+    let message = match &options.system_prompt {
+        Some(system_prompt) if !capabilities.system_messages => {
+            folded_message = format!("{system_prompt}\n\n{message}");
+            folded_message.as_str()
+        }
+        _ => message,
+    };
+    if let Some(system_prompt) = options.system_prompt.as_ref().filter(|_| capabilities.system_messages) {
+        messages.push(
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content(system_prompt.as_str())
+                .build()?
+                .into(),
+        );
+    }
+    for entry in history {
+        let request_message = match entry.role {
+            ConversationRole::User => ChatCompletionRequestUserMessageArgs::default()
+                .content(entry.content.as_str())
+                .build()?
+                .into(),
+            ConversationRole::Assistant => ChatCompletionRequestAssistantMessageArgs::default()
+                .content(entry.content.as_str())
+                .build()?
+                .into(),
+        };
+        messages.push(request_message);
+    }
+    messages.push(
+        ChatCompletionRequestUserMessageArgs::default()
+            .content(message)
+            .build()?
+            .into(),
+    );
+    Ok(messages)
+}
+
 // OpenAI ChatGPT implementation using async-openai SDK
 pub struct OpenAiBackend {
     client: Client<async_openai::config::OpenAIConfig>,
@@ -30,23 +146,41 @@ impl OpenAiBackend {
 
 #[async_trait]
 impl AiBackend for OpenAiBackend {
-    async fn chat(&self, message: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
-        let request = CreateChatCompletionRequestArgs::default()
-            .model(&self.model)
-            .max_tokens(500u32)
-            .messages(vec![
-                ChatCompletionRequestUserMessageArgs::default()
-                    .content(message)
-                    .build()?
-                    .into()
-            ])
-            .build()?;
+    async fn chat(
+        &self,
+        options: &ChatOptions,
+        history: &[ConversationMessage],
+        message: &str,
+    ) -> Result<ChatReply, Box<dyn Error + Send + Sync>> {
+        let capabilities = model_capabilities(&self.model);
+        let mut args = CreateChatCompletionRequestArgs::default();
+        args.model(&self.model)
+            .messages(build_chat_messages(options, capabilities, history, message)?);
+        if capabilities.max_completion_tokens {
+            args.max_completion_tokens(options.max_tokens() + REASONING_TOKEN_ALLOWANCE);
+        } else {
+            args.max_tokens(options.max_tokens());
+        }
+        // Chat settings and personas may carry sampling parameters the model would reject
+        if capabilities.sampling {
+            if let Some(temperature) = options.temperature {
+                args.temperature(temperature);
+            }
+            if let Some(top_p) = options.top_p {
+                args.top_p(top_p);
+            }
+        }
+        let request = args.build()?;
 
         let response = self.client.chat().create(request).await?;
+        let total_tokens = response.usage.as_ref().map(|usage| u64::from(usage.total_tokens));
 
         if let Some(choice) = response.choices.first() {
             if let Some(content) = &choice.message.content {
-                Ok(content.trim().to_string())
+                Ok(ChatReply {
+                    content: content.trim().to_string(),
+                    total_tokens,
+                })
             } else {
                 Err("No content in OpenAI response".into())
             }
@@ -60,7 +194,157 @@ impl AiBackend for OpenAiBackend {
     }
 }
 
-// Available OpenAI models
+pub(crate) const DEEPSEEK_API_BASE: &str = "https://api.deepseek.com";
+pub(crate) const DEEPSEEK_MODEL_PREFIX: &str = "deepseek-";
+
+// DeepSeek implementation using the OpenAI-compatible DeepSeek API
+pub struct DeepSeekBackend {
+    client: Client<async_openai::config::OpenAIConfig>,
+    model: String,
+    show_reasoning: bool,
+}
+
+impl DeepSeekBackend {
+    pub fn new(api_key: String, model: String, show_reasoning: bool) -> Self {
+        let config = async_openai::config::OpenAIConfig::new()
+            .with_api_base(DEEPSEEK_API_BASE)
+            .with_api_key(api_key);
+        Self {
+            client: Client::with_config(config),
+            model,
+            show_reasoning,
+        }
+    }
+}
+
+#[async_trait]
+impl AiBackend for DeepSeekBackend {
+    async fn chat(
+        &self,
+        options: &ChatOptions,
+        history: &[ConversationMessage],
+        message: &str,
+    ) -> Result<ChatReply, Box<dyn Error + Send + Sync>> {
+        // deepseek-reasoner returns a `reasoning_content` field that the typed
+        // OpenAI response does not model, so the raw JSON response is used here
+        let mut messages = Vec::with_capacity(history.len() + 2);
+        if let Some(system_prompt) = &options.system_prompt {
+            messages.push(serde_json::json!({ "role": "system", "content": system_prompt }));
+        }
+        messages.extend(
+            history
+                .iter()
+                .map(|entry| serde_json::json!({ "role": entry.role, "content": entry.content })),
+        );
+        messages.push(serde_json::json!({ "role": "user", "content": message }));
+
+        let mut request = serde_json::json!({
+            "model": self.model,
+            "max_tokens": options.max_tokens(),
+            "stream": false,
+            "messages": messages,
+        });
+        if let Some(temperature) = options.temperature {
+            request["temperature"] = serde_json::json!(temperature);
+        }
+        if let Some(top_p) = options.top_p {
+            request["top_p"] = serde_json::json!(top_p);
+        }
+
+        let response: serde_json::Value = self.client.chat().create_byot(request).await?;
+
+        let Some(choice_message) = response["choices"].get(0).map(|choice| &choice["message"]) else {
+            return Err("No response from DeepSeek".into());
+        };
+        let Some(content) = choice_message["content"].as_str() else {
+            return Err("No content in DeepSeek response".into());
+        };
+
+        let content = match choice_message["reasoning_content"].as_str() {
+            Some(reasoning) if self.show_reasoning && !reasoning.trim().is_empty() => {
+                info!("💭 DeepSeek returned reasoning content ({} chars)", reasoning.len());
+                format!("💭 Reasoning:\n{}\n\n💬 Answer:\n{}", reasoning.trim(), content.trim())
+            }
+            _ => content.trim().to_string(),
+        };
+        Ok(ChatReply {
+            content,
+            total_tokens: response["usage"]["total_tokens"].as_u64(),
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "DeepSeek"
+    }
+}
+
+pub(crate) const MISTRAL_API_BASE: &str = "https://api.mistral.ai/v1";
+pub(crate) const MISTRAL_MODEL_PREFIXES: [&str; 2] = ["mistral-", "codestral-"];
+
+// Mistral implementation using the OpenAI-compatible Mistral API
+pub struct MistralBackend {
+    client: Client<async_openai::config::OpenAIConfig>,
+    model: String,
+}
+
+impl MistralBackend {
+    pub fn new(api_key: String, model: String) -> Self {
+        let config = async_openai::config::OpenAIConfig::new()
+            .with_api_base(MISTRAL_API_BASE)
+            .with_api_key(api_key);
+        Self {
+            client: Client::with_config(config),
+            model,
+        }
+    }
+}
+
+#[async_trait]
+impl AiBackend for MistralBackend {
+    async fn chat(
+        &self,
+        options: &ChatOptions,
+        history: &[ConversationMessage],
+        message: &str,
+    ) -> Result<ChatReply, Box<dyn Error + Send + Sync>> {
+        let mut args = CreateChatCompletionRequestArgs::default();
+        args.model(&self.model)
+            .max_tokens(options.max_tokens())
+            .messages(build_chat_messages(options, model_capabilities(&self.model), history, message)?);
+        if let Some(temperature) = options.temperature {
+            args.temperature(temperature);
+        }
+        if let Some(top_p) = options.top_p {
+            args.top_p(top_p);
+        }
+        let request = args.build()?;
+
+        let response = self.client.chat().create(request).await?;
+        let total_tokens = response.usage.as_ref().map(|usage| u64::from(usage.total_tokens));
+
+        match response.choices.first() {
+            Some(choice) => match &choice.message.content {
+                Some(content) => Ok(ChatReply {
+                    content: content.trim().to_string(),
+                    total_tokens,
+                }),
+                None => Err("No content in Mistral response".into()),
+            },
+            None => Err("No response from Mistral".into()),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "Mistral"
+    }
+}
+
+// OpenAI key from the environment or from /setup
+pub fn get_openai_api_key() -> Result<String, &'static str> {
+    get_config("OPENAI_API_KEY").ok_or("OPENAI_API_KEY environment variable not set")
+}
+
+// Available AI models across all backends
 pub fn get_available_models() -> Vec<String> {
     vec![
         "gpt-4o".to_string(),
@@ -70,9 +354,39 @@ pub fn get_available_models() -> Vec<String> {
         "gpt-3.5-turbo".to_string(),
         "o1-preview".to_string(),
         "o1-mini".to_string(),
+        "deepseek-chat".to_string(),
+        "deepseek-reasoner".to_string(),
+        "mistral-large-latest".to_string(),
+        "codestral-latest".to_string(),
     ]
 }
 
+// Helper function to get the ordered fallback models from AI_FALLBACK_MODELS, e.g. "gpt-4o,gpt-4o-mini,gpt-3.5-turbo"
+pub fn get_fallback_models() -> Vec<String> {
+    std::env::var("AI_FALLBACK_MODELS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|model| model.trim().to_string())
+        .filter(|model| !model.is_empty())
+        .collect()
+}
+
+// Models to try for a request: the preferred one, then the fallbacks listed after it (or all of them)
+pub fn get_model_chain(preferred: &str) -> Vec<String> {
+    let fallbacks = get_fallback_models();
+    let start = fallbacks
+        .iter()
+        .position(|model| model == preferred)
+        .map_or(0, |index| index + 1);
+    let mut chain = vec![preferred.to_string()];
+    for model in &fallbacks[start..] {
+        if !chain.contains(model) {
+            chain.push(model.clone());
+        }
+    }
+    chain
+}
+
 // Get current model for a specific chat from DynamoDB
 pub async fn get_current_model(chat_id: &str) -> String {
     info!("🔍 Getting current model for chat_id: {chat_id}");
@@ -117,12 +431,294 @@ pub async fn set_current_model(chat_id: &str, model: String) -> Result<(), Box<d
     Ok(())
 }
 
+// Get the generation settings for a specific chat, falling back to defaults
+pub async fn get_ai_settings(chat_id: &str) -> AiSettings {
+    match create_storage().await {
+        Ok(storage) => match storage.get_ai_settings(chat_id).await {
+            Ok(settings) => settings,
+            Err(e) => {
+                warn!("⚠️ Failed to get AI settings from storage: {e}");
+                AiSettings::default()
+            }
+        },
+        Err(e) => {
+            warn!("⚠️ Failed to create storage client: {e}");
+            AiSettings::default()
+        }
+    }
+}
+
+// Save the generation settings for a specific chat in DynamoDB
+pub async fn set_ai_settings(chat_id: &str, settings: &AiSettings) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    storage.set_ai_settings(chat_id, settings).await?;
+    Ok(())
+}
+
+// Apply "key=value" changes such as "temperature=0.7 max_tokens=1500", "default" clears a value
+pub fn update_ai_settings(settings: &AiSettings, args: &str) -> Result<AiSettings, String> {
+    let mut updated = *settings;
+    for token in args.split_whitespace() {
+        let Some((key, value)) = token.split_once('=') else {
+            return Err(format!("Expected key=value, got '{token}'"));
+        };
+        let reset = matches!(value.to_lowercase().as_str(), "default" | "off" | "reset");
+        match key.to_lowercase().as_str() {
+            "temperature" | "temp" => {
+                updated.temperature = if reset {
+                    None
+                } else {
+                    match value.parse::<f32>() {
+                        Ok(v) if (0.0..=2.0).contains(&v) => Some(v),
+                        _ => return Err(format!("temperature must be between 0 and 2, got '{value}'")),
+                    }
+                }
+            }
+            "max_tokens" | "maxtokens" => {
+                updated.max_tokens = if reset {
+                    None
+                } else {
+                    match value.parse::<u32>() {
+                        Ok(v) if (1..=MAX_TOKENS_LIMIT).contains(&v) => Some(v),
+                        _ => return Err(format!("max_tokens must be between 1 and {MAX_TOKENS_LIMIT}, got '{value}'")),
+                    }
+                }
+            }
+            "top_p" | "topp" => {
+                updated.top_p = if reset {
+                    None
+                } else {
+                    match value.parse::<f32>() {
+                        Ok(v) if v > 0.0 && v <= 1.0 => Some(v),
+                        _ => return Err(format!("top_p must be greater than 0 and at most 1, got '{value}'")),
+                    }
+                }
+            }
+            other => return Err(format!("Unknown setting '{other}', use temperature, max_tokens or top_p")),
+        }
+    }
+    Ok(updated)
+}
+
+// Custom backend factory installed by an embedding application, see BotAppBuilder::ai_backend
+pub type AiBackendFactory =
+    dyn Fn(&str) -> Result<Box<dyn AiBackend>, Box<dyn Error + Send + Sync>> + Send + Sync;
+
+static AI_BACKEND_FACTORY: OnceLock<Arc<AiBackendFactory>> = OnceLock::new();
+
+// Install a custom backend factory, only the first one installed takes effect
+pub fn set_ai_backend_factory(factory: Arc<AiBackendFactory>) -> bool {
+    AI_BACKEND_FACTORY.set(factory).is_ok()
+}
+
 // AI Backend factory with configurable model
 pub fn create_ai_backend_with_model(model: &str) -> Result<Box<dyn AiBackend>, Box<dyn Error + Send + Sync>> {
-    if let Ok(api_key) = std::env::var("OPENAI_API_KEY") {
-        Ok(Box::new(OpenAiBackend::new(api_key, model.to_string())))
-    } else {
-        Err("OPENAI_API_KEY environment variable not set".into())
+    match AI_BACKEND_FACTORY.get() {
+        Some(factory) => factory(model),
+        None => create_builtin_ai_backend(model),
+    }
+}
+
+// Built-in OpenAI, DeepSeek and Mistral backends, selected by model name prefix
+pub fn create_builtin_ai_backend(model: &str) -> Result<Box<dyn AiBackend>, Box<dyn Error + Send + Sync>> {
+    if model.starts_with(DEEPSEEK_MODEL_PREFIX) {
+        let api_key = std::env::var("DEEPSEEK_API_KEY")
+            .map_err(|_| "DEEPSEEK_API_KEY environment variable not set")?;
+        let show_reasoning = std::env::var("DEEPSEEK_SHOW_REASONING")
+            .map(|v| v == "true")
+            .unwrap_or(false);
+        return Ok(Box::new(DeepSeekBackend::new(api_key, model.to_string(), show_reasoning)));
+    }
+
+    if MISTRAL_MODEL_PREFIXES.iter().any(|prefix| model.starts_with(prefix)) {
+        let api_key = std::env::var("MISTRAL_API_KEY")
+            .map_err(|_| "MISTRAL_API_KEY environment variable not set")?;
+        return Ok(Box::new(MistralBackend::new(api_key, model.to_string())));
+    }
+
+    Ok(Box::new(OpenAiBackend::new(get_openai_api_key()?, model.to_string())))
+}
+
+
+// Image generation settings parsed from /imagine options
+#[derive(Debug, Clone)]
+pub struct ImageOptions {
+    pub size: ImageSize,
+    pub quality: ImageQuality,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        Self {
+            size: ImageSize::S1024x1024,
+            quality: ImageQuality::Standard,
+        }
+    }
+}
+
+// Split leading `size=` / `quality=` options from the prompt
+pub fn parse_image_request(args: &str) -> Result<(ImageOptions, String), String> {
+    let mut options = ImageOptions::default();
+    let mut rest = args.trim();
+
+    loop {
+        let (token, remainder) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let Some((key, value)) = token.split_once('=') else {
+            break;
+        };
+        match key.to_lowercase().as_str() {
+            "size" => {
+                options.size = match value {
+                    "1024x1024" | "square" => ImageSize::S1024x1024,
+                    "1792x1024" | "landscape" => ImageSize::S1792x1024,
+                    "1024x1792" | "portrait" => ImageSize::S1024x1792,
+                    _ => return Err(format!("Unsupported size '{value}', use 1024x1024, 1792x1024 or 1024x1792")),
+                }
+            }
+            "quality" => {
+                options.quality = match value {
+                    "standard" => ImageQuality::Standard,
+                    "hd" => ImageQuality::HD,
+                    _ => return Err(format!("Unsupported quality '{value}', use standard or hd")),
+                }
+            }
+            _ => break,
+        }
+        rest = remainder.trim_start();
+    }
+
+    if rest.is_empty() {
+        return Err("Please describe the image, e.g. /imagine size=1792x1024 a lighthouse at dawn".to_string());
     }
+    Ok((options, rest.to_string()))
 }
 
+// Helper function to get the configured image model
+pub fn get_image_model() -> String {
+    std::env::var("IMAGE_MODEL").unwrap_or_else(|_| "dall-e-3".to_string())
+}
+
+// Generate an image and return its temporary URL
+pub async fn generate_image(prompt: &str, options: &ImageOptions) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let api_key = get_openai_api_key()?;
+    let client = Client::with_config(async_openai::config::OpenAIConfig::new().with_api_key(api_key));
+
+    let model = match get_image_model().as_str() {
+        "dall-e-2" => ImageModel::DallE2,
+        "dall-e-3" => ImageModel::DallE3,
+        other => ImageModel::Other(other.to_string()),
+    };
+    info!("🎨 Generating image with {model:?} ({:?}, {:?})", options.size, options.quality);
+
+    let request = CreateImageRequestArgs::default()
+        .prompt(prompt)
+        .model(model)
+        .n(1)
+        .size(options.size)
+        .quality(options.quality.clone())
+        .response_format(ImageResponseFormat::Url)
+        .build()?;
+
+    let response = client.images().create(request).await?;
+    match response.data.first().map(|image| image.as_ref()) {
+        Some(Image::Url { url, .. }) => Ok(url.clone()),
+        Some(Image::B64Json { .. }) => Err("Image API returned base64 data instead of a URL".into()),
+        None => Err("No image in OpenAI response".into()),
+    }
+}
+
+// OpenAI rejects speech input longer than this many characters
+const MAX_SPEECH_INPUT_CHARS: usize = 4096;
+
+// Helper function to get the configured text-to-speech model
+pub fn get_speech_model() -> String {
+    std::env::var("TTS_MODEL").unwrap_or_else(|_| "tts-1".to_string())
+}
+
+// Helper function to get the configured text-to-speech voice
+pub fn get_speech_voice() -> Voice {
+    match std::env::var("TTS_VOICE").unwrap_or_default().to_lowercase().as_str() {
+        "ash" => Voice::Ash,
+        "coral" => Voice::Coral,
+        "echo" => Voice::Echo,
+        "fable" => Voice::Fable,
+        "onyx" => Voice::Onyx,
+        "nova" => Voice::Nova,
+        "sage" => Voice::Sage,
+        "shimmer" => Voice::Shimmer,
+        _ => Voice::Alloy,
+    }
+}
+
+// Synthesize speech as OGG/Opus, the format Telegram expects for voice messages
+pub async fn synthesize_speech(text: &str) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let api_key = get_openai_api_key()?;
+    let client = Client::with_config(async_openai::config::OpenAIConfig::new().with_api_key(api_key));
+
+    let model = match get_speech_model().as_str() {
+        "tts-1" => SpeechModel::Tts1,
+        "tts-1-hd" => SpeechModel::Tts1Hd,
+        other => SpeechModel::Other(other.to_string()),
+    };
+    let voice = get_speech_voice();
+    let input: String = text.chars().take(MAX_SPEECH_INPUT_CHARS).collect();
+    info!("🔊 Synthesizing {} chars of speech with {model:?} ({voice:?})", input.chars().count());
+
+    let request = CreateSpeechRequestArgs::default()
+        .model(model)
+        .voice(voice)
+        .input(input)
+        .response_format(SpeechResponseFormat::Opus)
+        .build()?;
+
+    let response = client.audio().speech(request).await?;
+    Ok(response.bytes.to_vec())
+}
+
+// Check whether AI replies in a chat should be sent as voice messages
+pub async fn get_voice_replies(chat_id: &str) -> bool {
+    match create_storage().await {
+        Ok(storage) => match storage.get_voice_replies(chat_id).await {
+            Ok(enabled) => enabled,
+            Err(e) => {
+                warn!("⚠️ Failed to get voice reply setting from storage: {e}");
+                false
+            }
+        },
+        Err(e) => {
+            warn!("⚠️ Failed to create storage client: {e}");
+            false
+        }
+    }
+}
+
+// Enable or disable voice replies for a specific chat in DynamoDB
+pub async fn set_voice_replies(chat_id: &str, enabled: bool) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    storage.set_voice_replies(chat_id, enabled).await?;
+    Ok(())
+}
+
+// Check whether AI features are enabled in a chat, failing open when storage is unavailable
+pub async fn get_ai_enabled(chat_id: &str) -> bool {
+    match create_storage().await {
+        Ok(storage) => match storage.get_ai_enabled(chat_id).await {
+            Ok(enabled) => enabled,
+            Err(e) => {
+                warn!("⚠️ Failed to get AI kill switch from storage: {e}");
+                true
+            }
+        },
+        Err(e) => {
+            warn!("⚠️ Failed to create storage client: {e}");
+            true
+        }
+    }
+}
+
+// Turn all AI features of a chat on or off
+pub async fn set_ai_enabled(chat_id: &str, enabled: bool) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    storage.set_ai_enabled(chat_id, enabled).await?;
+    Ok(())
+}
@@ -0,0 +1,146 @@
+use log::info;
+use std::error::Error;
+use std::sync::Arc;
+use teloxide::prelude::*;
+
+use crate::ai::{set_ai_backend_factory, AiBackend};
+use crate::autodelete::spawn_autodelete_sweeper;
+use crate::chaos::log_chaos_configuration;
+use crate::deployment::{detect_deployment_mode, run_polling_mode, DeploymentMode};
+use crate::plugins::{bot_commands, register_plugins, CommandPlugin};
+use crate::quota::spawn_quota_report;
+use crate::setup::{notify_owner_if_incomplete, refresh_runtime_config};
+use crate::transcript::spawn_transcript_retention;
+use crate::watchdog::spawn_watchdog;
+
+#[cfg(feature = "lambda")]
+use crate::deployment::run_lambda_mode;
+
+#[cfg(feature = "axum-server")]
+use crate::deployment::run_webhook_mode;
+
+// A configured bot ready to run in one of the deployment modes
+pub struct BotApp {
+    bot: Bot,
+    deployment_mode: DeploymentMode,
+}
+
+// Builder for embedding the bot in another binary; unset parts use the environment like the stock binary
+#[derive(Default)]
+pub struct BotAppBuilder {
+    bot: Option<Bot>,
+    deployment_mode: Option<DeploymentMode>,
+    ai_backend_factory: Option<Arc<crate::ai::AiBackendFactory>>,
+    plugins: Vec<Arc<dyn CommandPlugin>>,
+}
+
+impl BotApp {
+    pub fn builder() -> BotAppBuilder {
+        BotAppBuilder::default()
+    }
+
+    pub fn bot(&self) -> &Bot {
+        &self.bot
+    }
+
+    pub fn deployment_mode(&self) -> DeploymentMode {
+        self.deployment_mode
+    }
+
+    pub async fn run(self) -> Result<(), Box<dyn Error>> {
+        info!("🚀 Bot deployment detection: {}", self.deployment_mode);
+        log_chaos_configuration();
+
+        // Keep Telegram's command menu in sync with built-in and plugin commands. Keys stored through /setup
+        // decide which commands can work, so load them first
+        refresh_runtime_config(&self.bot).await;
+        if let Err(e) = self.bot.set_my_commands(bot_commands()).await {
+            log::warn!("⚠️ Failed to update the bot command menu: {e}");
+        }
+        // Lambda cold starts are too frequent to message the owner on each one
+        if self.deployment_mode != DeploymentMode::Lambda {
+            notify_owner_if_incomplete(&self.bot).await;
+            spawn_watchdog(self.bot.clone());
+            spawn_transcript_retention();
+            spawn_autodelete_sweeper(self.bot.clone());
+            spawn_quota_report(self.bot.clone());
+        }
+
+        match self.deployment_mode {
+            DeploymentMode::Lambda => {
+                #[cfg(feature = "lambda")]
+                {
+                    run_lambda_mode(self.bot).await
+                }
+                #[cfg(not(feature = "lambda"))]
+                {
+                    Err("Lambda environment detected but lambda feature not enabled. Compile with --features lambda".into())
+                }
+            }
+            DeploymentMode::Webhook => {
+                #[cfg(feature = "axum-server")]
+                {
+                    run_webhook_mode(self.bot).await
+                }
+                #[cfg(not(feature = "axum-server"))]
+                {
+                    Err("Production environment detected but axum-server feature not enabled. Compile with --features axum-server".into())
+                }
+            }
+            DeploymentMode::Polling => {
+                run_polling_mode(self.bot).await;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl BotAppBuilder {
+    // Use this bot instead of Bot::from_env()
+    pub fn bot(mut self, bot: Bot) -> Self {
+        self.bot = Some(bot);
+        self
+    }
+
+    // Skip environment detection and always run in this mode
+    pub fn deployment_mode(mut self, deployment_mode: DeploymentMode) -> Self {
+        self.deployment_mode = Some(deployment_mode);
+        self
+    }
+
+    // Create AI backends with this factory; it can delegate to ai::create_builtin_ai_backend for other models
+    pub fn ai_backend<F>(mut self, factory: F) -> Self
+    where
+        F: Fn(&str) -> Result<Box<dyn AiBackend>, Box<dyn Error + Send + Sync>> + Send + Sync + 'static,
+    {
+        self.ai_backend_factory = Some(Arc::new(factory));
+        self
+    }
+
+    // Add a command without modifying commands.rs; names clashing with built-in commands are ignored
+    pub fn plugin<P: CommandPlugin + 'static>(mut self, plugin: P) -> Self {
+        self.plugins.push(Arc::new(plugin));
+        self
+    }
+
+    #[cfg_attr(not(feature = "wasm-plugins"), allow(unused_mut))]
+    pub fn build(mut self) -> BotApp {
+        // Operator-installed WASM modules register alongside compile-time plugins
+        #[cfg(feature = "wasm-plugins")]
+        self.plugins.extend(crate::wasm_plugins::load_wasm_plugins());
+
+        if !self.plugins.is_empty() && !register_plugins(self.plugins) {
+            log::warn!("⚠️ Plugins are already registered, keeping the first set");
+        }
+        if let Some(factory) = self.ai_backend_factory
+            && !set_ai_backend_factory(factory)
+        {
+            log::warn!("⚠️ A custom AI backend factory is already installed, keeping the first one");
+        }
+
+        BotApp {
+            bot: self.bot.unwrap_or_else(Bot::from_env),
+            deployment_mode: self.deployment_mode.unwrap_or_else(detect_deployment_mode),
+        }
+    }
+}
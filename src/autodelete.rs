@@ -0,0 +1,138 @@
+use log::{info, warn};
+use std::error::Error;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+use teloxide::prelude::*;
+use teloxide::types::MessageId;
+
+use crate::storage::create_storage;
+
+// Telegram only lets bots delete messages younger than 48 hours
+pub const MAX_AUTODELETE: Duration = Duration::from_secs(47 * 60 * 60);
+const MIN_AUTODELETE: Duration = Duration::from_secs(60);
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+// When this process last swept, so Lambda invocations sweep at most once a minute per warm instance
+static LAST_SWEEP: AtomicI64 = AtomicI64::new(0);
+
+pub fn validate_autodelete(duration: Duration) -> Result<u64, String> {
+    if duration < MIN_AUTODELETE || duration > MAX_AUTODELETE {
+        return Err("Auto-delete must be between 1 minute and 47 hours, Telegram refuses to delete older messages.".to_string());
+    }
+    Ok(duration.as_secs())
+}
+
+// How long the bot's messages stay in a chat, None when they are kept
+pub async fn get_autodelete(chat_id: &str) -> Option<u64> {
+    match create_storage().await {
+        Ok(storage) => match storage.get_autodelete_seconds(chat_id).await {
+            Ok(seconds) => seconds,
+            Err(e) => {
+                warn!("⚠️ Failed to get autodelete setting from storage: {e}");
+                None
+            }
+        },
+        Err(e) => {
+            warn!("⚠️ Failed to create storage client: {e}");
+            None
+        }
+    }
+}
+
+pub async fn set_autodelete(chat_id: &str, seconds: Option<u64>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    storage.set_autodelete_seconds(chat_id, seconds).await?;
+    // Messages already queued are still deleted after turning it off
+    if seconds.is_some() {
+        storage.set_autodelete_chat(chat_id, true).await?;
+    }
+    Ok(())
+}
+
+// Queue one of the bot's messages for deletion if the chat has auto-delete on
+pub async fn schedule_autodelete(chat_id: ChatId, message_id: MessageId) {
+    let key = chat_id.to_string();
+    let Some(seconds) = get_autodelete(&key).await else {
+        return;
+    };
+    let delete_at = chrono::Utc::now().timestamp() + seconds as i64;
+    let result = match create_storage().await {
+        Ok(storage) => storage.add_pending_delete(&key, format!("{delete_at}:{}", message_id.0)).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        warn!("⚠️ Failed to queue message {} in chat {chat_id} for deletion: {e}", message_id.0);
+    }
+}
+
+// Delete every queued message that is due, in all chats with auto-delete
+pub async fn sweep_due_deletes(bot: &Bot) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let now = chrono::Utc::now().timestamp();
+    LAST_SWEEP.store(now, Ordering::Relaxed);
+    let storage = create_storage().await?;
+
+    for chat in storage.get_autodelete_chats().await? {
+        let Ok(chat_id) = chat.parse::<i64>().map(ChatId) else {
+            continue;
+        };
+        let pending = storage.get_pending_deletes(&chat).await?;
+        if pending.is_empty() {
+            // Nothing queued and nothing more coming, so stop checking the chat
+            if storage.get_autodelete_seconds(&chat).await?.is_none() {
+                storage.set_autodelete_chat(&chat, false).await?;
+            }
+            continue;
+        }
+
+        let mut done = Vec::new();
+        for entry in pending {
+            let Some((delete_at, message_id)) = entry
+                .split_once(':')
+                .and_then(|(at, id)| Some((at.parse::<i64>().ok()?, id.parse::<i32>().ok()?)))
+            else {
+                done.push(entry);
+                continue;
+            };
+            if delete_at > now {
+                continue;
+            }
+            // A message someone already removed fails too, either way it leaves the queue
+            if let Err(e) = bot.delete_message(chat_id, MessageId(message_id)).await {
+                warn!("⚠️ Failed to auto-delete message {message_id} in chat {chat_id}: {e}");
+            }
+            done.push(entry);
+        }
+        if !done.is_empty() {
+            info!("🧽 Auto-deleted {} messages in chat {chat_id}", done.len());
+            storage.remove_pending_deletes(&chat, done).await?;
+        }
+    }
+    Ok(())
+}
+
+// Lambda has no background tasks, so invocations sweep on the way out when the last sweep is a minute old
+#[cfg(feature = "lambda")]
+pub async fn sweep_if_due(bot: &Bot) {
+    if chrono::Utc::now().timestamp() - LAST_SWEEP.load(Ordering::Relaxed) < SWEEP_INTERVAL.as_secs() as i64 {
+        return;
+    }
+    if let Err(e) = sweep_due_deletes(bot).await {
+        warn!("⚠️ Auto-delete sweep failed: {e}");
+    }
+}
+
+pub fn spawn_autodelete_sweeper(bot: Bot) {
+    // The queues live in the conversation table, without it there is nothing to sweep
+    if std::env::var("CONVERSATION_TABLE_NAME").is_err() {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = sweep_due_deletes(&bot).await {
+                warn!("⚠️ Auto-delete sweep failed: {e}");
+            }
+        }
+    });
+}
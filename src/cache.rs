@@ -0,0 +1,119 @@
+use log::{info, warn};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, LazyLock, Mutex};
+use tokio::sync::OnceCell;
+
+use crate::ai::ChatOptions;
+use crate::storage::create_storage;
+
+// Helper function to get how long AI replies are reused for identical requests, 0 (the default) disables the cache
+pub fn get_ai_cache_ttl() -> u64 {
+    std::env::var("AI_CACHE_TTL")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0)
+}
+
+// Case and whitespace differences should still hit the same entry
+fn normalize_prompt(prompt: &str) -> String {
+    prompt.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+// The instructions and sampling options are part of the key, so chats with different personas or
+// /aisettings never share replies
+fn cache_key(model: &str, options: &ChatOptions, prompt: &str) -> String {
+    let sampling = format!(
+        "temperature={:?} max_tokens={} top_p={:?}",
+        options.temperature,
+        options.max_tokens(),
+        options.top_p
+    );
+    let instructions = options.system_prompt.as_deref().unwrap_or_default();
+    let mut hasher = Sha256::new();
+    for part in [model, instructions, &sampling, &normalize_prompt(prompt)] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    hex::encode(hasher.finalize())
+}
+
+type Flight = Arc<OnceCell<Result<String, String>>>;
+
+// Identical cacheable requests being answered right now in this process. Only the upstream call is shared,
+// nothing here outlives it, so replicas and Lambda still behave the same
+static IN_FLIGHT: LazyLock<Mutex<HashMap<String, Flight>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// Run a model call, or wait for the identical one already running and share its result. If the running
+// call is dropped, for example because its user stopped it, one of the waiting requests makes the call instead
+pub async fn coalesce_reply<F>(model: &str, options: &ChatOptions, prompt: &str, request: F) -> Result<String, String>
+where
+    F: Future<Output = Result<String, String>>,
+{
+    let key = cache_key(model, options, prompt);
+    let flight = IN_FLIGHT
+        .lock()
+        .expect("in-flight request lock poisoned")
+        .entry(key.clone())
+        .or_default()
+        .clone();
+    let mut shared = true;
+    let result = flight
+        .get_or_init(|| {
+            shared = false;
+            request
+        })
+        .await
+        .clone();
+    if shared {
+        info!("🔗 Shared an in-flight {model} request");
+    }
+    // Later requests go to the reply cache, or make a fresh call when it is disabled
+    let mut in_flight = IN_FLIGHT.lock().expect("in-flight request lock poisoned");
+    if in_flight.get(&key).is_some_and(|current| Arc::ptr_eq(current, &flight)) {
+        in_flight.remove(&key);
+    }
+    result
+}
+
+// Cached reply for a request without conversation history, failing open to None
+pub async fn lookup_cached_reply(model: &str, options: &ChatOptions, prompt: &str) -> Option<String> {
+    if get_ai_cache_ttl() == 0 {
+        return None;
+    }
+    let key = cache_key(model, options, prompt);
+    let storage = match create_storage().await {
+        Ok(storage) => storage,
+        Err(e) => {
+            warn!("⚠️ Failed to create storage client, skipping reply cache: {e}");
+            return None;
+        }
+    };
+    match storage.get_cached_reply(&key).await {
+        Ok(Some(reply)) => {
+            info!("♻️ Reply cache hit for {model}");
+            Some(reply)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            warn!("⚠️ Failed to read reply cache: {e}");
+            None
+        }
+    }
+}
+
+pub async fn store_cached_reply(model: &str, options: &ChatOptions, prompt: &str, reply: &str) {
+    let ttl = get_ai_cache_ttl();
+    if ttl == 0 {
+        return;
+    }
+    let key = cache_key(model, options, prompt);
+    let result = match create_storage().await {
+        Ok(storage) => storage.set_cached_reply(&key, reply, ttl as i64).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        warn!("⚠️ Failed to write reply cache: {e}");
+    }
+}
@@ -0,0 +1,83 @@
+use log::{info, warn};
+use std::error::Error;
+use std::time::Duration;
+
+// Fault injection for staging: with CHAOS_ENABLED=true, AI requests and storage access fail or slow
+// down at the configured rates so degradation paths, fallbacks and alerting can be exercised for real.
+// Never set CHAOS_ENABLED in production.
+
+// Helper function to check whether fault injection is switched on at all
+pub fn is_chaos_enabled() -> bool {
+    std::env::var("CHAOS_ENABLED").map(|v| v == "true").unwrap_or(false)
+}
+
+// Probability between 0 and 1 from an environment variable, 0 when unset or chaos is off
+fn get_rate(variable: &str) -> f64 {
+    if !is_chaos_enabled() {
+        return 0.0;
+    }
+    std::env::var(variable)
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .map(|rate| rate.clamp(0.0, 1.0))
+        .unwrap_or(0.0)
+}
+
+// Helper function to get the artificial delay added before each AI request
+pub fn get_ai_latency() -> Duration {
+    if !is_chaos_enabled() {
+        return Duration::ZERO;
+    }
+    std::env::var("CHAOS_AI_LATENCY_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(Duration::ZERO)
+}
+
+// Uniform value in [0, 1) from the low bits of a v4 UUID, which are all random (the version and
+// variant bits sit higher up). Good enough for fault injection without another dependency
+fn roll() -> f64 {
+    const MANTISSA: u128 = 1 << 53;
+    (uuid::Uuid::new_v4().as_u128() % MANTISSA) as f64 / MANTISSA as f64
+}
+
+fn should_fail(rate: f64) -> bool {
+    rate > 0.0 && roll() < rate
+}
+
+// Delay and possibly fail an AI request to the given model, as configured by CHAOS_AI_LATENCY_MS and CHAOS_AI_ERROR_RATE
+pub async fn inject_ai_fault(model: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let latency = get_ai_latency();
+    if !latency.is_zero() {
+        info!("🐒 Chaos: delaying AI request to {model} by {} ms", latency.as_millis());
+        tokio::time::sleep(latency).await;
+    }
+    if should_fail(get_rate("CHAOS_AI_ERROR_RATE")) {
+        warn!("🐒 Chaos: failing AI request to {model}");
+        return Err(format!("Injected provider error for {model} (CHAOS_AI_ERROR_RATE)").into());
+    }
+    Ok(())
+}
+
+// Whether this storage access should fail, as configured by CHAOS_STORAGE_ERROR_RATE
+pub fn inject_storage_fault() -> bool {
+    let fail = should_fail(get_rate("CHAOS_STORAGE_ERROR_RATE"));
+    if fail {
+        warn!("🐒 Chaos: failing storage access");
+    }
+    fail
+}
+
+// Make it obvious in the logs that faults are being injected
+pub fn log_chaos_configuration() {
+    if !is_chaos_enabled() {
+        return;
+    }
+    warn!(
+        "🐒 Chaos fault injection is ENABLED: AI error rate {}, AI latency {} ms, storage error rate {}",
+        get_rate("CHAOS_AI_ERROR_RATE"),
+        get_ai_latency().as_millis(),
+        get_rate("CHAOS_STORAGE_ERROR_RATE")
+    );
+}
@@ -1,7 +1,68 @@
 use log::{info, warn};
-use teloxide::{prelude::*, utils::command::BotCommands};
+use minijinja::context;
+use teloxide::{
+    prelude::*,
+    types::{ChatAction, InputFile, MessageId, ParseMode},
+    utils::command::BotCommands,
+};
 
-use crate::ai::{create_ai_backend_with_model, get_available_models, get_current_model, set_current_model};
+use crate::ai::{
+    create_ai_backend_with_model, generate_image, get_ai_enabled, get_ai_settings, get_available_models, get_current_model,
+    get_model_chain, get_voice_replies, parse_image_request, set_ai_enabled, set_ai_settings, set_current_model, set_voice_replies,
+    synthesize_speech, update_ai_settings, ChatOptions, DEFAULT_MAX_TOKENS,
+};
+use crate::autodelete::{get_autodelete, schedule_autodelete, set_autodelete, validate_autodelete};
+use crate::cache::{coalesce_reply, lookup_cached_reply, store_cached_reply};
+use crate::chaos::inject_ai_fault;
+use crate::confirm::{request_confirmation, DestructiveAction};
+use crate::conversation::{
+    export_conversation, format_for_summary, history_token_budget,
+    load_conversation_history, load_conversation_summary, load_history_snapshot, record_conversation_turn,
+    save_conversation_summary, save_history_snapshot, split_for_compaction, ExportFormat, SUMMARIZE_HISTORY_PROMPT,
+};
+use crate::convert::convert;
+use crate::disclaimer::{append_disclaimer, get_chat_disclaimer, set_chat_disclaimer};
+use crate::flair::{get_flair_enabled, message_effect, set_flair_enabled, Occasion};
+use crate::formatter::markdown_to_telegram;
+use crate::health::{format_ai_status, record_ai_call, AiProvider};
+use crate::ingest::{
+    count_alert_mentions, create_github_ingest_token, create_ingest_token, get_alert_digest_window,
+    get_silent_deliveries, ingest_url, revoke_ingest_token, set_alert_digest_window, set_alert_mention,
+    set_delivery_silent, DeliveryType,
+};
+use crate::knowledge::{forget, list_knowledge, retrieve_knowledge, teach, MAX_DOCUMENT_BYTES};
+use crate::moderation::{
+    get_moderation_mode, moderate_prompt, moderate_reply, set_moderation_mode, validate_moderation_mode,
+    ModerationMode, PromptModeration, ReplyModeration,
+};
+use crate::pause::{format_duration, get_paused_until, parse_duration, parse_pause_duration, pause_chat, resume_chat};
+use crate::outbound::{capture_outbound, get_outbound_debug_size, outbound_debug_enabled, recent_outbound};
+use crate::persona::{add_custom_persona, get_chat_persona, list_chat_personas, remove_custom_persona, set_chat_persona};
+use crate::plugins::command_descriptions;
+use crate::progress::{cancel_chat_tasks, TaskProgress, TASK_CANCELLED};
+use crate::qr::generate_qr_png;
+use crate::quota::{format_quota_report, record_provider_usage, QuotaSource};
+use crate::risk::calculate_position;
+use crate::search::{create_search_provider, format_search_context, format_sources, web_search, SEARCH_PROMPT};
+use crate::settings::ChatSetting;
+use crate::setup::{get_owner_id, is_owner, start_setup};
+use crate::storage::{AiSettings, ConversationMessage, ConversationRole};
+use crate::summarize::{
+    fetch_page_text, get_recent_message_cache_size, load_recent_transcript, parse_page_url, DEFAULT_SUMMARY_MESSAGES,
+    SUMMARIZE_CHAT_PROMPT, SUMMARIZE_PAGE_PROMPT, SUMMARIZE_PROMPT,
+};
+use crate::templates::render;
+use crate::transcript::{get_transcript_retention_days, record_exchange, transcript_logging_enabled};
+use crate::translate::{
+    get_translate_language, parse_translate_args, set_translate_language, validate_language, TRANSLATE_PROMPT,
+};
+use crate::typing::ChatActionKeepAlive;
+use crate::untrusted::{guard_instructions, wrap_untrusted, UNTRUSTED_CONTENT_NOTICE};
+use crate::usage::{
+    check_ai_budget, consume_daily_image_quota, get_ai_budget, get_budget_report, next_budget_reset,
+    consume_ai_rate_limit, record_ai_tokens, refund_daily_image_quota, set_ai_budget, BudgetScope, BudgetStatus,
+    QuotaStatus, RateLimitStatus,
+};
 
 #[derive(BotCommands, Clone, Debug)]
 #[command(
@@ -19,6 +80,492 @@ pub enum Command {
     General(String),
     #[command(description = "change or view current AI model - use '/model list' to see available models.")]
     Model(String),
+    #[command(description = "view or change AI generation settings - '/aisettings temperature=0.7 max_tokens=1500 top_p=0.9' or '/aisettings reset'.")]
+    AiSettings(String),
+    #[command(description = "generate a QR code image - send the text or URL after the command.")]
+    Qr(String),
+    #[command(description = "convert between units - e.g. '/convert 5 miles to km'.")]
+    Convert(String),
+    #[command(description = "size a position from account risk - e.g. '/risk account=10000 risk=1% entry=150 stop=145'.")]
+    Risk(String),
+    #[command(description = "summarize with AI - reply to a message with /summarize, '/summarize <url>' for a web page, or '/summarize [count]' in groups for recent messages.")]
+    Summarize(String),
+    #[command(description = "translate with AI - '/translate [to:es] <text>', reply to a message with /translate, or '/translate set <language>'.")]
+    Translate(String),
+    #[command(description = "answer a question from fresh web search results with cited links - '/search <question>'.")]
+    Search(String),
+    #[command(description = "stop your AI request that is still running in this chat, admins stop everyone's.")]
+    Cancel,
+    #[command(description = "clear the AI conversation history for this chat.")]
+    Clear,
+    #[command(rename = "export_chat", description = "export the AI conversation history as a file - '/export_chat [markdown|json]'.")]
+    ExportChat(String),
+    #[command(description = "create or revoke an inbound alert URL for this chat - '/ingest new', '/ingest github <owner/repo>', '/ingest revoke <token>', '/ingest digest <seconds|off>' or '/ingest notify on|off' to be mentioned in alerts.")]
+    Ingest(String),
+    #[command(description = "teach the AI facts for this chat - '/teach <text>', reply to a message or .txt/.md file with /teach, '/teach list', '/teach forget <id>' or '/teach clear'.")]
+    Teach(String),
+    #[command(description = "switch the AI persona - '/persona list', '/persona <name>', '/persona off', '/persona add <name> <prompt>' or '/persona remove <name>'.")]
+    Persona(String),
+    #[command(description = "generate an image with AI - '/imagine [size=1792x1024] [quality=hd] <description>'.")]
+    Imagine(String),
+    #[command(description = "get the AI reply as a voice message - '/speak <message>', or '/speak on|off' to voice every AI reply in this chat.")]
+    Speak(String),
+    #[command(description = "show AI token usage or set monthly caps - '/budget', '/budget chat <tokens|off>' or '/budget user <tokens|off>'.")]
+    Budget(String),
+    #[command(description = "filter AI prompts and replies with OpenAI moderation - '/moderation off|refuse|redact'.")]
+    Moderation(String),
+    #[command(description = "show or set the disclaimer added to AI replies and alerts - '/disclaimer <text>|off|reset'.")]
+    Disclaimer(String),
+    #[command(description = "check AI backend keys, reachability, error rate and latency (admins).")]
+    AiStatus,
+    #[command(description = "view or change chat settings - '/groupconfig ai on|off' turns all AI features on or off, '/groupconfig autodelete <duration|off>' deletes the bot's replies after a while, '/groupconfig silent <alerts|digests> on|off' delivers them without a sound, '/groupconfig flair on|off' adds message effects (admins).")]
+    GroupConfig(String),
+    #[command(description = "change one chat setting by key - '/set <key> <value>', e.g. '/set autodelete 30m'. Most settings are admin only.")]
+    Set(String),
+    #[command(description = "show chat settings - '/get' lists them all, '/get <key>' explains one.")]
+    Get(String),
+    #[command(description = "mute alerts and unprompted replies in this chat for a while - '/pause 2h' (admins).")]
+    Pause(String),
+    #[command(description = "end a /pause early (admins).")]
+    Resume,
+    #[command(description = "show what the bot stores about this chat and whether transcripts are recorded.")]
+    Privacy,
+    #[command(description = "complete missing bot settings over chat (bot owner, private chat only).")]
+    Setup,
+    #[command(description = "download the latest AI replies as sent to Telegram, captured while OUTBOUND_DEBUG is on - '/outbound [count]' (bot owner, private chat only).")]
+    Outbound(String),
+}
+
+impl Command {
+    // Commands that call an AI model, rejected while AI is turned off in the chat
+    fn uses_ai(&self) -> bool {
+        matches!(
+            self,
+            Command::General(_)
+                | Command::Summarize(_)
+                | Command::Translate(_)
+                | Command::Search(_)
+                | Command::Imagine(_)
+                | Command::Speak(_)
+                | Command::Teach(_)
+        )
+    }
+}
+
+// Whether the integrations a command needs are configured in this deployment, so /help and the command
+// menu never offer commands that can only fail. Images, speech, moderation and /teach embeddings use OpenAI
+pub fn command_available(name: &str) -> bool {
+    match name {
+        "general" | "summarize" | "translate" | "model" | "aisettings" | "persona" => AiProvider::any_configured(),
+        "search" => AiProvider::any_configured() && create_search_provider().is_ok(),
+        "imagine" | "speak" | "moderation" | "teach" => AiProvider::OpenAi.is_configured(),
+        "setup" | "outbound" => get_owner_id().is_some(),
+        _ => true,
+    }
+}
+
+// Private chats are always allowed, groups require an administrator or the owner
+async fn is_chat_admin(bot: &Bot, msg: &Message) -> ResponseResult<bool> {
+    if msg.chat.is_private() {
+        return Ok(true);
+    }
+    let Some(user) = msg.from.as_ref() else {
+        return Ok(false);
+    };
+    let member = bot.get_chat_member(msg.chat.id, user.id).await?;
+    Ok(member.is_privileged())
+}
+
+// Forum topic a message was sent in. Replies in ordinary groups carry a thread id too, so only topic messages count
+fn forum_topic(msg: &Message) -> Option<i32> {
+    msg.thread_id.filter(|_| msg.is_topic_message).map(|thread| thread.0.0)
+}
+
+// A bot answer that a message replies to
+struct RepliedAnswer<'a> {
+    message_id: MessageId,
+    text: &'a str,
+}
+
+// The bot message this message replies to, None for replies to anyone else
+async fn replied_bot_answer<'a>(bot: &Bot, msg: &'a Message) -> ResponseResult<Option<RepliedAnswer<'a>>> {
+    let Some(reply) = msg.reply_to_message() else {
+        return Ok(None);
+    };
+    if !reply.from.as_ref().is_some_and(|user| user.is_bot) {
+        return Ok(None);
+    }
+    let me = bot.get_me().await?;
+    Ok(reply
+        .from
+        .as_ref()
+        .filter(|user| user.id == me.id)
+        .and_then(|_| reply.text())
+        .map(|text| RepliedAnswer { message_id: reply.id, text }))
+}
+
+// An AI reply and the history stored with it, which becomes the branch point for replies to it
+struct AiReply {
+    text: String,
+    history: Vec<ConversationMessage>,
+}
+
+// An AI reply, and the chat's model when a fallback model had to answer instead
+struct AiAnswer {
+    content: String,
+    model: String,
+    fallback_from: Option<String>,
+}
+
+// Send one request to the chat's AI model, enforcing budgets and recording token usage.
+// When the model fails, the next one from AI_FALLBACK_MODELS is tried
+async fn run_ai_request(
+    chat_id: &str,
+    user_id: u64,
+    locale: Option<&str>,
+    options: &ChatOptions,
+    history: &[ConversationMessage],
+    message: &str,
+) -> Result<AiAnswer, String> {
+    // Plugins reach the model through here too, so the kill switch is checked again
+    if !get_ai_enabled(chat_id).await {
+        return Err(render("ai.disabled", locale, context! {}));
+    }
+
+    if let BudgetStatus::Exceeded { scope, used, limit } = check_ai_budget(chat_id, user_id).await {
+        let scope = match scope {
+            BudgetScope::Chat => "chat",
+            BudgetScope::User => "user",
+        };
+        let resets_on = next_budget_reset().format("%Y-%m-%d").to_string();
+        return Err(render("error.budget", locale, context! { scope, used, limit, resets_on }));
+    }
+
+    let moderation = get_moderation_mode(chat_id).await;
+    if moderation != ModerationMode::Off {
+        match moderate_prompt(chat_id, message).await {
+            PromptModeration::Clean => {}
+            PromptModeration::Flagged(categories) => {
+                return Err(render("moderation.refused", locale, context! { categories => categories.join(", ") }));
+            }
+            PromptModeration::Unavailable => return Err(render("moderation.unavailable", locale, context! {})),
+        }
+    }
+
+    let current_model = get_current_model(chat_id).await;
+    // Replies only depend on the prompt when there is no history, so only those are cached
+    let cacheable = history.is_empty();
+    if cacheable
+        && let Some(content) = lookup_cached_reply(&current_model, options, message).await
+    {
+        let content = moderate_ai_reply(chat_id, locale, moderation, content).await?;
+        return Ok(AiAnswer {
+            content,
+            model: current_model,
+            fallback_from: None,
+        });
+    }
+
+    let mut first_error = None;
+    for model in get_model_chain(&current_model) {
+        info!("🔧 Using AI model: {model}");
+
+        let ai_backend = match create_ai_backend_with_model(&model) {
+            Ok(ai_backend) => ai_backend,
+            Err(e) => {
+                warn!("⚙️ AI backend configuration failed for chat {chat_id} with {model}: {e}");
+                first_error.get_or_insert_with(|| render("error.config", locale, context! { error => e.to_string() }));
+                continue;
+            }
+        };
+        info!("✅ AI backend created successfully with model: {model}");
+
+        // Fallback replies are neither cached nor shared so the chat's own model answers once it recovers
+        let primary = cacheable && model == current_model;
+        let call = async {
+            let started = std::time::Instant::now();
+            let result = match inject_ai_fault(&model).await {
+                Ok(()) => ai_backend.chat(options, history, message).await,
+                Err(e) => Err(e),
+            };
+            record_ai_call(&model, result.is_ok(), started.elapsed()).await;
+            let tokens = result.as_ref().ok().and_then(|reply| reply.total_tokens).unwrap_or(0);
+            record_provider_usage(QuotaSource::Ai(AiProvider::for_model(&model)), tokens).await;
+            let reply = result.map_err(|e| e.to_string())?;
+            // Requests sharing this call are not charged for it
+            if let Some(tokens) = reply.total_tokens {
+                info!("🧮 AI request used {tokens} tokens");
+                record_ai_tokens(chat_id, user_id, tokens).await;
+            }
+            info!("🤖 AI response from {model}: '{}'", reply.content);
+            if primary {
+                store_cached_reply(&model, options, message, &reply.content).await;
+            }
+            Ok::<_, String>(reply.content)
+        };
+        let result = if primary {
+            coalesce_reply(&model, options, message, call).await
+        } else {
+            call.await
+        };
+        match result {
+            Ok(content) => {
+                let content = moderate_ai_reply(chat_id, locale, moderation, content).await?;
+                let fallback_from = (model != current_model).then(|| current_model.clone());
+                return Ok(AiAnswer {
+                    content,
+                    model,
+                    fallback_from,
+                });
+            }
+            Err(e) => {
+                warn!("❌ AI request failed for chat {chat_id} with {model}: {e}");
+                first_error.get_or_insert_with(|| render("error.ai", locale, context! { error => e }));
+            }
+        }
+    }
+    // The chain always starts with the chat's model, so an error was recorded
+    Err(first_error.unwrap_or_default())
+}
+
+// Apply the chat's moderation mode to an AI reply, returning a user-facing error when it is withheld
+async fn moderate_ai_reply(
+    chat_id: &str,
+    locale: Option<&str>,
+    moderation: ModerationMode,
+    content: String,
+) -> Result<String, String> {
+    match moderation {
+        ModerationMode::Off => Ok(content),
+        mode => match moderate_reply(chat_id, &content, mode).await {
+            ReplyModeration::Clean => Ok(content),
+            ReplyModeration::Redacted(content) => Ok(content),
+            ReplyModeration::Withheld(categories) => {
+                Err(render("moderation.withheld", locale, context! { categories => categories.join(", ") }))
+            }
+            ReplyModeration::Unavailable => Err(render("moderation.unavailable", locale, context! {})),
+        },
+    }
+}
+
+// Run a message through the chat's AI model, persona and history, returning a user-facing error on failure.
+// `replied_to` is the bot answer the message replies to, if any
+async fn generate_ai_reply(
+    chat_id: &str,
+    user_id: u64,
+    locale: Option<&str>,
+    message: &str,
+    replied_to: Option<RepliedAnswer<'_>>,
+) -> Result<AiReply, String> {
+    check_ai_rate_limit(user_id, locale).await?;
+    let latest = load_conversation_history(chat_id).await;
+    // Replying to an earlier answer branches from the history as it stood then, replacing the latest thread
+    let branch = match &replied_to {
+        Some(answer) => load_history_snapshot(chat_id, answer.message_id.0)
+            .await
+            .filter(|snapshot| snapshot.last().map(|entry| &entry.timestamp) != latest.last().map(|entry| &entry.timestamp)),
+        None => None,
+    };
+    let branched = branch.is_some();
+    if branched {
+        info!("🌿 Branching the conversation in chat {chat_id} from an earlier answer");
+    }
+    let history = branch.unwrap_or(latest);
+    info!("🧠 Replaying {} history messages for chat {chat_id}", history.len());
+    // Explicit chat settings take precedence over the persona's temperature
+    let settings = get_ai_settings(chat_id).await;
+    let persona = get_chat_persona(chat_id).await;
+    let mut options = ChatOptions {
+        temperature: settings.temperature.or(persona.as_ref().and_then(|persona| persona.temperature)),
+        system_prompt: persona.map(|persona| persona.system_prompt),
+        max_tokens: settings.max_tokens,
+        top_p: settings.top_p,
+    };
+
+    // Facts taught with /teach go into the system prompt before budgeting, so compaction makes room for them
+    let knowledge = retrieve_knowledge(chat_id, message).await;
+    if !knowledge.is_empty() {
+        // Taught documents may come from anywhere, so they are fenced like any other third-party text
+        let context = format!(
+            "Facts this chat taught you, use them when relevant:\n{}\n\n{UNTRUSTED_CONTENT_NOTICE}",
+            knowledge.iter().map(|chunk| wrap_untrusted(chunk)).collect::<Vec<_>>().join("\n")
+        );
+        options.system_prompt = Some(match options.system_prompt {
+            Some(prompt) => format!("{prompt}\n\n{context}"),
+            None => context,
+        });
+    }
+
+    // Fold the oldest messages into the rolling summary once the history outgrows the context budget
+    let model = get_current_model(chat_id).await;
+    let max_tokens = options.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS);
+    let budget = history_token_budget(&model, options.system_prompt.as_deref(), message, max_tokens);
+    // A summary only exists alongside stored history, so skip the lookup when there is none.
+    // It may cover what came after a branch point, so a branch starts without one
+    let mut summary = if history.is_empty() || branched { None } else { load_conversation_summary(chat_id).await };
+    let (older, history) = split_for_compaction(&model, history, budget);
+    if !older.is_empty() {
+        info!("🗜️ Compacting {} history messages for chat {chat_id}", older.len());
+        let input = format_for_summary(summary.as_deref(), &older);
+        match run_instructed_request(chat_id, user_id, locale, SUMMARIZE_HISTORY_PROMPT, &input).await {
+            Ok(updated) => {
+                if let Err(e) = save_conversation_summary(chat_id, &updated).await {
+                    warn!("⚠️ Failed to save conversation summary for chat {chat_id}: {e}");
+                }
+                summary = Some(updated);
+            }
+            // The older messages are dropped either way, the previous summary stays in place
+            Err(e) => warn!("⚠️ Failed to summarize history for chat {chat_id}, truncating it: {e}"),
+        }
+    }
+    if let Some(summary) = summary {
+        let context = format!("Summary of the earlier conversation:\n{summary}");
+        options.system_prompt = Some(match options.system_prompt {
+            Some(prompt) => format!("{prompt}\n\n{context}"),
+            None => context,
+        });
+    }
+
+    // A reply to an earlier answer continues that exchange, even when it is no longer in the replayed history
+    let threaded;
+    let context = match replied_to.map(|answer| answer.text) {
+        Some(previous)
+            if !history
+                .iter()
+                .any(|entry| entry.role == ConversationRole::Assistant && entry.content.trim() == previous.trim()) =>
+        {
+            info!("🧵 Threading replied-to answer into the context for chat {chat_id}");
+            let previous = ConversationMessage::new(ConversationRole::Assistant, previous.to_string());
+            threaded = [history.as_slice(), &[previous]].concat();
+            threaded.as_slice()
+        }
+        _ => history.as_slice(),
+    };
+
+    let answer = run_ai_request(chat_id, user_id, locale, &options, context, message).await?;
+    let stored = match record_conversation_turn(chat_id, history, message, &answer.content).await {
+        Ok(stored) => stored,
+        Err(e) => {
+            warn!("⚠️ Failed to save conversation history for chat {chat_id}: {e}");
+            Vec::new()
+        }
+    };
+    // Only the visible reply mentions the fallback and carries the disclaimer, the stored history keeps the plain answer
+    let reply = match answer.fallback_from {
+        Some(preferred) => {
+            let note = render("note.fallback", locale, context! { model => answer.model, preferred });
+            format!("{}\n\n{note}", answer.content)
+        }
+        None => answer.content,
+    };
+    Ok(AiReply {
+        text: append_disclaimer(chat_id, locale, reply).await,
+        history: stored,
+    })
+}
+
+// Reserve one of the user's hourly AI requests, returning a user-facing error once they are used up
+async fn check_ai_rate_limit(user_id: u64, locale: Option<&str>) -> Result<(), String> {
+    match consume_ai_rate_limit(user_id).await {
+        RateLimitStatus::Allowed => Ok(()),
+        RateLimitStatus::Exceeded { limit, retry_in_minutes } => {
+            Err(render("error.rate_limited", locale, context! { limit, minutes => retry_in_minutes }))
+        }
+    }
+}
+
+// One-off AI task with its own instructions, outside the persona and conversation history
+pub(crate) async fn run_ai_task(
+    chat_id: &str,
+    user_id: u64,
+    locale: Option<&str>,
+    instructions: &str,
+    input: &str,
+)  -> Result<String, String> {
+    check_ai_rate_limit(user_id, locale).await?;
+    run_instructed_request(chat_id, user_id, locale, instructions, input).await
+}
+
+// Requests the bot makes on its own behalf, like history compaction, do not count against the rate limit
+async fn run_instructed_request(
+    chat_id: &str,
+    user_id: u64,
+    locale: Option<&str>,
+    instructions: &str,
+    input: &str,
+) -> Result<String, String> {
+    let settings = get_ai_settings(chat_id).await;
+    let options = ChatOptions {
+        system_prompt: Some(instructions.to_string()),
+        temperature: settings.temperature,
+        max_tokens: settings.max_tokens,
+        top_p: settings.top_p,
+    };
+    run_ai_request(chat_id, user_id, locale, &options, &[], input)
+        .await
+        .map(|answer| answer.content)
+}
+
+// Send an AI reply as text, or as a voice message falling back to text if speech synthesis fails
+async fn send_ai_reply(bot: &Bot, msg: &Message, response: String, as_voice: bool) -> ResponseResult<Message> {
+    if as_voice {
+        let _action = ChatActionKeepAlive::start(bot, msg.chat.id, ChatAction::RecordVoice);
+        match synthesize_speech(&response).await {
+            Ok(audio) => {
+                info!(
+                    "📤 Sending AI voice response to chat {} ({} bytes)",
+                    msg.chat.id,
+                    audio.len()
+                );
+                return bot
+                    .send_voice(msg.chat.id, InputFile::memory(audio).file_name("reply.ogg"))
+                    .await;
+            }
+            Err(e) => warn!("❌ Speech synthesis failed for chat {}, sending text: {e}", msg.chat.id),
+        }
+    }
+    info!(
+        "📤 Sending AI response to chat {} (length: {} chars)",
+        msg.chat.id,
+        response.len()
+    );
+    send_formatted(bot, msg.chat.id, response).await
+}
+
+// Send model output rendered as MarkdownV2, falling back to plain text if it cannot be converted or Telegram rejects it.
+// Every attempt is captured for /outbound, the only sends it covers since they are the ones built from model output
+async fn send_formatted(bot: &Bot, chat_id: ChatId, text: String) -> ResponseResult<Message> {
+    match markdown_to_telegram(&text) {
+        Ok(formatted) => {
+            let result = bot.send_message(chat_id, formatted.clone()).parse_mode(ParseMode::MarkdownV2).await;
+            capture_outbound(chat_id, &text, &formatted, Some(ParseMode::MarkdownV2), &result).await;
+            match result {
+                Ok(message) => return Ok(message),
+                Err(e) => warn!("⚠️ Telegram rejected the formatted reply for chat {chat_id}, sending plain text: {e}"),
+            }
+        }
+        Err(e) => warn!("⚠️ {e} for chat {chat_id}, sending plain text"),
+    }
+    let result = bot.send_message(chat_id, text.clone()).await;
+    capture_outbound(chat_id, &text, &text, None, &result).await;
+    result
+}
+
+fn format_timestamp(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+fn format_ai_settings(title: &str, settings: &AiSettings) -> String {
+    let temperature = settings
+        .temperature
+        .map_or("default (model or persona)".to_string(), |v| v.to_string());
+    let max_tokens = settings
+        .max_tokens
+        .map_or(format!("default ({DEFAULT_MAX_TOKENS})"), |v| v.to_string());
+    let top_p = settings.top_p.map_or("default".to_string(), |v| v.to_string());
+    format!("{title}\n\ntemperature: {temperature}\nmax_tokens: {max_tokens}\ntop_p: {top_p}")
 }
 
 pub async fn answer(bot: Bot, msg: Message, cmd: Command) -> ResponseResult<()> {
@@ -41,6 +588,7 @@ pub async fn answer(bot: Bot, msg: Message, cmd: Command) -> ResponseResult<()>
         .map(|s| s.as_str())
         .unwrap_or("<no_username>");
     let user_id = msg.from.as_ref().map(|user| user.id.0).unwrap_or(0);
+    let locale = msg.from.as_ref().and_then(|user| user.language_code.as_deref());
     let message_text = msg.text().unwrap_or("<no_text>");
 
     info!(
@@ -49,9 +597,15 @@ pub async fn answer(bot: Bot, msg: Message, cmd: Command) -> ResponseResult<()>
     );
     info!("💬 Processing command: {cmd:?}");
 
-    match cmd {
+    if cmd.uses_ai() && !get_ai_enabled(&msg.chat.id.to_string()).await {
+        info!("🚫 AI is turned off in chat {}, rejecting {cmd:?}", msg.chat.id);
+        bot.send_message(msg.chat.id, render("ai.disabled", locale, context! {})).await?;
+        return Ok(());
+    }
+
+    let sent = match cmd {
         Command::Help => {
-            let response = Command::descriptions().to_string();
+            let response = command_descriptions();
             info!("📤 Sending help response to chat {}", msg.chat.id);
             bot.send_message(msg.chat.id, response).await?
         }
@@ -84,46 +638,27 @@ pub async fn answer(bot: Bot, msg: Message, cmd: Command) -> ResponseResult<()>
                     "🤖 Processing AI request from chat {}: '{}'",
                     msg.chat.id, message
                 );
-                // Send typing indicator
-                bot.send_chat_action(msg.chat.id, teloxide::types::ChatAction::Typing)
-                    .await?;
+                // Keep the typing indicator up until the reply is sent
+                let _action = ChatActionKeepAlive::start(&bot, msg.chat.id, ChatAction::Typing);
 
                 let chat_id = msg.chat.id.to_string();
-                let current_model = get_current_model(&chat_id).await;
-                info!("🔧 Using AI model: {current_model}");
-
-                match create_ai_backend_with_model(&current_model) {
-                    Ok(ai_backend) => {
-                        info!("✅ AI backend created successfully with model: {current_model}");
-                        match ai_backend.chat(&message).await {
-                            Ok(response) => {
-                                info!(
-                                    "📤 Sending AI response to chat {} (length: {} chars)",
-                                    msg.chat.id,
-                                    response.len()
-                                );
-                                info!("🤖 AI response: '{response}'");
-                                bot.send_message(msg.chat.id, response).await?
-                            }
-                            Err(e) => {
-                                let error_msg = format!("AI Error: {e}");
-                                warn!("❌ AI request failed for chat {}: {}", msg.chat.id, e);
-                                info!(
-                                    "📤 Sending AI error response to chat {}: '{}'",
-                                    msg.chat.id, error_msg
-                                );
-                                bot.send_message(msg.chat.id, error_msg).await?
-                            }
-                        }
+                let replied_to = replied_bot_answer(&bot, &msg).await?;
+                let progress = TaskProgress::start(&bot, msg.chat.id, user_id, "Thinking");
+                let reply = progress
+                    .run(generate_ai_reply(&chat_id, user_id, locale, &message, replied_to))
+                    .await;
+                drop(progress);
+                match reply {
+                    None => bot.send_message(msg.chat.id, TASK_CANCELLED).await?,
+                    Some(Ok(reply)) => {
+                        let as_voice = get_voice_replies(&chat_id).await;
+                        let sent = send_ai_reply(&bot, &msg, reply.text, as_voice).await?;
+                        save_history_snapshot(&chat_id, sent.id.0, &reply.history).await;
+                        sent
                     }
-                    Err(e) => {
-                        let error_msg = format!("Configuration Error: {e}");
-                        warn!(
-                            "⚙️ AI backend configuration failed for chat {}: {}",
-                            msg.chat.id, e
-                        );
+                    Some(Err(error_msg)) => {
                         info!(
-                            "📤 Sending config error response to chat {}: '{}'",
+                            "📤 Sending AI error response to chat {}: '{}'",
                             msg.chat.id, error_msg
                         );
                         bot.send_message(msg.chat.id, error_msg).await?
@@ -138,7 +673,7 @@ pub async fn answer(bot: Bot, msg: Message, cmd: Command) -> ResponseResult<()>
                 "list" => {
                     let models = get_available_models();
                     let current = get_current_model(&chat_id).await;
-                    let mut response = format!("📋 Available AI models:\n\n");
+                    let mut response = "📋 Available AI models:\n\n".to_string();
                     for model in &models {
                         let indicator = if model == &current { "✅" } else { "  " };
                         response.push_str(&format!("{indicator} {model}\n"));
@@ -197,7 +732,1038 @@ pub async fn answer(bot: Bot, msg: Message, cmd: Command) -> ResponseResult<()>
                 }
             }
         }
+        Command::AiSettings(args) => {
+            let chat_id = msg.chat.id.to_string();
+            let args = args.trim();
+            let current = get_ai_settings(&chat_id).await;
+            let updated = match args.to_lowercase().as_str() {
+                "" => Ok(None),
+                "reset" => Ok(Some(AiSettings::default())),
+                _ => update_ai_settings(&current, args).map(Some),
+            };
+            let response = match updated {
+                Ok(None) => format_ai_settings("⚙️ AI settings for this chat:", &current),
+                Ok(Some(settings)) => match set_ai_settings(&chat_id, &settings).await {
+                    Ok(()) => {
+                        info!("⚙️ AI settings for chat {} updated: {settings:?}", msg.chat.id);
+                        format_ai_settings("✅ AI settings updated:", &settings)
+                    }
+                    Err(e) => {
+                        warn!("❌ Failed to save AI settings for chat {}: {e}", msg.chat.id);
+                        format!("❌ Failed to save AI settings: {e}")
+                    }
+                },
+                Err(e) => format!("❌ {e}\n\nUsage: /aisettings temperature=<0-2> max_tokens=<n> top_p=<0-1>, 'default' clears one value, '/aisettings reset' clears all."),
+            };
+            info!(
+                "📤 Sending AI settings response to chat {}: '{}'",
+                msg.chat.id, response
+            );
+            bot.send_message(msg.chat.id, response).await?
+        }
+        Command::Qr(content) => {
+            let content = content.trim();
+            if content.is_empty() {
+                let response = "Please provide the text or URL to encode, e.g. /qr https://example.com";
+                info!(
+                    "📤 Sending empty QR content help to chat {}: '{}'",
+                    msg.chat.id, response
+                );
+                bot.send_message(msg.chat.id, response).await?
+            } else {
+                match generate_qr_png(content) {
+                    Ok(png) => {
+                        info!("📤 Sending QR code photo to chat {}", msg.chat.id);
+                        let mut request = bot.send_photo(msg.chat.id, InputFile::memory(png).file_name("qr.png"));
+                        if let Some(effect) = message_effect(&msg.chat, Occasion::Done).await {
+                            request = request.message_effect_id(effect);
+                        }
+                        request.await?
+                    }
+                    Err(e) => {
+                        let response = format!("❌ Failed to generate QR code: {e}");
+                        warn!("❌ QR generation failed for chat {}: {e}", msg.chat.id);
+                        bot.send_message(msg.chat.id, response).await?
+                    }
+                }
+            }
+        }
+        Command::Convert(query) => {
+            let response = match convert(&query) {
+                Ok(conversion) => format!("🔁 {conversion}"),
+                Err(e) => {
+                    warn!("❌ Conversion failed for chat {}: {e}", msg.chat.id);
+                    format!("❌ {e}")
+                }
+            };
+            info!(
+                "📤 Sending conversion response to chat {}: '{}'",
+                msg.chat.id, response
+            );
+            bot.send_message(msg.chat.id, response).await?
+        }
+        Command::Risk(args) => {
+            let response = match calculate_position(&args) {
+                Ok(position) => format!("📐 {position}"),
+                Err(e) => {
+                    warn!("❌ Risk calculation failed for chat {}: {e}", msg.chat.id);
+                    format!("❌ {e}")
+                }
+            };
+            info!(
+                "📤 Sending risk response to chat {}: '{}'",
+                msg.chat.id, response
+            );
+            bot.send_message(msg.chat.id, response).await?
+        }
+        Command::Summarize(args) => {
+            let chat_id = msg.chat.id.to_string();
+            let args = args.trim();
+            let replied_text = msg
+                .reply_to_message()
+                .and_then(|reply| reply.text().or(reply.caption()))
+                .map(str::to_string);
+
+            // Started once there is work to show, the page fetch or the summary itself
+            let mut progress = None;
+
+            // A reply wins, then a link, then a message count in groups, then text given after the command
+            let page_url = if replied_text.is_none() { parse_page_url(args) } else { None };
+            let request = match (replied_text, page_url, args.parse::<usize>()) {
+                (Some(text), _, _) => Ok((SUMMARIZE_PROMPT, text)),
+                (None, Some(url), _) => {
+                    let _action = ChatActionKeepAlive::start(&bot, msg.chat.id, ChatAction::Typing);
+                    let progress = progress.insert(TaskProgress::start(&bot, msg.chat.id, user_id, "Summarizing"));
+                    progress.stage("Fetching the page…");
+                    match progress.run(fetch_page_text(&url)).await {
+                        Some(page) => page.map(|(title, text)| {
+                            let title = title.unwrap_or_else(|| url.to_string());
+                            (SUMMARIZE_PAGE_PROMPT, format!("Title: {title}\nURL: {url}\n\n{text}"))
+                        }),
+                        None => Err(TASK_CANCELLED.to_string()),
+                    }
+                }
+                (None, None, count) if !msg.chat.is_private() && (args.is_empty() || count.is_ok()) => {
+                    let cache_size = get_recent_message_cache_size();
+                    let count = count.unwrap_or(DEFAULT_SUMMARY_MESSAGES).clamp(1, cache_size.max(1));
+                    if cache_size == 0 {
+                        Err("Recent message caching is disabled, reply to a message with /summarize instead.".to_string())
+                    } else {
+                        match load_recent_transcript(&chat_id, count).await {
+                            Ok(Some(transcript)) => Ok((SUMMARIZE_CHAT_PROMPT, transcript)),
+                            Ok(None) => Err("No recent messages are cached for this chat yet. The bot only sees group messages when its privacy mode is disabled.".to_string()),
+                            Err(e) => {
+                                warn!("❌ Failed to load recent messages for chat {}: {e}", msg.chat.id);
+                                Err(format!("❌ Failed to load recent messages: {e}"))
+                            }
+                        }
+                    }
+                }
+                (None, None, _) if !args.is_empty() => Ok((SUMMARIZE_PROMPT, args.to_string())),
+                _ => Err("Reply to a message with /summarize, or send /summarize <text>.".to_string()),
+            };
+
+            match request {
+                Ok((instructions, text)) => {
+                    info!("📝 Summarizing {} chars for chat {}", text.len(), msg.chat.id);
+                    let _action = ChatActionKeepAlive::start(&bot, msg.chat.id, ChatAction::Typing);
+                    // Replied-to messages, pages and group transcripts are third-party text
+                    let instructions = guard_instructions(instructions);
+                    let progress =
+                        progress.unwrap_or_else(|| TaskProgress::start(&bot, msg.chat.id, user_id, "Summarizing"));
+                    progress.stage("Writing the summary…");
+                    let summary = progress
+                        .run(run_ai_task(&chat_id, user_id, locale, &instructions, &wrap_untrusted(&text)))
+                        .await;
+                    drop(progress);
+                    match summary {
+                        Some(Ok(summary)) => send_formatted(&bot, msg.chat.id, format!("📝 Summary:\n\n{summary}")).await?,
+                        Some(Err(error_msg)) => bot.send_message(msg.chat.id, error_msg).await?,
+                        None => bot.send_message(msg.chat.id, TASK_CANCELLED).await?,
+                    }
+                }
+                Err(response) => bot.send_message(msg.chat.id, response).await?,
+            }
+        }
+        Command::Translate(args) => {
+            let chat_id = msg.chat.id.to_string();
+            let (action, rest) = args.trim().split_once(char::is_whitespace).unwrap_or((args.trim(), ""));
+            if action.eq_ignore_ascii_case("set") {
+                let result = match rest.trim() {
+                    "default" | "off" => set_translate_language(&chat_id, None).await.map_err(|e| e.to_string()),
+                    language => match validate_language(language) {
+                        Ok(language) => set_translate_language(&chat_id, Some(&language))
+                            .await
+                            .map_err(|e| e.to_string()),
+                        Err(e) => Err(e),
+                    },
+                };
+                let response = match result {
+                    Ok(()) => format!("🌐 Translations in this chat now go to {}.", get_translate_language(&chat_id).await),
+                    Err(e) => {
+                        warn!("❌ Failed to set translation language for chat {}: {e}", msg.chat.id);
+                        format!("❌ {e}")
+                    }
+                };
+                bot.send_message(msg.chat.id, response).await?
+            } else {
+                let replied_text = msg
+                    .reply_to_message()
+                    .and_then(|reply| reply.text().or(reply.caption()))
+                    .map(str::to_string);
+                match parse_translate_args(&args) {
+                    Err(e) => bot.send_message(msg.chat.id, format!("❌ {e}")).await?,
+                    Ok((language, text)) => {
+                        // Text after the command wins over the replied-to message
+                        let text = if text.is_empty() { replied_text } else { Some(text.to_string()) };
+                        match text {
+                            None => {
+                                let response = format!(
+                                    "Usage: /translate [to:es] <text>, or reply to a message with /translate.\nSet this chat's default with /translate set <language>. Current default: {}",
+                                    get_translate_language(&chat_id).await
+                                );
+                                bot.send_message(msg.chat.id, response).await?
+                            }
+                            Some(text) => {
+                                let language = match language {
+                                    Some(language) => language,
+                                    None => get_translate_language(&chat_id).await,
+                                };
+                                info!("🌐 Translating {} chars to {language} for chat {}", text.len(), msg.chat.id);
+                                let _action = ChatActionKeepAlive::start(&bot, msg.chat.id, ChatAction::Typing);
+                                let input = format!("Target language: {language}\n\nText:\n{}", wrap_untrusted(&text));
+                                let instructions = guard_instructions(TRANSLATE_PROMPT);
+                                match run_ai_task(&chat_id, user_id, locale, &instructions, &input).await {
+                                    Ok(translation) => send_formatted(&bot, msg.chat.id, format!("🌐 {translation}")).await?,
+                                    Err(error_msg) => bot.send_message(msg.chat.id, error_msg).await?,
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Command::Search(query) => {
+            let chat_id = msg.chat.id.to_string();
+            let query = query.trim();
+            if query.is_empty() {
+                bot.send_message(msg.chat.id, "Please provide a question, e.g. /search who won the match last night?").await?
+            } else {
+                let _action = ChatActionKeepAlive::start(&bot, msg.chat.id, ChatAction::Typing);
+                let progress = TaskProgress::start(&bot, msg.chat.id, user_id, "Searching");
+                progress.stage("Searching the web…");
+                let results = progress.run(web_search(query)).await;
+                match results {
+                    None => bot.send_message(msg.chat.id, TASK_CANCELLED).await?,
+                    Some(Ok(results)) if results.is_empty() => {
+                        bot.send_message(msg.chat.id, "🔎 The search returned no results.").await?
+                    }
+                    Some(Ok(results)) => {
+                        let context = format_search_context(query, &results);
+                        let instructions = guard_instructions(SEARCH_PROMPT);
+                        progress.stage("Reading the results…");
+                        let answer = progress
+                            .run(run_ai_task(&chat_id, user_id, locale, &instructions, &context))
+                            .await;
+                        drop(progress);
+                        match answer {
+                            None => bot.send_message(msg.chat.id, TASK_CANCELLED).await?,
+                            Some(Ok(answer)) => {
+                                let reply = format!("🔎 {answer}\n\nSources:\n{}", format_sources(&results));
+                                send_formatted(&bot, msg.chat.id, reply).await?
+                            }
+                            Some(Err(error_msg)) => bot.send_message(msg.chat.id, error_msg).await?,
+                        }
+                    }
+                    Some(Err(e)) => {
+                        warn!("❌ Web search failed for chat {}: {e}", msg.chat.id);
+                        bot.send_message(msg.chat.id, format!("❌ Web search failed: {e}")).await?
+                    }
+                }
+            }
+        }
+        Command::Cancel => {
+            // Admins can stop any request in a group, everyone else only their own
+            let scope = if !msg.chat.is_private() && is_chat_admin(&bot, &msg).await? { None } else { Some(user_id) };
+            let response = match cancel_chat_tasks(msg.chat.id, scope).await {
+                Ok(0) => "Nothing is running that you can stop here.".to_string(),
+                Ok(1) => "⏹ Stopping the running request.".to_string(),
+                Ok(count) => format!("⏹ Stopping {count} running requests."),
+                Err(e) => {
+                    warn!("❌ Failed to stop running requests in chat {}: {e}", msg.chat.id);
+                    format!("❌ Failed to stop running requests: {e}")
+                }
+            };
+            bot.send_message(msg.chat.id, response).await?
+        }
+        Command::Clear => request_confirmation(&bot, &msg, user_id, DestructiveAction::ClearHistory).await?,
+        Command::ExportChat(args) => {
+            let chat_id = msg.chat.id.to_string();
+            match ExportFormat::parse(&args) {
+                None => bot.send_message(msg.chat.id, "❌ Unknown export format, use markdown or json.").await?,
+                Some(format) => match export_conversation(&chat_id, format).await {
+                    Ok(Some((file_name, contents))) => {
+                        info!("📤 Sending conversation export {file_name} to chat {}", msg.chat.id);
+                        bot.send_chat_action(msg.chat.id, ChatAction::UploadDocument).await?;
+                        bot.send_document(msg.chat.id, InputFile::memory(contents).file_name(file_name))
+                            .await?
+                    }
+                    Ok(None) => bot.send_message(msg.chat.id, "📭 There is no conversation history to export.").await?,
+                    Err(e) => {
+                        warn!("❌ Failed to export history for chat {}: {e}", msg.chat.id);
+                        bot.send_message(msg.chat.id, format!("❌ Failed to export conversation history: {e}")).await?
+                    }
+                },
+            }
+        }
+        // Destructive subcommands only run once the user confirms them with an inline button
+        Command::Ingest(args) if args.trim().starts_with("revoke ") => {
+            if !is_chat_admin(&bot, &msg).await? {
+                bot.send_message(msg.chat.id, "⛔ Only group administrators can manage inbound alert URLs.").await?
+            } else {
+                let token = args.trim()["revoke ".len()..].trim().to_string();
+                request_confirmation(&bot, &msg, user_id, DestructiveAction::RevokeIngest { token }).await?
+            }
+        }
+        Command::Teach(args) if args.trim() == "clear" => {
+            if !is_chat_admin(&bot, &msg).await? {
+                bot.send_message(msg.chat.id, "⛔ Only group administrators can change what the bot has been taught.").await?
+            } else {
+                request_confirmation(&bot, &msg, user_id, DestructiveAction::ClearKnowledge).await?
+            }
+        }
+        // Any member can opt in to alert mentions, but only for themselves
+        Command::Ingest(args) if args.split_whitespace().next() == Some("notify") => {
+            let chat_id = msg.chat.id.to_string();
+            let topic = forum_topic(&msg);
+            let username = msg.from.as_ref().and_then(|user| user.username.clone());
+            let response = match (args.split_whitespace().nth(1), username) {
+                (Some("on"), None) => "❌ Set a Telegram username first, alerts mention members by @username.".to_string(),
+                (Some(value @ ("on" | "off")), username) => {
+                    let enabled = value == "on";
+                    match set_alert_mention(&chat_id, topic, user_id, username.as_deref().unwrap_or_default(), enabled).await {
+                        Ok(()) if enabled => "🔔 You will be mentioned in alerts posted here. Stop with /ingest notify off".to_string(),
+                        Ok(()) => "🔕 You will no longer be mentioned in alerts posted here.".to_string(),
+                        Err(e) => {
+                            warn!("❌ Failed to update alert mention for chat {}: {e}", msg.chat.id);
+                            format!("❌ Failed to update alert mentions: {e}")
+                        }
+                    }
+                }
+                _ => match count_alert_mentions(&chat_id, topic).await {
+                    Ok(count) => format!("🔔 {count} member(s) are mentioned in alerts posted here. Use /ingest notify on|off to opt in or out."),
+                    Err(e) => format!("❌ Failed to load alert mentions: {e}"),
+                },
+            };
+            bot.send_message(msg.chat.id, response).await?
+        }
+        Command::Ingest(args) => {
+            let chat_id = msg.chat.id.to_string();
+            let topic = forum_topic(&msg);
+            let mut parts = args.split_whitespace();
+            let response = if !is_chat_admin(&bot, &msg).await? {
+                "⛔ Only group administrators can manage inbound alert URLs.".to_string()
+            } else {
+                match (parts.next(), parts.next()) {
+                    (Some("new"), None) => match create_ingest_token(&chat_id, topic, user_id).await {
+                        Ok(binding) => {
+                            info!("🔑 Created ingest token for chat {}", msg.chat.id);
+                            format!(
+                                "📥 New inbound alert URL for this chat:\n{}\n\nPOST JSON to it from Grafana, TradingView or any other system. Add ?source=grafana or ?source=tradingview to force a template.\nRevoke with /ingest revoke {}",
+                                ingest_url(&binding.token),
+                                binding.token
+                            )
+                        }
+                        Err(e) => {
+                            warn!("❌ Failed to create ingest token for chat {}: {e}", msg.chat.id);
+                            format!("❌ Failed to create inbound alert URL: {e}")
+                        }
+                    },
+                    (Some("github"), Some(repository)) if repository.split('/').count() == 2 => {
+                        match create_github_ingest_token(&chat_id, topic, user_id, repository).await {
+                            Ok(binding) => {
+                                info!("🔑 Created GitHub ingest token for {repository} in chat {}", msg.chat.id);
+                                let details = format!(
+                                    "🐙 GitHub webhook for {repository}:\n\nPayload URL: {}\nContent type: application/json\nSecret: {}\n\nEnable push, pull request, issue and release events.\nRevoke with /ingest revoke {}",
+                                    ingest_url(&binding.token),
+                                    binding.secret.as_deref().unwrap_or_default(),
+                                    binding.token
+                                );
+                                if msg.chat.is_private() {
+                                    details
+                                } else {
+                                    // The secret signs deliveries, so it never goes to a group, only to the admin who asked
+                                    match bot.send_message(ChatId(user_id as i64), details).await {
+                                        Ok(_) => format!(
+                                            "🐙 GitHub webhook for {repository} created, I sent you the secret in a private message.\nPayload URL: {}",
+                                            ingest_url(&binding.token)
+                                        ),
+                                        Err(e) => {
+                                            warn!("⚠️ Failed to send the GitHub webhook secret to user {user_id}: {e}");
+                                            if let Err(e) = revoke_ingest_token(&binding.token, &chat_id).await {
+                                                warn!("❌ Failed to revoke undelivered GitHub ingest token for chat {}: {e}", msg.chat.id);
+                                            }
+                                            "❌ I couldn't send you the webhook secret privately. Start a private chat with me, then run /ingest github again.".to_string()
+                                        }
+                                    }
+                                }
+                            }
+                            Err(e) => {
+                                warn!("❌ Failed to create GitHub ingest token for chat {}: {e}", msg.chat.id);
+                                format!("❌ Failed to create GitHub webhook URL: {e}")
+                            }
+                        }
+                    }
+                    (Some("digest"), None) => match get_alert_digest_window(&chat_id, topic).await {
+                        Ok(0) => "🔔 Alerts are delivered one message each. Combine bursts with /ingest digest <seconds>".to_string(),
+                        Ok(seconds) => format!("🗂️ Alerts arriving within {seconds}s of the first one are combined into a single message."),
+                        Err(e) => format!("❌ Failed to load alert digest setting: {e}"),
+                    },
+                    (Some("digest"), Some(value)) => {
+                        let seconds = if value.eq_ignore_ascii_case("off") { Some(0) } else { value.parse::<u64>().ok() };
+                        match seconds {
+                            Some(seconds) if seconds <= 24 * 60 * 60 => match set_alert_digest_window(&chat_id, topic, seconds).await {
+                                Ok(()) if seconds == 0 => "🔔 Alert digests turned off.".to_string(),
+                                Ok(()) => format!("🗂️ Alerts arriving within {seconds}s of the first one will be combined into a single message."),
+                                Err(e) => {
+                                    warn!("❌ Failed to set alert digest window for chat {}: {e}", msg.chat.id);
+                                    format!("❌ Failed to save alert digest setting: {e}")
+                                }
+                            },
+                            _ => "❌ Digest window must be a number of seconds up to 86400, or off.".to_string(),
+                        }
+                    }
+                    _ => "Usage:\n/ingest new - create an inbound alert URL for this chat\n/ingest github <owner/repo> - create a signed GitHub webhook URL\n/ingest revoke <token> - revoke one\n/ingest digest <seconds|off> - combine alerts arriving within a window into one message\n/ingest notify on|off - get mentioned when alerts arrive".to_string(),
+                }
+            };
+            bot.send_message(msg.chat.id, response).await?
+        }
+        Command::Teach(args) => {
+            let chat_id = msg.chat.id.to_string();
+            let args = args.trim();
+            let mut parts = args.splitn(2, char::is_whitespace);
+            let response = match (parts.next().unwrap_or(""), parts.next().map(str::trim)) {
+                ("list", None) => match list_knowledge(&chat_id).await {
+                    Ok(lines) if lines.is_empty() => "📚 Nothing has been taught in this chat yet.".to_string(),
+                    Ok(lines) => format!("📚 Knowledge for this chat:\n\n{}", lines.join("\n")),
+                    Err(e) => {
+                        warn!("❌ Failed to list knowledge for chat {}: {e}", msg.chat.id);
+                        format!("❌ Failed to load knowledge: {e}")
+                    }
+                },
+                _ if !is_chat_admin(&bot, &msg).await? => {
+                    "⛔ Only group administrators can change what the bot has been taught.".to_string()
+                }
+                ("forget", Some(id)) => match forget(&chat_id, id).await {
+                    Ok(true) => format!("🗑️ Forgot entry {id}."),
+                    Ok(false) => format!("❌ No entry with id {id}, see /teach list."),
+                    Err(e) => {
+                        warn!("❌ Failed to forget knowledge for chat {}: {e}", msg.chat.id);
+                        format!("❌ Failed to update knowledge: {e}")
+                    }
+                },
+                ("forget", None) => "Usage: /teach forget <id>, see /teach list for ids.".to_string(),
+                _ => {
+                    // Text after the command wins, then a replied-to text document, then the replied-to message
+                    let reply = msg.reply_to_message();
+                    let source = match (args, reply.and_then(|reply| reply.document())) {
+                        (text, _) if !text.is_empty() => Ok(Some(("message".to_string(), text.to_string()))),
+                        (_, Some(document)) => {
+                            let name = document.file_name.clone().unwrap_or_else(|| "document".to_string());
+                            let lower = name.to_lowercase();
+                            if !(lower.ends_with(".txt") || lower.ends_with(".md")) {
+                                Err("❌ Only .txt and .md documents can be taught.".to_string())
+                            } else if document.file.size > MAX_DOCUMENT_BYTES {
+                                Err(format!("❌ Documents are limited to {} KB.", MAX_DOCUMENT_BYTES / 1024))
+                            } else {
+                                let file = bot.get_file(document.file.id.clone()).await?;
+                                let mut bytes = Vec::new();
+                                match teloxide::net::Download::download_file(&bot, &file.path, &mut bytes).await {
+                                    Ok(()) => match String::from_utf8(bytes) {
+                                        Ok(text) => Ok(Some((name, text))),
+                                        Err(_) => Err("❌ The document is not UTF-8 text.".to_string()),
+                                    },
+                                    Err(e) => {
+                                        warn!("❌ Failed to download document for chat {}: {e}", msg.chat.id);
+                                        Err(format!("❌ Failed to download the document: {e}"))
+                                    }
+                                }
+                            }
+                        }
+                        _ => Ok(reply
+                            .and_then(|reply| reply.text().or(reply.caption()))
+                            .map(|text| ("message".to_string(), text.to_string()))),
+                    };
+
+                    match source {
+                        Ok(Some((source, text))) => {
+                            let _action = ChatActionKeepAlive::start(&bot, msg.chat.id, ChatAction::Typing);
+                            match teach(&chat_id, &source, &text, user_id).await {
+                                Ok((id, chunks)) => format!("📚 Learned {chunks} chunk(s) as entry {id}."),
+                                Err(e) => {
+                                    warn!("❌ Failed to teach chat {}: {e}", msg.chat.id);
+                                    format!("❌ {e}")
+                                }
+                            }
+                        }
+                        Ok(None) => "Usage:\n/teach <text> - teach a fact\nReply to a message or .txt/.md file with /teach - teach its contents\n/teach list - show what was taught\n/teach forget <id> - remove an entry\n/teach clear - remove everything".to_string(),
+                        Err(error_msg) => error_msg,
+                    }
+                }
+            };
+            bot.send_message(msg.chat.id, response).await?
+        }
+        Command::Persona(args) => {
+            let chat_id = msg.chat.id.to_string();
+            let args = args.trim();
+            let (action, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+            let rest = rest.trim();
+
+            let response = match action.to_lowercase().as_str() {
+                "" | "list" => match list_chat_personas(&chat_id).await {
+                    Ok((personas, active)) => {
+                        let mut response = "🎭 Available personas:\n\n".to_string();
+                        for persona in &personas {
+                            let indicator = if active.as_deref() == Some(persona.name.as_str()) { "✅" } else { "  " };
+                            response.push_str(&format!("{indicator} {} - {}\n", persona.name, persona.description));
+                        }
+                        response.push_str(&format!(
+                            "\nCurrent persona: {}\nUse /persona <name> to switch or /persona off to reset.",
+                            active.as_deref().unwrap_or("none")
+                        ));
+                        response
+                    }
+                    Err(e) => format!("❌ Failed to load personas: {e}"),
+                },
+                "off" | "none" | "default" => match set_chat_persona(&chat_id, None).await {
+                    Ok(_) => "🎭 Persona cleared - back to the model's default behaviour.".to_string(),
+                    Err(e) => format!("❌ Failed to clear persona: {e}"),
+                },
+                "add" => match rest.split_once(char::is_whitespace) {
+                    Some((name, prompt)) if !prompt.trim().is_empty() => {
+                        match add_custom_persona(&chat_id, name, prompt).await {
+                            Ok(()) => format!("✅ Persona '{}' saved. Use /persona {} to activate it.", name.to_lowercase(), name.to_lowercase()),
+                            Err(e) => format!("❌ {e}"),
+                        }
+                    }
+                    _ => "Usage: /persona add <name> <system prompt>".to_string(),
+                },
+                "remove" if !rest.is_empty() => match remove_custom_persona(&chat_id, rest).await {
+                    Ok(true) => format!("🗑️ Persona '{}' removed.", rest.to_lowercase()),
+                    Ok(false) => format!("❌ No custom persona named '{}'.", rest.to_lowercase()),
+                    Err(e) => format!("❌ {e}"),
+                },
+                _ => match set_chat_persona(&chat_id, Some(args)).await {
+                    Ok(Some(persona)) => {
+                        info!("🎭 Persona for chat {} set to {}", msg.chat.id, persona.name);
+                        format!("🎭 Persona switched to {} - {}", persona.name, persona.description)
+                    }
+                    Ok(None) => "🎭 Persona cleared.".to_string(),
+                    Err(e) => {
+                        warn!("❌ Failed to set persona for chat {}: {e}", msg.chat.id);
+                        format!("❌ {e}\n\nUse /persona list to see available personas.")
+                    }
+                },
+            };
+            info!(
+                "📤 Sending persona response to chat {}: '{}'",
+                msg.chat.id, response
+            );
+            bot.send_message(msg.chat.id, response).await?
+        }
+        Command::Imagine(args) => match parse_image_request(&args) {
+            Err(usage) => bot.send_message(msg.chat.id, usage).await?,
+            Ok((options, prompt)) => match consume_daily_image_quota(user_id).await {
+                QuotaStatus::Exceeded { limit } => {
+                    let response = format!("⛔ You have used all {limit} image generations for today. The quota resets at 00:00 UTC.");
+                    bot.send_message(msg.chat.id, response).await?
+                }
+                quota => {
+                    info!("🎨 Processing image request from chat {}: '{prompt}'", msg.chat.id);
+                    let _action = ChatActionKeepAlive::start(&bot, msg.chat.id, ChatAction::UploadPhoto);
+                    let progress = TaskProgress::start(&bot, msg.chat.id, user_id, "Creating your image");
+                    progress.stage("Generating the image…");
+
+                    match progress.run(generate_image(&prompt, &options)).await {
+                        None => {
+                            refund_daily_image_quota(user_id).await;
+                            bot.send_message(msg.chat.id, TASK_CANCELLED).await?
+                        }
+                        Some(Ok(url)) => match url.parse() {
+                            Ok(url) => {
+                                let caption = match quota {
+                                    QuotaStatus::Allowed { used, limit } => format!("🎨 {prompt}\n\n({used}/{limit} images today)"),
+                                    _ => format!("🎨 {prompt}"),
+                                };
+                                info!("📤 Sending generated image to chat {}", msg.chat.id);
+                                progress.stage("Uploading…");
+                                let mut request = bot.send_photo(msg.chat.id, InputFile::url(url)).caption(caption);
+                                if let Some(effect) = message_effect(&msg.chat, Occasion::Created).await {
+                                    request = request.message_effect_id(effect);
+                                }
+                                request.await?
+                            }
+                            Err(e) => {
+                                refund_daily_image_quota(user_id).await;
+                                warn!("❌ Image API returned an invalid URL for chat {}: {e}", msg.chat.id);
+                                bot.send_message(msg.chat.id, format!("❌ Image generation failed: {e}")).await?
+                            }
+                        },
+                        Some(Err(e)) => {
+                            refund_daily_image_quota(user_id).await;
+                            warn!("❌ Image generation failed for chat {}: {e}", msg.chat.id);
+                            bot.send_message(msg.chat.id, format!("❌ Image generation failed: {e}")).await?
+                        }
+                    }
+                }
+            },
+        },
+        Command::Speak(args) => {
+            let chat_id = msg.chat.id.to_string();
+            match args.trim().to_lowercase().as_str() {
+                "" => {
+                    let status = if get_voice_replies(&chat_id).await { "on" } else { "off" };
+                    let response = format!(
+                        "🔊 Voice replies are {status} for this chat.\n\nUse /speak <message> for a single spoken reply or /speak on|off to voice every AI reply."
+                    );
+                    bot.send_message(msg.chat.id, response).await?
+                }
+                setting @ ("on" | "off") => {
+                    let enabled = setting == "on";
+                    let response = match set_voice_replies(&chat_id, enabled).await {
+                        Ok(()) => {
+                            info!("🔊 Voice replies for chat {} set to {setting}", msg.chat.id);
+                            format!("🔊 Voice replies turned {setting} for this chat.")
+                        }
+                        Err(e) => {
+                            warn!("❌ Failed to save voice reply setting for chat {}: {e}", msg.chat.id);
+                            format!("❌ Failed to save voice reply setting: {e}")
+                        }
+                    };
+                    bot.send_message(msg.chat.id, response).await?
+                }
+                _ => {
+                    info!("🔊 Processing spoken AI request from chat {}: '{args}'", msg.chat.id);
+                    let _action = ChatActionKeepAlive::start(&bot, msg.chat.id, ChatAction::Typing);
+                    let progress = TaskProgress::start(&bot, msg.chat.id, user_id, "Thinking");
+                    let reply = progress.run(generate_ai_reply(&chat_id, user_id, locale, args.trim(), None)).await;
+                    drop(progress);
+                    match reply {
+                        None => bot.send_message(msg.chat.id, TASK_CANCELLED).await?,
+                        Some(Ok(reply)) => send_ai_reply(&bot, &msg, reply.text, true).await?,
+                        Some(Err(error_msg)) => bot.send_message(msg.chat.id, error_msg).await?,
+                    }
+                }
+            }
+        }
+        Command::Budget(args) => {
+            let chat_id = msg.chat.id.to_string();
+            let mut parts = args.split_whitespace();
+            let response = match (parts.next(), parts.next(), parts.next()) {
+                (None, _, _) => match get_budget_report(&chat_id, user_id).await {
+                    Ok(report) => {
+                        let cap = |limit: Option<u64>| limit.map_or("no cap".to_string(), |limit| format!("cap {limit}"));
+                        format!(
+                            "🧮 AI token usage this month:\n\nThis chat: {} ({})\nYou: {} ({} per user)\n\nBudgets reset on {}.",
+                            report.chat_used,
+                            cap(report.budget.chat_tokens),
+                            report.user_used,
+                            cap(report.budget.user_tokens),
+                            next_budget_reset().format("%Y-%m-%d")
+                        )
+                    }
+                    Err(e) => {
+                        warn!("❌ Failed to load AI budget for chat {}: {e}", msg.chat.id);
+                        format!("❌ Failed to load AI usage: {e}")
+                    }
+                },
+                (Some(scope @ ("chat" | "group" | "user")), Some(amount), None) => {
+                    let limit = match amount {
+                        "off" => Ok(None),
+                        amount => amount.parse::<u64>().map(Some).map_err(|_| ()),
+                    };
+                    if !is_chat_admin(&bot, &msg).await? {
+                        "⛔ Only group administrators can change AI budgets.".to_string()
+                    } else if let Ok(limit) = limit {
+                        match get_ai_budget(&chat_id).await {
+                            Ok(mut budget) => {
+                                if scope == "user" {
+                                    budget.user_tokens = limit;
+                                } else {
+                                    budget.chat_tokens = limit;
+                                }
+                                match set_ai_budget(&chat_id, &budget).await {
+                                    Ok(()) => {
+                                        info!("🧮 AI budget for chat {} updated: {budget:?}", msg.chat.id);
+                                        let target = if scope == "user" { "Each user" } else { "This chat" };
+                                        match limit {
+                                            Some(limit) => format!("✅ {target} can now use {limit} AI tokens per month."),
+                                            None => format!("✅ {target} no longer has a monthly AI token cap."),
+                                        }
+                                    }
+                                    Err(e) => {
+                                        warn!("❌ Failed to save AI budget for chat {}: {e}", msg.chat.id);
+                                        format!("❌ Failed to save AI budget: {e}")
+                                    }
+                                }
+                            }
+                            Err(e) => format!("❌ Failed to load AI budget: {e}"),
+                        }
+                    } else {
+                        format!("❌ '{amount}' is not a token amount. Use a whole number such as 200000, or 'off'.")
+                    }
+                }
+                _ => "Usage:\n/budget - show this month's AI token usage\n/budget chat <tokens|off> - cap the whole chat\n/budget user <tokens|off> - cap each user in this chat".to_string(),
+            };
+            info!(
+                "📤 Sending budget response to chat {}: '{}'",
+                msg.chat.id, response
+            );
+            bot.send_message(msg.chat.id, response).await?
+        }
+        Command::Moderation(args) => {
+            let chat_id = msg.chat.id.to_string();
+            let args = args.trim();
+            let response = if args.is_empty() {
+                format!(
+                    "🛡️ Moderation for this chat: {}\n\nUse /moderation off|refuse|redact. 'refuse' blocks flagged prompts and replies, 'redact' blocks flagged prompts and removes flagged paragraphs from replies.",
+                    get_moderation_mode(&chat_id).await
+                )
+            } else if let Some(mode) = ModerationMode::parse(args) {
+                if !is_chat_admin(&bot, &msg).await? {
+                    "⛔ Only group administrators can change moderation.".to_string()
+                } else if let Err(e) = validate_moderation_mode(mode) {
+                    format!("❌ {e}")
+                } else {
+                    match set_moderation_mode(&chat_id, mode).await {
+                        Ok(()) => {
+                            info!("🛡️ Moderation for chat {} set to {mode}", msg.chat.id);
+                            format!("🛡️ Moderation for this chat set to {mode}.")
+                        }
+                        Err(e) => {
+                            warn!("❌ Failed to save moderation mode for chat {}: {e}", msg.chat.id);
+                            format!("❌ Failed to save moderation mode: {e}")
+                        }
+                    }
+                }
+            } else {
+                format!("❌ Unknown moderation mode '{args}', use off, refuse or redact.")
+            };
+            info!(
+                "📤 Sending moderation response to chat {}: '{}'",
+                msg.chat.id, response
+            );
+            bot.send_message(msg.chat.id, response).await?
+        }
+        Command::Disclaimer(args) => {
+            let chat_id = msg.chat.id.to_string();
+            let args = args.trim();
+            let response = if args.is_empty() {
+                match get_chat_disclaimer(&chat_id).await {
+                    Some(text) => format!("ℹ️ AI replies and alerts in this chat end with:\n\n{text}\n\nUse /disclaimer <text>, /disclaimer off or /disclaimer reset."),
+                    None => "ℹ️ No disclaimer is added in this chat. Set one with /disclaimer <text>.".to_string(),
+                }
+            } else if !is_chat_admin(&bot, &msg).await? {
+                "⛔ Only group administrators can change the disclaimer.".to_string()
+            } else {
+                let text = match args.to_lowercase().as_str() {
+                    "reset" => None,
+                    "off" => Some("off"),
+                    _ => Some(args),
+                };
+                match set_chat_disclaimer(&chat_id, text).await {
+                    Ok(()) => {
+                        info!("ℹ️ Disclaimer for chat {} updated", msg.chat.id);
+                        match text {
+                            None => "ℹ️ Disclaimer reset to the bot's default.".to_string(),
+                            Some("off") => "ℹ️ Disclaimer turned off for this chat.".to_string(),
+                            Some(_) => "ℹ️ Disclaimer saved.".to_string(),
+                        }
+                    }
+                    Err(e) => {
+                        warn!("❌ Failed to save disclaimer for chat {}: {e}", msg.chat.id);
+                        format!("❌ Failed to save disclaimer: {e}")
+                    }
+                }
+            };
+            bot.send_message(msg.chat.id, response).await?
+        }
+        Command::AiStatus => {
+            let response = if !is_chat_admin(&bot, &msg).await? {
+                "⛔ Only group administrators can check AI backend status.".to_string()
+            } else {
+                let _action = ChatActionKeepAlive::start(&bot, msg.chat.id, ChatAction::Typing);
+                let status = format_ai_status(&get_current_model(&msg.chat.id.to_string()).await).await;
+                match format_quota_report().await {
+                    Ok(quotas) => format!("{status}\n\n{quotas}"),
+                    Err(e) => {
+                        warn!("⚠️ Failed to load provider usage for /aistatus: {e}");
+                        status
+                    }
+                }
+            };
+            bot.send_message(msg.chat.id, response).await?
+        }
+        Command::GroupConfig(args) => {
+            let chat_id = msg.chat.id.to_string();
+            let mut parts = args.split_whitespace();
+            let response = match (parts.next(), parts.next()) {
+                (None, _) => {
+                    let ai = if get_ai_enabled(&chat_id).await { "on" } else { "off" };
+                    let autodelete = get_autodelete(&chat_id)
+                        .await
+                        .map_or("off".to_string(), |seconds| format!("after {}", format_duration(seconds)));
+                    let silent = match get_silent_deliveries(&chat_id).await {
+                        Ok(silent) if silent.is_empty() => "none".to_string(),
+                        Ok(silent) => silent.iter().map(|delivery| delivery.name()).collect::<Vec<_>>().join(", "),
+                        Err(e) => format!("unknown ({e})"),
+                    };
+                    let flair = if get_flair_enabled(&chat_id).await { "on" } else { "off" };
+                    format!(
+                        "⚙️ Settings for this chat:\n\nai: {ai}\nautodelete: {autodelete}\nsilent: {silent}\nflair: {flair}\n\nChange with /groupconfig ai on|off, /groupconfig autodelete <duration|off>, /groupconfig silent <alerts|digests> on|off or /groupconfig flair on|off"
+                    )
+                }
+                (Some("flair"), Some(value @ ("on" | "off"))) => {
+                    if !is_chat_admin(&bot, &msg).await? {
+                        "⛔ Only group administrators can change chat settings.".to_string()
+                    } else {
+                        match set_flair_enabled(&chat_id, value == "on").await {
+                            Ok(()) if msg.chat.is_private() => format!("✨ Flair turned {value}."),
+                            Ok(()) => format!("✨ Flair turned {value}. Telegram only shows message effects in private chats."),
+                            Err(e) => {
+                                warn!("❌ Failed to save flair setting for chat {}: {e}", msg.chat.id);
+                                format!("❌ Failed to save chat setting: {e}")
+                            }
+                        }
+                    }
+                }
+                (Some("silent"), Some(delivery)) => {
+                    let value = parts.next();
+                    match (DeliveryType::parse(delivery), value) {
+                        (Some(delivery), Some(value @ ("on" | "off"))) => {
+                            if !is_chat_admin(&bot, &msg).await? {
+                                "⛔ Only group administrators can change chat settings.".to_string()
+                            } else {
+                                match set_delivery_silent(&chat_id, delivery, value == "on").await {
+                                    Ok(()) if value == "on" => format!("🔕 {} will be delivered without a notification sound.", delivery.name()),
+                                    Ok(()) => format!("🔔 {} will notify as usual.", delivery.name()),
+                                    Err(e) => {
+                                        warn!("❌ Failed to save silent deliveries for chat {}: {e}", msg.chat.id);
+                                        format!("❌ Failed to save chat setting: {e}")
+                                    }
+                                }
+                            }
+                        }
+                        _ => "Usage: /groupconfig silent <alerts|digests> on|off".to_string(),
+                    }
+                }
+                (Some("autodelete"), Some(value)) => {
+                    let seconds = if value == "off" {
+                        Ok(None)
+                    } else {
+                        parse_duration(value).and_then(validate_autodelete).map(Some)
+                    };
+                    if !is_chat_admin(&bot, &msg).await? {
+                        "⛔ Only group administrators can change chat settings.".to_string()
+                    } else {
+                        match seconds {
+                            Ok(seconds) => match set_autodelete(&chat_id, seconds).await {
+                                Ok(()) => {
+                                    info!("⚙️ Autodelete for chat {} set to {seconds:?}", msg.chat.id);
+                                    match seconds {
+                                        Some(seconds) => format!("🧽 The bot's replies in this chat will be deleted after {}.", format_duration(seconds)),
+                                        None => "⚙️ Auto-delete turned off for this chat.".to_string(),
+                                    }
+                                }
+                                Err(e) => {
+                                    warn!("❌ Failed to save autodelete setting for chat {}: {e}", msg.chat.id);
+                                    format!("❌ Failed to save chat setting: {e}")
+                                }
+                            },
+                            Err(e) => format!("❌ {e}"),
+                        }
+                    }
+                }
+                (Some("ai"), Some(value @ ("on" | "off"))) => {
+                    if !is_chat_admin(&bot, &msg).await? {
+                        "⛔ Only group administrators can change chat settings.".to_string()
+                    } else {
+                        let enabled = value == "on";
+                        match set_ai_enabled(&chat_id, enabled).await {
+                            Ok(()) => {
+                                info!("⚙️ AI for chat {} turned {value}", msg.chat.id);
+                                format!("⚙️ AI features turned {value} for this chat.")
+                            }
+                            Err(e) => {
+                                warn!("❌ Failed to save AI setting for chat {}: {e}", msg.chat.id);
+                                format!("❌ Failed to save chat setting: {e}")
+                            }
+                        }
+                    }
+                }
+                _ => "Usage:\n/groupconfig - show chat settings\n/groupconfig ai on|off - turn all AI features on or off\n/groupconfig autodelete <duration|off> - delete the bot's replies after e.g. 30m or 2h\n/groupconfig silent <alerts|digests> on|off - deliver alerts or digests without a sound\n/groupconfig flair on|off - add message effects in private chats".to_string(),
+            };
+            bot.send_message(msg.chat.id, response).await?
+        }
+        Command::Set(args) => {
+            let chat_id = msg.chat.id.to_string();
+            let (key, value) = args.trim().split_once(char::is_whitespace).unwrap_or((args.trim(), ""));
+            let response = match ChatSetting::parse(key) {
+                None => format!("Unknown setting '{key}'. Use /get to list the settings."),
+                Some(setting) if value.trim().is_empty() => format!("Usage: {}", setting.usage()),
+                Some(setting) if setting.admin_only() && !is_chat_admin(&bot, &msg).await? => {
+                    "⛔ Only group administrators can change chat settings.".to_string()
+                }
+                Some(setting) => match setting.write(&chat_id, value).await {
+                    Ok(value) => {
+                        info!("⚙️ {} for chat {} set to {value}", setting.key(), msg.chat.id);
+                        format!("⚙️ {} is now {value}.", setting.key())
+                    }
+                    Err(e) => {
+                        warn!("❌ Failed to set {} for chat {}: {e}", setting.key(), msg.chat.id);
+                        format!("❌ {e}\nUsage: {}", setting.usage())
+                    }
+                },
+            };
+            bot.send_message(msg.chat.id, response).await?
+        }
+        Command::Get(args) => {
+            let chat_id = msg.chat.id.to_string();
+            let key = args.trim();
+            let response = if key.is_empty() {
+                let mut lines = Vec::new();
+                for setting in ChatSetting::ALL {
+                    let value = setting.read(&chat_id).await.unwrap_or_else(|e| format!("unknown ({e})"));
+                    lines.push(format!("{}: {value}", setting.key()));
+                }
+                format!("⚙️ Settings for this chat:\n\n{}\n\nChange one with /set <key> <value>, or /get <key> for details.", lines.join("\n"))
+            } else {
+                match ChatSetting::parse(key) {
+                    None => format!("Unknown setting '{key}'. Use /get to list the settings."),
+                    Some(setting) => {
+                        let value = setting.read(&chat_id).await.unwrap_or_else(|e| format!("unknown ({e})"));
+                        let who = if setting.admin_only() { "admins" } else { "anyone" };
+                        format!(
+                            "⚙️ {}: {value}\n\n{}, default {}. Changed by {who} with {}",
+                            setting.key(),
+                            setting.description(),
+                            setting.default_value(),
+                            setting.usage()
+                        )
+                    }
+                }
+            };
+            bot.send_message(msg.chat.id, response).await?
+        }
+        Command::Pause(args) => {
+            let response = if args.trim().is_empty() {
+                match get_paused_until(msg.chat.id).await {
+                    Some(until) => format!("⏸️ Paused until {} UTC. End it early with /resume", format_timestamp(until)),
+                    None => "▶️ Not paused. Mute alerts and unprompted replies with /pause <duration>, e.g. /pause 2h".to_string(),
+                }
+            } else if !is_chat_admin(&bot, &msg).await? {
+                "⛔ Only group administrators can pause the bot.".to_string()
+            } else {
+                match parse_pause_duration(&args) {
+                    Ok(duration) => match pause_chat(&bot, msg.chat.id, duration).await {
+                        Ok(until) => format!(
+                            "⏸️ Paused until {} UTC. Alerts are dropped and only mentions and commands get an answer. End it early with /resume",
+                            format_timestamp(until)
+                        ),
+                        Err(e) => {
+                            warn!("❌ Failed to pause chat {}: {e}", msg.chat.id);
+                            format!("❌ Failed to pause: {e}")
+                        }
+                    },
+                    Err(e) => format!("❌ {e}"),
+                }
+            };
+            bot.send_message(msg.chat.id, response).await?
+        }
+        Command::Resume => {
+            let response = if !is_chat_admin(&bot, &msg).await? {
+                "⛔ Only group administrators can resume the bot.".to_string()
+            } else {
+                match resume_chat(msg.chat.id).await {
+                    Ok(true) => "▶️ Resumed, alerts and replies are back on.".to_string(),
+                    Ok(false) => "▶️ The bot was not paused.".to_string(),
+                    Err(e) => {
+                        warn!("❌ Failed to resume chat {}: {e}", msg.chat.id);
+                        format!("❌ Failed to resume: {e}")
+                    }
+                }
+            };
+            bot.send_message(msg.chat.id, response).await?
+        }
+        Command::Privacy => {
+            let cache_size = get_recent_message_cache_size();
+            let recent = if cache_size == 0 {
+                "Group messages are not cached.".to_string()
+            } else {
+                format!("Up to {cache_size} recent group messages per chat are cached for /summarize.")
+            };
+            let transcripts = if transcript_logging_enabled() {
+                format!(
+                    "Commands and the bot's responses are recorded in an encrypted transcript and kept for {} days for compliance.",
+                    get_transcript_retention_days()
+                )
+            } else {
+                "Transcript logging is off.".to_string()
+            };
+            let outbound = if outbound_debug_enabled() {
+                format!("\n\nThe latest {} formatted AI replies are kept for the bot owner to debug formatting.", get_outbound_debug_size())
+            } else {
+                String::new()
+            };
+            let response = format!(
+                "🔒 What this bot stores:\n\n\
+                 • AI conversation history, its summary and snapshots for branching from earlier replies - /export_chat to download, /clear to delete\n\
+                 • Chat settings such as model, persona and moderation\n\
+                 • Facts taught with /teach - /teach clear to delete\n\
+                 • Usage counters for quotas and budgets\n\
+                 • {recent}\n\n\
+                 {transcripts}{outbound}\n\n\
+                 Messages for AI features are sent to the configured AI provider."
+            );
+            bot.send_message(msg.chat.id, response).await?
+        }
+        Command::Setup => {
+            start_setup(&bot, &msg).await?;
+            return Ok(());
+        }
+        Command::Outbound(args) => {
+            if !is_owner(&msg) {
+                bot.send_message(msg.chat.id, "⛔ Only the bot owner can view outbound messages, in a private chat with the bot.")
+                    .await?
+            } else {
+                let count = args.trim().parse().unwrap_or_else(|_| get_outbound_debug_size());
+                match recent_outbound(count).await {
+                    Ok(records) if records.is_empty() => {
+                        let hint = if outbound_debug_enabled() { "" } else { " Set OUTBOUND_DEBUG=true to start capturing." };
+                        bot.send_message(msg.chat.id, format!("📭 No outbound messages captured.{hint}")).await?
+                    }
+                    Ok(records) => match serde_json::to_vec_pretty(&records) {
+                        Ok(contents) => {
+                            info!("📤 Sending {} captured outbound messages to the owner", records.len());
+                            bot.send_document(msg.chat.id, InputFile::memory(contents).file_name("outbound.json"))
+                                .caption(format!("📤 The latest {} formatted messages as sent to Telegram", records.len()))
+                                .await?
+                        }
+                        Err(e) => bot.send_message(msg.chat.id, format!("❌ Failed to export outbound messages: {e}")).await?,
+                    },
+                    Err(e) => {
+                        warn!("❌ Failed to load outbound messages: {e}");
+                        bot.send_message(msg.chat.id, format!("❌ Failed to load outbound messages: {e}")).await?
+                    }
+                }
+            }
+        }
     };
 
+    // Compliance transcript of the command and what the bot answered, a no-op unless TRANSCRIPT_SINK is set
+    let response = sent.text().or(sent.caption()).unwrap_or("<media>");
+    record_exchange(&msg.chat.id.to_string(), user_id, message_text, response);
+    schedule_autodelete(msg.chat.id, sent.id).await;
+
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,149 @@
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup},
+};
+
+use crate::conversation::clear_conversation_history;
+use crate::dialogue::{save_dialogue_state, take_dialogue_state};
+use crate::ingest::revoke_ingest_token;
+use crate::knowledge::clear_knowledge;
+use crate::transcript::record_exchange;
+
+// Long enough to read the prompt, short enough that a stale button cannot be pressed much later
+const CONFIRMATION_TTL: Duration = Duration::from_secs(2 * 60);
+const CONFIRM_SCOPE: &str = "confirm";
+
+// Commands that delete data and only run once the user presses the confirm button
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DestructiveAction {
+    ClearHistory,
+    ClearKnowledge,
+    RevokeIngest { token: String },
+}
+
+impl DestructiveAction {
+    fn prompt(&self) -> String {
+        match self {
+            DestructiveAction::ClearHistory => "⚠️ Delete the AI conversation history and summary for this chat?".to_string(),
+            DestructiveAction::ClearKnowledge => "⚠️ Delete everything taught with /teach in this chat?".to_string(),
+            DestructiveAction::RevokeIngest { .. } => {
+                "⚠️ Revoke this inbound alert URL? Anything still posting to it will start failing.".to_string()
+            }
+        }
+    }
+
+    async fn run(&self, chat_id: &str) -> String {
+        match self {
+            DestructiveAction::ClearHistory => match clear_conversation_history(chat_id).await {
+                Ok(0) => "🧹 There was no conversation history to clear.".to_string(),
+                Ok(deleted) => format!("🧹 Conversation cleared - deleted {deleted} messages."),
+                Err(e) => {
+                    warn!("❌ Failed to clear history for chat {chat_id}: {e}");
+                    format!("❌ Failed to clear conversation history: {e}")
+                }
+            },
+            DestructiveAction::ClearKnowledge => match clear_knowledge(chat_id).await {
+                Ok(()) => "🗑️ Cleared everything taught in this chat.".to_string(),
+                Err(e) => {
+                    warn!("❌ Failed to clear knowledge for chat {chat_id}: {e}");
+                    format!("❌ Failed to clear knowledge: {e}")
+                }
+            },
+            DestructiveAction::RevokeIngest { token } => match revoke_ingest_token(token, chat_id).await {
+                Ok(true) => "🗑️ Inbound alert URL revoked.".to_string(),
+                Ok(false) => "❌ No inbound alert URL with that token exists for this chat.".to_string(),
+                Err(e) => {
+                    warn!("❌ Failed to revoke ingest token for chat {chat_id}: {e}");
+                    format!("❌ Failed to revoke inbound alert URL: {e}")
+                }
+            },
+        }
+    }
+}
+
+// The action waiting for a user's confirmation, tied to the buttons of one prompt by its token
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingConfirmation {
+    token: String,
+    action: DestructiveAction,
+}
+
+// Ask the user to confirm an action with inline buttons. A newer request replaces the pending one,
+// so only the latest prompt's buttons work
+pub async fn request_confirmation(
+    bot: &Bot,
+    msg: &Message,
+    user_id: u64,
+    action: DestructiveAction,
+) -> ResponseResult<Message> {
+    let chat_id = msg.chat.id.to_string();
+    let token = uuid::Uuid::new_v4().simple().to_string()[..12].to_string();
+    let prompt = action.prompt();
+    let pending = PendingConfirmation { token: token.clone(), action };
+    if let Err(e) = save_dialogue_state(&chat_id, user_id, CONFIRM_SCOPE, &pending, CONFIRMATION_TTL).await {
+        warn!("❌ Failed to save confirmation for chat {}: {e}", msg.chat.id);
+        return bot
+            .send_message(msg.chat.id, format!("❌ Failed to start confirmation: {e}"))
+            .await;
+    }
+
+    let keyboard = InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback("✅ Confirm", format!("confirm:{token}")),
+        InlineKeyboardButton::callback("✖️ Cancel", format!("cancel:{token}")),
+    ]]);
+    info!("⚠️ Asking user {user_id} in chat {} to confirm {:?}", msg.chat.id, pending.action);
+    bot.send_message(msg.chat.id, format!("{prompt}\n\nThis expires in {} minutes.", CONFIRMATION_TTL.as_secs() / 60))
+        .reply_markup(keyboard)
+        .await
+}
+
+// Handle a press on a confirmation button, returning false for callbacks that are not ours
+pub async fn handle_confirmation(bot: &Bot, query: &CallbackQuery) -> ResponseResult<bool> {
+    let Some((choice, token)) = query.data.as_deref().and_then(|data| data.split_once(':')) else {
+        return Ok(false);
+    };
+    if choice != "confirm" && choice != "cancel" {
+        return Ok(false);
+    }
+    let Some(message) = query.message.as_ref() else {
+        return Ok(false);
+    };
+    let chat = message.chat().id;
+    let chat_id = chat.to_string();
+    let user_id = query.from.id.0;
+
+    // Keyed by the pressing user, so nobody else can confirm a prompt and a used or replaced token finds nothing.
+    // Taking the confirmation is a single conditional delete, so a repeated press cannot run the action twice
+    let pending = match take_dialogue_state::<PendingConfirmation>(&chat_id, user_id, CONFIRM_SCOPE, token).await {
+        Ok(Some(pending)) if pending.token == token => pending,
+        Ok(_) => {
+            bot.answer_callback_query(query.id.clone())
+                .text("This confirmation expired or belongs to someone else.")
+                .await?;
+            return Ok(true);
+        }
+        Err(e) => {
+            warn!("❌ Failed to claim confirmation for chat {chat}: {e}");
+            bot.answer_callback_query(query.id.clone())
+                .text("Failed to check this confirmation, try again.")
+                .await?;
+            return Ok(true);
+        }
+    };
+
+    let response = if choice == "confirm" {
+        info!("✅ User {user_id} confirmed {:?} in chat {chat}", pending.action);
+        pending.action.run(&chat_id).await
+    } else {
+        info!("✖️ User {user_id} cancelled {:?} in chat {chat}", pending.action);
+        "✖️ Cancelled, nothing was changed.".to_string()
+    };
+    bot.answer_callback_query(query.id.clone()).await?;
+    // Editing without a keyboard removes the buttons
+    bot.edit_message_text(chat, message.id(), response.clone()).await?;
+    record_exchange(&chat_id, user_id, &format!("{choice} {:?}", pending.action), &response);
+    Ok(true)
+}
@@ -0,0 +1,323 @@
+use log::{info, warn};
+use std::error::Error;
+use std::sync::OnceLock;
+use tiktoken_rs::tokenizer::{get_tokenizer, Tokenizer};
+use tiktoken_rs::CoreBPE;
+
+use crate::ai::model_capabilities;
+use crate::storage::{create_storage, ConversationMessage, ConversationRole};
+
+// Number of user/assistant exchanges kept per chat when no override is configured
+const DEFAULT_HISTORY_TURNS: usize = 10;
+// Upper bound on prompt tokens spent on history, so long chats stay cheap even on large-context models
+const DEFAULT_HISTORY_TOKEN_BUDGET: usize = 4000;
+// AI replies per chat that can still be replied to as a branch point
+const DEFAULT_BRANCH_POINTS: usize = 20;
+// Rough per-message overhead of the chat format on top of the content tokens
+const TOKENS_PER_MESSAGE: usize = 4;
+
+pub const SUMMARIZE_HISTORY_PROMPT: &str = "You maintain the memory of a conversation between a user and an AI \
+    assistant. You are given the previous summary, if any, followed by older messages as 'role: text' lines. \
+    Write an updated summary of at most 200 words that keeps names, facts, preferences, decisions and open \
+    questions the assistant needs to continue the conversation. Reply with the summary only, in the language of \
+    the conversation.";
+
+// Helper function to get the configured history length in turns
+pub fn get_history_turns() -> usize {
+    std::env::var("CONVERSATION_HISTORY_TURNS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_HISTORY_TURNS)
+}
+
+// Helper function to get how many branch points are kept per chat, 0 turns branching off
+pub fn get_branch_points() -> usize {
+    std::env::var("CONVERSATION_BRANCH_POINTS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_BRANCH_POINTS)
+}
+
+// Helper function to get the configured history token budget
+pub fn get_history_token_budget() -> usize {
+    std::env::var("CONVERSATION_MAX_CONTEXT_TOKENS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_HISTORY_TOKEN_BUDGET)
+}
+
+// Context window of a model in tokens, AI_CONTEXT_WINDOW overrides it for models not listed here
+pub fn get_context_window(model: &str) -> usize {
+    if let Some(window) = std::env::var("AI_CONTEXT_WINDOW").ok().and_then(|v| v.parse().ok()) {
+        return window;
+    }
+    let model = model.to_lowercase();
+    if model.starts_with("gpt-4.1") {
+        1_047_576
+    } else if model.starts_with("gpt-4o") || model.starts_with("gpt-4-turbo") || model_capabilities(&model).reasoning {
+        128_000
+    } else if model.starts_with("gpt-3.5") {
+        16_385
+    } else if model.starts_with("deepseek") {
+        64_000
+    } else {
+        8_192
+    }
+}
+
+// Models without a known tokenizer are counted with cl100k, which is close enough for budgeting
+pub fn count_tokens(model: &str, text: &str) -> usize {
+    static CL100K: OnceLock<Option<CoreBPE>> = OnceLock::new();
+    static O200K: OnceLock<Option<CoreBPE>> = OnceLock::new();
+
+    let bpe = match get_tokenizer(model) {
+        Some(Tokenizer::O200kBase) => O200K.get_or_init(|| tiktoken_rs::o200k_base().ok()),
+        _ => CL100K.get_or_init(|| tiktoken_rs::cl100k_base().ok()),
+    };
+    match bpe {
+        Some(bpe) => bpe.encode_with_special_tokens(text).len(),
+        // Roughly four characters per token in English text
+        None => text.len().div_ceil(4),
+    }
+}
+
+pub fn count_history_tokens(model: &str, history: &[ConversationMessage]) -> usize {
+    history
+        .iter()
+        .map(|entry| count_tokens(model, &entry.content) + TOKENS_PER_MESSAGE)
+        .sum()
+}
+
+// Tokens available for history once the system prompt, the new message and the reply are accounted for
+pub fn history_token_budget(model: &str, system_prompt: Option<&str>, message: &str, max_tokens: u32) -> usize {
+    let reserved = system_prompt.map(|prompt| count_tokens(model, prompt)).unwrap_or(0)
+        + count_tokens(model, message)
+        + max_tokens as usize
+        + TOKENS_PER_MESSAGE * 2;
+    get_context_window(model)
+        .saturating_sub(reserved)
+        .min(get_history_token_budget())
+}
+
+// Split history into the older messages to fold into the summary and the newest ones that are
+// kept verbatim. The kept part uses at most half of the budget so compaction does not run every turn
+pub fn split_for_compaction(
+    model: &str,
+    history: Vec<ConversationMessage>,
+    budget: usize,
+) -> (Vec<ConversationMessage>, Vec<ConversationMessage>) {
+    if count_history_tokens(model, &history) <= budget {
+        return (Vec::new(), history);
+    }
+
+    let mut kept_tokens = 0;
+    let mut keep_from = history.len();
+    for (index, entry) in history.iter().enumerate().rev() {
+        kept_tokens += count_tokens(model, &entry.content) + TOKENS_PER_MESSAGE;
+        if kept_tokens > budget / 2 {
+            break;
+        }
+        keep_from = index;
+    }
+    // Start the kept part on a user message so the model never sees a dangling answer
+    while keep_from < history.len() && history[keep_from].role != ConversationRole::User {
+        keep_from += 1;
+    }
+
+    let mut older = history;
+    let recent = older.split_off(keep_from);
+    (older, recent)
+}
+
+// Input for SUMMARIZE_HISTORY_PROMPT: the previous summary followed by the messages to fold in
+pub fn format_for_summary(summary: Option<&str>, older: &[ConversationMessage]) -> String {
+    let mut lines = Vec::with_capacity(older.len() + 2);
+    if let Some(summary) = summary {
+        lines.push(format!("Previous summary:\n{summary}\n"));
+    }
+    for entry in older {
+        let role = match entry.role {
+            ConversationRole::User => "user",
+            ConversationRole::Assistant => "assistant",
+        };
+        lines.push(format!("{role}: {}", entry.content));
+    }
+    lines.join("\n")
+}
+
+pub async fn load_conversation_summary(chat_id: &str) -> Option<String> {
+    match create_storage().await {
+        Ok(storage) => match storage.get_conversation_summary(chat_id).await {
+            Ok(summary) => summary,
+            Err(e) => {
+                warn!("⚠️ Failed to load conversation summary, continuing without it: {e}");
+                None
+            }
+        },
+        Err(e) => {
+            warn!("⚠️ Failed to create storage client, continuing without summary: {e}");
+            None
+        }
+    }
+}
+
+pub async fn save_conversation_summary(chat_id: &str, summary: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    storage.set_conversation_summary(chat_id, summary).await?;
+    info!("🧠 Conversation summary for chat {chat_id} updated");
+    Ok(())
+}
+
+// Load the stored conversation for a chat, falling back to an empty history
+pub async fn load_conversation_history(chat_id: &str) -> Vec<ConversationMessage> {
+    if get_history_turns() == 0 {
+        return Vec::new();
+    }
+
+    match create_storage().await {
+        Ok(storage) => match storage.get_conversation_history(chat_id).await {
+            Ok(history) => history,
+            Err(e) => {
+                warn!("⚠️ Failed to load conversation history, continuing without context: {e}");
+                Vec::new()
+            }
+        },
+        Err(e) => {
+            warn!("⚠️ Failed to create storage client, continuing without context: {e}");
+            Vec::new()
+        }
+    }
+}
+
+// Append a completed exchange to the chat history and persist the most recent turns, returning what was stored
+pub async fn record_conversation_turn(
+    chat_id: &str,
+    mut history: Vec<ConversationMessage>,
+    user_message: &str,
+    ai_response: &str,
+) -> Result<Vec<ConversationMessage>, Box<dyn Error + Send + Sync>> {
+    let max_messages = get_history_turns() * 2;
+    if max_messages == 0 {
+        return Ok(Vec::new());
+    }
+
+    history.push(ConversationMessage::new(ConversationRole::User, user_message.to_string()));
+    history.push(ConversationMessage::new(ConversationRole::Assistant, ai_response.to_string()));
+    if history.len() > max_messages {
+        history.drain(..history.len() - max_messages);
+    }
+
+    let storage = create_storage().await?;
+    storage.set_conversation_history(chat_id, &history).await?;
+
+    info!("🧠 Conversation history for chat {chat_id} now holds {} messages", history.len());
+    Ok(history)
+}
+
+// History as it stood right after the AI reply with this message id, None when it was never saved or has been pruned
+pub async fn load_history_snapshot(chat_id: &str, message_id: i32) -> Option<Vec<ConversationMessage>> {
+    if get_branch_points() == 0 {
+        return None;
+    }
+
+    match create_storage().await {
+        Ok(storage) => match storage.get_history_snapshot(chat_id, message_id).await {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                warn!("⚠️ Failed to load history snapshot, continuing with the latest history: {e}");
+                None
+            }
+        },
+        Err(e) => {
+            warn!("⚠️ Failed to create storage client, continuing with the latest history: {e}");
+            None
+        }
+    }
+}
+
+// Remember the history behind a sent AI reply, so replying to that message later branches from it
+pub async fn save_history_snapshot(chat_id: &str, message_id: i32, history: &[ConversationMessage]) {
+    let keep = get_branch_points();
+    if keep == 0 || history.is_empty() {
+        return;
+    }
+
+    let result = match create_storage().await {
+        Ok(storage) => storage.set_history_snapshot(chat_id, message_id, history, keep).await.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    // Without a snapshot a reply to this message just continues the latest history
+    if let Err(e) = result {
+        warn!("⚠️ Failed to save history snapshot for chat {chat_id}: {e}");
+    }
+}
+
+// Wipe the stored conversation for a chat, returning how many messages were removed
+pub async fn clear_conversation_history(chat_id: &str) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    let deleted = storage.delete_conversation_history(chat_id).await?;
+    Ok(deleted)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "" | "md" | "markdown" => Some(ExportFormat::Markdown),
+            "json" => Some(ExportFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+// Render the stored conversation and its rolling summary as a file, None when there is nothing to export
+pub async fn export_conversation(
+    chat_id: &str,
+    format: ExportFormat,
+) -> Result<Option<(String, Vec<u8>)>, Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    let history = storage.get_conversation_history(chat_id).await?;
+    let summary = storage.get_conversation_summary(chat_id).await?;
+    if history.is_empty() && summary.is_none() {
+        return Ok(None);
+    }
+
+    let exported_at = chrono::Utc::now();
+    let file_stem = format!("conversation-{chat_id}-{}", exported_at.format("%Y%m%d-%H%M%S"));
+    let file = match format {
+        ExportFormat::Json => {
+            let export = serde_json::json!({
+                "chat_id": chat_id,
+                "exported_at": exported_at.to_rfc3339(),
+                "summary": summary,
+                "messages": history,
+            });
+            (format!("{file_stem}.json"), serde_json::to_vec_pretty(&export)?)
+        }
+        ExportFormat::Markdown => {
+            let mut markdown = format!("# Conversation export\n\nChat: {chat_id}\nExported: {}\n", exported_at.to_rfc3339());
+            if let Some(summary) = summary {
+                markdown.push_str(&format!("\n## Summary of earlier messages\n\n{summary}\n"));
+            }
+            if !history.is_empty() {
+                markdown.push_str("\n## Messages\n");
+            }
+            for entry in &history {
+                let author = match entry.role {
+                    ConversationRole::User => "👤 User",
+                    ConversationRole::Assistant => "🤖 Assistant",
+                };
+                markdown.push_str(&format!("\n### {author} ({})\n\n{}\n", entry.timestamp, entry.content));
+            }
+            (format!("{file_stem}.md"), markdown.into_bytes())
+        }
+    };
+
+    info!("📦 Exported {} history messages for chat {chat_id}", history.len());
+    Ok(Some(file))
+}
@@ -0,0 +1,184 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UnitKind {
+    Length,
+    Mass,
+    Volume,
+    Speed,
+    Data,
+    Temperature,
+}
+
+// A unit with the factor that converts one of it into the kind's base unit
+struct Unit {
+    symbol: &'static str,
+    aliases: &'static [&'static str],
+    kind: UnitKind,
+    to_base: f64,
+}
+
+// Base units: meter, kilogram, liter, meter per second, byte; temperature is handled separately
+const UNITS: &[Unit] = &[
+    Unit { symbol: "mm", aliases: &["millimeter", "millimeters", "millimetre", "millimetres"], kind: UnitKind::Length, to_base: 0.001 },
+    Unit { symbol: "cm", aliases: &["centimeter", "centimeters", "centimetre", "centimetres"], kind: UnitKind::Length, to_base: 0.01 },
+    Unit { symbol: "m", aliases: &["meter", "meters", "metre", "metres"], kind: UnitKind::Length, to_base: 1.0 },
+    Unit { symbol: "km", aliases: &["kilometer", "kilometers", "kilometre", "kilometres"], kind: UnitKind::Length, to_base: 1000.0 },
+    Unit { symbol: "in", aliases: &["inch", "inches"], kind: UnitKind::Length, to_base: 0.0254 },
+    Unit { symbol: "ft", aliases: &["foot", "feet"], kind: UnitKind::Length, to_base: 0.3048 },
+    Unit { symbol: "yd", aliases: &["yard", "yards"], kind: UnitKind::Length, to_base: 0.9144 },
+    Unit { symbol: "mi", aliases: &["mile", "miles"], kind: UnitKind::Length, to_base: 1609.344 },
+    Unit { symbol: "nmi", aliases: &["nautical mile", "nautical miles"], kind: UnitKind::Length, to_base: 1852.0 },
+    Unit { symbol: "mg", aliases: &["milligram", "milligrams"], kind: UnitKind::Mass, to_base: 0.000_001 },
+    Unit { symbol: "g", aliases: &["gram", "grams"], kind: UnitKind::Mass, to_base: 0.001 },
+    Unit { symbol: "kg", aliases: &["kilogram", "kilograms", "kilo", "kilos"], kind: UnitKind::Mass, to_base: 1.0 },
+    Unit { symbol: "t", aliases: &["tonne", "tonnes", "metric ton", "metric tons"], kind: UnitKind::Mass, to_base: 1000.0 },
+    Unit { symbol: "oz", aliases: &["ounce", "ounces"], kind: UnitKind::Mass, to_base: 0.028_349_523_125 },
+    Unit { symbol: "lb", aliases: &["lbs", "pound", "pounds"], kind: UnitKind::Mass, to_base: 0.453_592_37 },
+    Unit { symbol: "ml", aliases: &["milliliter", "milliliters", "millilitre", "millilitres"], kind: UnitKind::Volume, to_base: 0.001 },
+    Unit { symbol: "l", aliases: &["liter", "liters", "litre", "litres"], kind: UnitKind::Volume, to_base: 1.0 },
+    Unit { symbol: "gal", aliases: &["gallon", "gallons"], kind: UnitKind::Volume, to_base: 3.785_411_784 },
+    Unit { symbol: "qt", aliases: &["quart", "quarts"], kind: UnitKind::Volume, to_base: 0.946_352_946 },
+    Unit { symbol: "cup", aliases: &["cups"], kind: UnitKind::Volume, to_base: 0.236_588_236_5 },
+    Unit { symbol: "floz", aliases: &["fl oz", "fluid ounce", "fluid ounces"], kind: UnitKind::Volume, to_base: 0.029_573_529_562_5 },
+    Unit { symbol: "m/s", aliases: &["mps", "meters per second"], kind: UnitKind::Speed, to_base: 1.0 },
+    Unit { symbol: "km/h", aliases: &["kmh", "kph", "kilometers per hour"], kind: UnitKind::Speed, to_base: 1000.0 / 3600.0 },
+    Unit { symbol: "mph", aliases: &["mi/h", "miles per hour"], kind: UnitKind::Speed, to_base: 1609.344 / 3600.0 },
+    Unit { symbol: "kn", aliases: &["knot", "knots"], kind: UnitKind::Speed, to_base: 1852.0 / 3600.0 },
+    Unit { symbol: "B", aliases: &["byte", "bytes"], kind: UnitKind::Data, to_base: 1.0 },
+    Unit { symbol: "KB", aliases: &["kilobyte", "kilobytes"], kind: UnitKind::Data, to_base: 1e3 },
+    Unit { symbol: "MB", aliases: &["megabyte", "megabytes"], kind: UnitKind::Data, to_base: 1e6 },
+    Unit { symbol: "GB", aliases: &["gigabyte", "gigabytes"], kind: UnitKind::Data, to_base: 1e9 },
+    Unit { symbol: "TB", aliases: &["terabyte", "terabytes"], kind: UnitKind::Data, to_base: 1e12 },
+    Unit { symbol: "KiB", aliases: &["kibibyte", "kibibytes"], kind: UnitKind::Data, to_base: 1024.0 },
+    Unit { symbol: "MiB", aliases: &["mebibyte", "mebibytes"], kind: UnitKind::Data, to_base: 1_048_576.0 },
+    Unit { symbol: "GiB", aliases: &["gibibyte", "gibibytes"], kind: UnitKind::Data, to_base: 1_073_741_824.0 },
+    Unit { symbol: "°C", aliases: &["c", "celsius", "degc"], kind: UnitKind::Temperature, to_base: 1.0 },
+    Unit { symbol: "°F", aliases: &["f", "fahrenheit", "degf"], kind: UnitKind::Temperature, to_base: 1.0 },
+    Unit { symbol: "K", aliases: &["kelvin"], kind: UnitKind::Temperature, to_base: 1.0 },
+];
+
+#[derive(Debug, PartialEq)]
+pub enum ConvertError {
+    Usage,
+    InvalidAmount(String),
+    UnknownUnit(String),
+    Incompatible(String, String),
+    CurrencyUnsupported,
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConvertError::Usage => write!(f, "Usage: /convert <amount> <unit> to <unit>, e.g. /convert 5 miles to km"),
+            ConvertError::InvalidAmount(amount) => write!(f, "'{amount}' is not a valid number"),
+            ConvertError::UnknownUnit(unit) => write!(f, "Unknown unit: {unit}"),
+            ConvertError::Incompatible(from, to) => write!(f, "Cannot convert {from} to {to}"),
+            ConvertError::CurrencyUnsupported => write!(f, "Currency conversion is not available: no exchange rate provider is configured"),
+        }
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+#[derive(Debug, PartialEq)]
+pub struct Conversion {
+    pub amount: f64,
+    pub from: &'static str,
+    pub result: f64,
+    pub to: &'static str,
+}
+
+impl fmt::Display for Conversion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} = {} {}", format_number(self.amount), self.from, format_number(self.result), self.to)
+    }
+}
+
+// Up to 6 significant decimals without trailing zeros
+fn format_number(value: f64) -> String {
+    let formatted = format!("{value:.6}");
+    formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn find_unit(name: &str) -> Option<&'static Unit> {
+    let lower = name.to_lowercase();
+    UNITS
+        .iter()
+        .find(|unit| unit.symbol == name || unit.symbol.to_lowercase() == lower)
+        .or_else(|| UNITS.iter().find(|unit| unit.aliases.contains(&lower.as_str())))
+}
+
+// Three-letter uppercase codes like USD or JPY are treated as currencies
+fn looks_like_currency(name: &str) -> bool {
+    name.len() == 3 && name.chars().all(|c| c.is_ascii_uppercase())
+}
+
+fn celsius_from(unit: &Unit, value: f64) -> f64 {
+    match unit.symbol {
+        "°F" => (value - 32.0) * 5.0 / 9.0,
+        "K" => value - 273.15,
+        _ => value,
+    }
+}
+
+fn celsius_to(unit: &Unit, celsius: f64) -> f64 {
+    match unit.symbol {
+        "°F" => celsius * 9.0 / 5.0 + 32.0,
+        "K" => celsius + 273.15,
+        _ => celsius,
+    }
+}
+
+// Parse and evaluate "<amount> <unit> to|in <unit>"
+pub fn convert(input: &str) -> Result<Conversion, ConvertError> {
+    let input = input.trim();
+    let (left, target) = [" to ", " in ", " TO ", " IN "]
+        .iter()
+        .find_map(|sep| input.split_once(sep))
+        .ok_or(ConvertError::Usage)?;
+
+    let left = left.trim();
+    let amount_end = left
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | ',' | '-' | '+')))
+        .unwrap_or(left.len());
+    let (amount_text, source) = left.split_at(amount_end);
+    let source = source.trim();
+    let target = target.trim();
+    if amount_text.is_empty() || source.is_empty() || target.is_empty() {
+        return Err(ConvertError::Usage);
+    }
+
+    let amount: f64 = amount_text
+        .replace(',', "")
+        .parse()
+        .map_err(|_| ConvertError::InvalidAmount(amount_text.to_string()))?;
+
+    let from = find_unit(source);
+    let to = find_unit(target);
+    let (from, to) = match (from, to) {
+        (Some(from), Some(to)) => (from, to),
+        _ if looks_like_currency(source) && looks_like_currency(target) => {
+            return Err(ConvertError::CurrencyUnsupported);
+        }
+        (None, _) => return Err(ConvertError::UnknownUnit(source.to_string())),
+        (_, None) => return Err(ConvertError::UnknownUnit(target.to_string())),
+    };
+
+    if from.kind != to.kind {
+        return Err(ConvertError::Incompatible(from.symbol.to_string(), to.symbol.to_string()));
+    }
+
+    let result = if from.kind == UnitKind::Temperature {
+        celsius_to(to, celsius_from(from, amount))
+    } else {
+        amount * from.to_base / to.to_base
+    };
+
+    Ok(Conversion {
+        amount,
+        from: from.symbol,
+        result,
+        to: to.symbol,
+    })
+}
@@ -1,5 +1,7 @@
 use std::env;
 use log::info;
+#[cfg(feature = "axum-server")]
+use log::warn;
 use teloxide::prelude::*;
 
 #[cfg(feature = "axum-server")]
@@ -8,7 +10,7 @@ use axum::{routing::get, routing::post, Router};
 #[cfg(feature = "lambda")]
 use lambda_runtime::service_fn;
 
-use crate::handlers::handle_message;
+use crate::handlers::{handle_callback_query, handle_message};
 
 #[cfg(feature = "lambda")]
 use crate::handlers::lambda_handler;
@@ -84,9 +86,15 @@ pub async fn run_lambda_mode(bot: Bot) -> Result<(), Box<dyn std::error::Error>>
 
 #[cfg(feature = "axum-server")]
 pub async fn run_webhook_mode(bot: Bot) -> Result<(), Box<dyn std::error::Error>> {
-    use axum::extract::State;
+    use axum::body::Bytes;
+    use axum::extract::{Path, Query, State};
+    use axum::http::{HeaderMap, StatusCode};
     use axum::response::Html;
     use axum::Json;
+    use std::collections::HashMap;
+
+    use crate::ingest::{deliver_ingest_payload, IngestError, IngestRequest};
+    use crate::watchdog::spawn_webhook_watchdog;
     
     async fn health_check() -> Html<&'static str> {
         Html("<h1>Bot is running!</h1>")
@@ -98,14 +106,52 @@ pub async fn run_webhook_mode(bot: Bot) -> Result<(), Box<dyn std::error::Error>
     ) -> &'static str {
         info!("🔗 Webhook received update: {:?}", update.id);
 
-        if let teloxide::types::UpdateKind::Message(message) = update.kind {
-            let _ = handle_message(bot, message).await;
-        } else {
-            info!("🔄 Received non-message update in webhook");
+        match update.kind {
+            teloxide::types::UpdateKind::Message(message) => {
+                let _ = handle_message(bot, message).await;
+            }
+            teloxide::types::UpdateKind::CallbackQuery(query) => {
+                let _ = handle_callback_query(bot, query).await;
+            }
+            _ => info!("🔄 Received non-message update in webhook"),
         }
         "OK"
     }
 
+    async fn ingest_handler(
+        State(bot): State<Bot>,
+        Path(token): Path<String>,
+        Query(mut params): Query<HashMap<String, String>>,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> (StatusCode, &'static str) {
+        info!("📥 Ingest endpoint received payload");
+
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+        let request = IngestRequest {
+            source_hint: params.remove("source"),
+            github_event: header("x-github-event"),
+            signature: header("x-hub-signature-256"),
+        };
+
+        match deliver_ingest_payload(&bot, &token, &request, &body).await {
+            Ok(()) => (StatusCode::OK, "OK"),
+            Err(IngestError::UnknownToken) => (StatusCode::NOT_FOUND, "Unknown token"),
+            Err(IngestError::InvalidPayload(e)) => {
+                warn!("❌ Ingest payload rejected: {e}");
+                (StatusCode::BAD_REQUEST, "Invalid payload")
+            }
+            Err(IngestError::Unauthorized(e)) => {
+                warn!("❌ Ingest request unauthorized: {e}");
+                (StatusCode::UNAUTHORIZED, "Unauthorized")
+            }
+            Err(e) => {
+                warn!("❌ Ingest delivery failed: {e}");
+                (StatusCode::BAD_GATEWAY, "Delivery failed")
+            }
+        }
+    }
+
     let webhook_url = env::var("WEBHOOK_URL")
         .map_err(|_| "WEBHOOK_URL must be set for webhook mode")?;
     let port: u16 = env::var("PORT")
@@ -116,13 +162,16 @@ pub async fn run_webhook_mode(bot: Bot) -> Result<(), Box<dyn std::error::Error>
     info!("🌐 Production environment detected - running in WEBHOOK mode");
     info!("🔗 Setting up webhook at: {webhook_url}");
 
-    bot.set_webhook(webhook_url.parse()?)
+    let webhook_url: url::Url = webhook_url.parse()?;
+    bot.set_webhook(webhook_url.clone())
         .await
         .map_err(|e| format!("Failed to set webhook: {e}"))?;
+    spawn_webhook_watchdog(bot.clone(), webhook_url);
 
     let app = Router::new()
         .route("/", get(health_check))
         .route("/webhook", post(webhook_handler))
+        .route("/ingest/:token", post(ingest_handler))
         .with_state(bot);
 
     let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{port}"))
@@ -141,6 +190,14 @@ pub async fn run_polling_mode(bot: Bot) {
     info!("👂 Starting polling loop - ready to receive updates!");
 
     // Use message handler that properly handles group chats
-    let handler = Update::filter_message().endpoint(handle_message);
-    Dispatcher::builder(bot, handler).build().dispatch().await;
+    let handler = dptree::entry()
+        .branch(Update::filter_message().endpoint(handle_message))
+        .branch(Update::filter_callback_query().endpoint(handle_callback_query));
+    // Updates run concurrently instead of queueing per chat, so /cancel and Stop presses reach a chat
+    // while its slow AI request is still running, the same as in webhook mode
+    Dispatcher::builder(bot, handler)
+        .distribution_function(|_| None::<()>)
+        .build()
+        .dispatch()
+        .await;
 }
\ No newline at end of file
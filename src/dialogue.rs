@@ -0,0 +1,67 @@
+use log::info;
+use serde::{de::DeserializeOwned, Serialize};
+use std::error::Error;
+use std::time::Duration;
+
+use crate::storage::create_storage;
+
+// Multi-step commands, confirmations and pagination cursors must not live in process memory:
+// a webhook replica or Lambda invocation handling the next message may not be the one that
+// handled the previous. They are kept here instead, keyed by chat, user and a scope name.
+
+// How long pending state survives when the caller does not pick a lifetime
+pub const DEFAULT_DIALOGUE_TTL: Duration = Duration::from_secs(15 * 60);
+
+fn dialogue_key(chat_id: &str, user_id: u64, scope: &str) -> String {
+    format!("{chat_id}#{user_id}#{scope}")
+}
+
+// Load the pending state of a user's dialogue in a chat, None when there is none or it expired
+pub async fn load_dialogue_state<T: DeserializeOwned>(
+    chat_id: &str,
+    user_id: u64,
+    scope: &str,
+) -> Result<Option<T>, Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    match storage.get_dialogue_state(&dialogue_key(chat_id, user_id, scope)).await? {
+        Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+        None => Ok(None),
+    }
+}
+
+pub async fn save_dialogue_state<T: Serialize>(
+    chat_id: &str,
+    user_id: u64,
+    scope: &str,
+    state: &T,
+    ttl: Duration,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let json = serde_json::to_string(state)?;
+    let storage = create_storage().await?;
+    storage
+        .set_dialogue_state(&dialogue_key(chat_id, user_id, scope), &json, ttl.as_secs() as i64)
+        .await?;
+    info!("💬 Saved {scope} dialogue state for user {user_id} in chat {chat_id}");
+    Ok(())
+}
+
+// Load and clear the state in one step, only if it still contains the marker, e.g. the token of the prompt
+// being answered. A second caller racing for the same state gets None
+pub async fn take_dialogue_state<T: DeserializeOwned>(
+    chat_id: &str,
+    user_id: u64,
+    scope: &str,
+    marker: &str,
+) -> Result<Option<T>, Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    match storage.take_dialogue_state(&dialogue_key(chat_id, user_id, scope), marker).await? {
+        Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+        None => Ok(None),
+    }
+}
+
+pub async fn clear_dialogue_state(chat_id: &str, user_id: u64, scope: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    storage.delete_dialogue_state(&dialogue_key(chat_id, user_id, scope)).await?;
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use log::warn;
+use minijinja::context;
+use std::error::Error;
+
+use crate::storage::create_storage;
+use crate::templates::render;
+
+// Stored instead of a text when a chat opts out of the global disclaimer
+const DISCLAIMER_OFF: &str = "off";
+
+// Helper function to get the disclaimer for chats that have not set their own
+pub fn get_default_disclaimer() -> Option<String> {
+    std::env::var("DISCLAIMER_TEXT")
+        .ok()
+        .filter(|text| !text.trim().is_empty())
+}
+
+// The chat's own disclaimer, or the global one. None when the chat turned it off or none is configured
+pub async fn get_chat_disclaimer(chat_id: &str) -> Option<String> {
+    let stored = match create_storage().await {
+        Ok(storage) => match storage.get_disclaimer(chat_id).await {
+            Ok(stored) => stored,
+            Err(e) => {
+                warn!("⚠️ Failed to get disclaimer from storage: {e}");
+                None
+            }
+        },
+        Err(e) => {
+            warn!("⚠️ Failed to create storage client: {e}");
+            None
+        }
+    };
+    match stored {
+        Some(text) if text == DISCLAIMER_OFF => None,
+        Some(text) => Some(text),
+        None => get_default_disclaimer(),
+    }
+}
+
+// Some(text) sets the chat's disclaimer, "off" disables it, None goes back to the global one
+pub async fn set_chat_disclaimer(chat_id: &str, text: Option<&str>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    storage.set_disclaimer(chat_id, text).await?;
+    Ok(())
+}
+
+// Append the chat's disclaimer as a footer. The text goes through the footer.disclaimer template so
+// operators can provide translated wording per locale
+pub async fn append_disclaimer(chat_id: &str, locale: Option<&str>, message: String) -> String {
+    match get_chat_disclaimer(chat_id).await {
+        Some(text) => {
+            let footer = render("footer.disclaimer", locale, context! { text });
+            format!("{message}\n\n{footer}")
+        }
+        None => message,
+    }
+}
@@ -0,0 +1,57 @@
+use log::warn;
+use teloxide::types::{Chat, EffectId};
+
+use crate::storage::create_storage;
+
+// Presentation extras live here so commands only say what happened, not how to decorate it.
+// Telegram only shows message effects in private chats, elsewhere messages go out plain.
+// Premium custom emoji need a bot username bought on Fragment, so standard emoji are used instead.
+
+// What a message is celebrating, which picks the effect
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Occasion {
+    // Something the user asked for was created, like an image
+    Created,
+    // A request went through
+    Done,
+}
+
+impl Occasion {
+    fn effect_id(&self) -> &'static str {
+        match self {
+            Occasion::Created => "5046509860389126442", // 🎉
+            Occasion::Done => "5107584321108051014",    // 👍
+        }
+    }
+}
+
+// Check whether a chat opted in to flair, off by default
+pub async fn get_flair_enabled(chat_id: &str) -> bool {
+    match create_storage().await {
+        Ok(storage) => match storage.get_flair_enabled(chat_id).await {
+            Ok(enabled) => enabled,
+            Err(e) => {
+                warn!("⚠️ Failed to get flair setting from storage: {e}");
+                false
+            }
+        },
+        Err(e) => {
+            warn!("⚠️ Failed to create storage client: {e}");
+            false
+        }
+    }
+}
+
+pub async fn set_flair_enabled(chat_id: &str, enabled: bool) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    storage.set_flair_enabled(chat_id, enabled).await?;
+    Ok(())
+}
+
+// Effect to attach to a message for the occasion, None where it would not show or the chat has flair off
+pub async fn message_effect(chat: &Chat, occasion: Occasion) -> Option<EffectId> {
+    if !chat.is_private() || !get_flair_enabled(&chat.id.to_string()).await {
+        return None;
+    }
+    Some(EffectId::from(occasion.effect_id().to_string()))
+}
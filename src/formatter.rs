@@ -0,0 +1,171 @@
+use std::error::Error;
+use std::fmt;
+
+// Characters Telegram MarkdownV2 requires to be escaped outside of entities
+const SPECIAL_CHARS: &[char] = &[
+    '_', '*', '[', ']', '(', ')', '~', '`', '>', '#', '+', '-', '=', '|', '{', '}', '.', '!', '\\',
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatError(String);
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Cannot format as MarkdownV2: {}", self.0)
+    }
+}
+
+impl Error for FormatError {}
+
+pub fn escape_markdown_v2(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if SPECIAL_CHARS.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// Inside code spans and blocks only ` and \ are escaped
+fn escape_code(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('`', "\\`")
+}
+
+// Inside the (...) part of a link only ) and \ are escaped
+fn escape_url(url: &str) -> String {
+    url.replace('\\', "\\\\").replace(')', "\\)")
+}
+
+// Convert the Markdown models typically produce into Telegram MarkdownV2.
+// Fails on constructs that cannot be rendered faithfully, so the caller can send plain text instead
+pub fn markdown_to_telegram(markdown: &str) -> Result<String, FormatError> {
+    let mut output = Vec::new();
+    let mut lines = markdown.lines();
+    while let Some(line) = lines.next() {
+        if let Some(language) = line.trim_start().strip_prefix("```") {
+            let mut code = Vec::new();
+            let mut closed = false;
+            for line in lines.by_ref() {
+                if line.trim_start().starts_with("```") {
+                    closed = true;
+                    break;
+                }
+                code.push(line);
+            }
+            if !closed {
+                return Err(FormatError("unclosed code block".to_string()));
+            }
+            // Telegram only accepts a plain word as the language tag
+            let language = language.trim();
+            let language = if language.chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '#') {
+                language
+            } else {
+                ""
+            };
+            output.push(format!("```{language}\n{}\n```", escape_code(&code.join("\n"))));
+            continue;
+        }
+        output.push(convert_line(line));
+    }
+    Ok(output.join("\n"))
+}
+
+fn convert_line(line: &str) -> String {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+
+    // Headings have no MarkdownV2 equivalent, render them bold
+    let hashes = trimmed.chars().take_while(|c| *c == '#').count();
+    if (1..=6).contains(&hashes)
+        && let Some(title) = trimmed[hashes..].strip_prefix(' ')
+    {
+        let title = title.trim().replace("**", "").replace("__", "");
+        return format!("{indent}*{}*", escape_markdown_v2(&title));
+    }
+
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(item) = trimmed.strip_prefix(bullet) {
+            return format!("{indent}• {}", convert_inline(item));
+        }
+    }
+
+    if let Some(quote) = trimmed.strip_prefix('>') {
+        return format!(">{}", convert_inline(quote.strip_prefix(' ').unwrap_or(quote)));
+    }
+
+    format!("{indent}{}", convert_inline(trimmed))
+}
+
+// Index of the next `delimiter` at or after `from` for which `accept` holds
+fn find_closing(chars: &[char], from: usize, delimiter: &[char], accept: impl Fn(usize) -> bool) -> Option<usize> {
+    (from..chars.len().saturating_sub(delimiter.len() - 1))
+        .find(|&index| chars[index..index + delimiter.len()] == *delimiter && accept(index))
+}
+
+// Convert inline code, bold, italic, strikethrough and links, escaping everything else.
+// Unmatched markers are kept as literal characters
+fn convert_inline(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let collect = |range: std::ops::Range<usize>| chars[range].iter().collect::<String>();
+    let is_word = |index: usize| chars.get(index).is_some_and(|c| c.is_alphanumeric());
+    let is_space = |index: usize| chars.get(index).is_none_or(|c| c.is_whitespace());
+
+    let mut output = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let doubled = chars.get(i + 1) == Some(&c);
+
+        match c {
+            '`' => {
+                if let Some(end) = find_closing(&chars, i + 1, &['`'], |_| true) {
+                    output.push_str(&format!("`{}`", escape_code(&collect(i + 1..end))));
+                    i = end + 1;
+                    continue;
+                }
+            }
+            '*' | '_' | '~' if doubled && !is_space(i + 2) => {
+                if let Some(end) = find_closing(&chars, i + 2, &[c, c], |end| !is_space(end - 1)) {
+                    // **bold** and __bold__ become bold, ~~strike~~ becomes strikethrough
+                    let marker = if c == '~' { '~' } else { '*' };
+                    output.push_str(&format!("{marker}{}{marker}", convert_inline(&collect(i + 2..end))));
+                    i = end + 2;
+                    continue;
+                }
+            }
+            // snake_case words and arithmetic like 2*3 stay literal
+            '*' | '_' if !doubled && !is_space(i + 1) && !is_word(i.wrapping_sub(1)) => {
+                if let Some(end) = find_closing(&chars, i + 1, &[c], |end| {
+                    !is_space(end - 1) && !is_word(end + 1) && chars.get(end + 1) != Some(&c)
+                }) {
+                    output.push_str(&format!("_{}_", convert_inline(&collect(i + 1..end))));
+                    i = end + 1;
+                    continue;
+                }
+            }
+            '[' => {
+                if let Some(middle) = find_closing(&chars, i + 1, &[']', '('], |_| true)
+                    && let Some(end) = find_closing(&chars, middle + 2, &[')'], |_| true)
+                {
+                    output.push_str(&format!(
+                        "[{}]({})",
+                        convert_inline(&collect(i + 1..middle)),
+                        escape_url(&collect(middle + 2..end))
+                    ));
+                    i = end + 1;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+
+        if SPECIAL_CHARS.contains(&c) {
+            output.push('\\');
+        }
+        output.push(c);
+        i += 1;
+    }
+    output
+}
@@ -1,4 +1,5 @@
 use log::info;
+use minijinja::context;
 
 #[cfg(feature = "lambda")]
 use log::warn;
@@ -9,9 +10,26 @@ use lambda_runtime::{Error as LambdaError, LambdaEvent};
 #[cfg(feature = "lambda")]
 use serde_json::Value;
 
+#[cfg(feature = "lambda")]
+use crate::autodelete::sweep_if_due;
+#[cfg(feature = "lambda")]
+use crate::watchdog::flush_events_if_due;
+
 use crate::commands::{Command, answer};
+use crate::confirm::handle_confirmation;
+use crate::pause::is_paused;
+use crate::plugins::{command_descriptions, find_plugin_command, PluginContext};
+use crate::progress::handle_progress_cancel;
+use crate::setup::{handle_setup_reply, refresh_runtime_config};
+use crate::summarize::{cache_group_message, parse_page_url};
+use crate::templates::render;
+use crate::watchdog::{count_event, WatchEvent};
 
 pub async fn handle_message(bot: Bot, msg: Message) -> ResponseResult<()> {
+    // Pick up settings completed through /setup, possibly on another replica
+    refresh_runtime_config(&bot).await;
+    count_event(WatchEvent::Update).await;
+
     if let Some(text) = msg.text() {
         // Get bot info to use the correct username for command parsing
         let bot_user = bot.get_me().await?;
@@ -23,15 +41,24 @@ pub async fn handle_message(bot: Bot, msg: Message) -> ResponseResult<()> {
         let bot_mention = format!("@{bot_username}");
         let is_private_chat = msg.chat.is_private();
         let is_mentioned = text.contains(&bot_mention);
+        // In groups, replying to the bot continues the conversation without a mention
+        let is_reply_to_bot = msg
+            .reply_to_message()
+            .and_then(|reply| reply.from.as_ref())
+            .is_some_and(|user| user.id == bot_user.id)
+            // A paused chat still answers mentions and commands, but not plain replies
+            && (text.starts_with('/') || is_private_chat || is_mentioned || !is_paused(&bot, msg.chat.id).await);
+        let locale = msg.from.as_ref().and_then(|user| user.language_code.as_deref());
 
         info!(
-            "💬 Chat type: {}, Bot mentioned: {}",
+            "💬 Chat type: {}, Bot mentioned: {}, Reply to bot: {}",
             if is_private_chat { "Private" } else { "Group" },
-            is_mentioned
+            is_mentioned,
+            is_reply_to_bot
         );
 
-        // Process message if it's a private chat OR bot is mentioned in group
-        if is_private_chat || is_mentioned {
+        // Process message if it's a private chat, the bot is mentioned or replied to in a group
+        if is_private_chat || is_mentioned || is_reply_to_bot {
             let processed_text = if is_mentioned {
                 // Remove bot mention and clean up the text
                 let cleaned = text.replace(&bot_mention, "").trim().to_string();
@@ -41,19 +68,28 @@ pub async fn handle_message(bot: Bot, msg: Message) -> ResponseResult<()> {
                 text.to_string()
             };
 
-            // Try to parse as command first
-            if let Ok(cmd) = Command::parse(&processed_text, "") {
+            // An owner in the middle of /setup answers its questions first
+            if is_private_chat && handle_setup_reply(&bot, &msg, &processed_text).await? {
+                info!("🛠️ Message consumed by the setup dialogue");
+            } else if let Ok(cmd) = Command::parse(&processed_text, "") {
                 info!("✅ Command parsed successfully: {cmd:?}");
                 answer(bot, msg, cmd).await?;
+            } else if let Some((plugin, args)) = find_plugin_command(&processed_text) {
+                info!("🧩 Dispatching to plugin command /{}", plugin.name());
+                plugin.handle(PluginContext { bot, message: msg }, args).await?;
             } else if processed_text.starts_with('/') {
                 // If it starts with '/' but couldn't parse, it's an unknown command
                 info!("❌ Unknown command: '{processed_text}'");
-                let response = format!(
-                    "Unknown command: {}\n\nAvailable commands:\n{}",
-                    processed_text,
-                    Command::descriptions()
+                let response = render(
+                    "unknown_command",
+                    locale,
+                    context! { command => processed_text, commands => command_descriptions() },
                 );
                 bot.send_message(msg.chat.id, response).await?;
+            } else if parse_page_url(&processed_text).is_some() {
+                // A bare link gets a summary of the page instead of a chat reply
+                info!("🌐 Message is a link - converting to Command::Summarize");
+                answer(bot, msg, Command::Summarize(processed_text)).await?;
             } else if !processed_text.trim().is_empty() {
                 // Not a command, treat as general AI chat (default behavior)
                 info!("🤖 No command detected - defaulting to /general for message: '{processed_text}'");
@@ -62,23 +98,25 @@ pub async fn handle_message(bot: Bot, msg: Message) -> ResponseResult<()> {
             } else {
                 // Empty message after mention removal
                 info!("🙄 Empty message after processing mention");
-                let response = if is_private_chat {
-                    format!(
-                        "Hello! Send me a command or message.\n\n{}",
-                        Command::descriptions()
-                    )
-                } else {
-                    format!(
-                        "Hello! You mentioned me. Send a command or message after @{}.\n\n{}",
-                        bot_username,
-                        Command::descriptions()
-                    )
-                };
+                let template = if is_private_chat { "welcome.private" } else { "welcome.group" };
+                let response = render(
+                    template,
+                    locale,
+                    context! { bot_username, commands => command_descriptions() },
+                );
                 bot.send_message(msg.chat.id, response).await?;
             }
         } else {
-            // In group chat but bot not mentioned - ignore
-            info!("😶 Group message without bot mention - ignoring");
+            // In group chat but bot not mentioned - only keep it for /summarize
+            info!("😶 Group message without bot mention - caching for summaries");
+            if !text.starts_with('/') {
+                let author = msg
+                    .from
+                    .as_ref()
+                    .map(|user| user.full_name())
+                    .unwrap_or_else(|| "unknown".to_string());
+                cache_group_message(&msg.chat.id.to_string(), &author, text).await;
+            }
         }
     } else {
         info!("📷 Received non-text message");
@@ -86,6 +124,18 @@ pub async fn handle_message(bot: Bot, msg: Message) -> ResponseResult<()> {
     Ok(())
 }
 
+// Inline button presses: confirmations of destructive commands and Stop on progress messages, anything else
+// is acknowledged and ignored
+pub async fn handle_callback_query(bot: Bot, query: CallbackQuery) -> ResponseResult<()> {
+    count_event(WatchEvent::Update).await;
+
+    if !handle_confirmation(&bot, &query).await? && !handle_progress_cancel(&bot, &query).await? {
+        info!("🔄 Ignoring unknown callback query: {:?}", query.data);
+        bot.answer_callback_query(query.id.clone()).await?;
+    }
+    Ok(())
+}
+
 #[cfg(feature = "lambda")]
 pub async fn lambda_handler(
     event: LambdaEvent<Value>,
@@ -93,6 +143,18 @@ pub async fn lambda_handler(
     info!("🔗 Lambda received event: {:?}", event.payload);
     
     let bot = Bot::from_env();
+
+    // EventBridge schedules invoke the function directly to run the jobs other modes keep in the background
+    if event.payload.get("source").and_then(|s| s.as_str()) == Some("aws.events") {
+        run_scheduled_jobs(&bot).await;
+        return Ok(serde_json::json!({ "statusCode": 200, "body": "OK" }));
+    }
+
+    // Inbound alerts share the function URL with Telegram updates
+    let path = event.payload.get("rawPath").and_then(|p| p.as_str()).unwrap_or("");
+    if let Some(token) = path.strip_prefix("/ingest/") {
+        return Ok(lambda_ingest_handler(&bot, token, &event.payload).await);
+    }
     
     // Parse the Telegram webhook update from the Lambda event body
     if let Some(body) = event.payload.get("body").and_then(|b| b.as_str()) {
@@ -101,10 +163,14 @@ pub async fn lambda_handler(
         if let Ok(update) = serde_json::from_str::<teloxide::types::Update>(body) {
             info!("✅ Successfully parsed Telegram update: {:?}", update.id);
             
-            if let teloxide::types::UpdateKind::Message(message) = update.kind {
-                let _ = handle_message(bot, message).await;
-            } else {
-                info!("🔄 Received non-message update in Lambda");
+            match update.kind {
+                teloxide::types::UpdateKind::Message(message) => {
+                    let _ = handle_message(bot.clone(), message).await;
+                }
+                teloxide::types::UpdateKind::CallbackQuery(query) => {
+                    let _ = handle_callback_query(bot.clone(), query).await;
+                }
+                _ => info!("🔄 Received non-message update in Lambda"),
             }
         } else {
             warn!("❌ Failed to parse Telegram update from body: {body}");
@@ -113,9 +179,75 @@ pub async fn lambda_handler(
         warn!("❌ No body field found in Lambda event");
     }
     
+    // Lambda runs no background tasks, so due auto-deletions and watchdog counts happen on the way out of an invocation
+    sweep_if_due(&bot).await;
+    flush_events_if_due().await;
+
     // Return success response
     Ok(serde_json::json!({
         "statusCode": 200,
         "body": "OK"
     }))
-}
\ No newline at end of file
+}
+
+// Background jobs for a scheduled Lambda invocation, each failing on its own without stopping the others
+#[cfg(feature = "lambda")]
+async fn run_scheduled_jobs(bot: &Bot) {
+    use crate::autodelete::sweep_due_deletes;
+    use crate::quota::send_quota_report_if_due;
+    use crate::watchdog::{run_scheduled_watchdog, run_scheduled_webhook_check};
+
+    info!("⏰ Running scheduled jobs");
+    if let Err(e) = sweep_due_deletes(bot).await {
+        warn!("⚠️ Auto-delete sweep failed: {e}");
+    }
+    run_scheduled_watchdog(bot).await;
+    run_scheduled_webhook_check(bot).await;
+    send_quota_report_if_due(bot).await;
+}
+
+#[cfg(feature = "lambda")]
+async fn lambda_ingest_handler(bot: &Bot, token: &str, payload: &Value) -> Value {
+    use crate::ingest::{deliver_ingest_payload, IngestError, IngestRequest};
+
+    info!("📥 Lambda ingest endpoint received payload");
+
+    let Some(body) = payload.get("body").and_then(|b| b.as_str()) else {
+        warn!("❌ Ingest request without a body");
+        return serde_json::json!({ "statusCode": 400, "body": "Missing body" });
+    };
+    // Function URL events use lowercase header names
+    let header = |name: &str| {
+        payload
+            .get("headers")
+            .and_then(|h| h.get(name))
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+    };
+    let request = IngestRequest {
+        source_hint: payload
+            .get("queryStringParameters")
+            .and_then(|q| q.get("source"))
+            .and_then(|s| s.as_str())
+            .map(str::to_string),
+        github_event: header("x-github-event"),
+        signature: header("x-hub-signature-256"),
+    };
+
+    match deliver_ingest_payload(bot, token, &request, body.as_bytes()).await {
+        Ok(()) => serde_json::json!({ "statusCode": 200, "body": "OK" }),
+        Err(IngestError::UnknownToken) => serde_json::json!({ "statusCode": 404, "body": "Unknown token" }),
+        Err(IngestError::InvalidPayload(e)) => {
+            warn!("❌ Ingest payload rejected: {e}");
+            serde_json::json!({ "statusCode": 400, "body": "Invalid payload" })
+        }
+        Err(IngestError::Unauthorized(e)) => {
+            warn!("❌ Ingest request unauthorized: {e}");
+            serde_json::json!({ "statusCode": 401, "body": "Unauthorized" })
+        }
+        Err(e) => {
+            warn!("❌ Ingest delivery failed: {e}");
+            serde_json::json!({ "statusCode": 502, "body": "Delivery failed" })
+        }
+    }
+}
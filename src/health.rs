@@ -0,0 +1,230 @@
+use async_openai::Client;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::ai::{get_openai_api_key, DEEPSEEK_API_BASE, DEEPSEEK_MODEL_PREFIX, MISTRAL_API_BASE, MISTRAL_MODEL_PREFIXES};
+use crate::storage::{create_storage, DynamoDbStorage};
+use crate::watchdog::{count_event, WatchEvent};
+
+// Samples kept per provider and the window /aistatus reports on
+const MAX_METRIC_SAMPLES: usize = 200;
+const METRICS_WINDOW_SECS: i64 = 24 * 60 * 60;
+// A reachability probe that takes longer than this counts as unreachable
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AiProvider {
+    OpenAi,
+    DeepSeek,
+    Mistral,
+}
+
+impl AiProvider {
+    pub const ALL: [AiProvider; 3] = [AiProvider::OpenAi, AiProvider::DeepSeek, AiProvider::Mistral];
+
+    // Same prefix routing as create_builtin_ai_backend
+    pub fn for_model(model: &str) -> Self {
+        if model.starts_with(DEEPSEEK_MODEL_PREFIX) {
+            AiProvider::DeepSeek
+        } else if MISTRAL_MODEL_PREFIXES.iter().any(|prefix| model.starts_with(prefix)) {
+            AiProvider::Mistral
+        } else {
+            AiProvider::OpenAi
+        }
+    }
+
+    fn key_var(&self) -> &'static str {
+        match self {
+            AiProvider::OpenAi => "OPENAI_API_KEY",
+            AiProvider::DeepSeek => "DEEPSEEK_API_KEY",
+            AiProvider::Mistral => "MISTRAL_API_KEY",
+        }
+    }
+
+    fn api_key(&self) -> Option<String> {
+        match self {
+            AiProvider::OpenAi => get_openai_api_key().ok(),
+            _ => std::env::var(self.key_var()).ok(),
+        }
+    }
+
+    fn api_base(&self) -> Option<&'static str> {
+        match self {
+            AiProvider::OpenAi => None,
+            AiProvider::DeepSeek => Some(DEEPSEEK_API_BASE),
+            AiProvider::Mistral => Some(MISTRAL_API_BASE),
+        }
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.api_key().is_some()
+    }
+
+    // Whether any AI backend has a key, environment or /setup
+    pub fn any_configured() -> bool {
+        Self::ALL.iter().any(AiProvider::is_configured)
+    }
+
+    pub fn metrics_key(&self) -> &'static str {
+        match self {
+            AiProvider::OpenAi => "openai",
+            AiProvider::DeepSeek => "deepseek",
+            AiProvider::Mistral => "mistral",
+        }
+    }
+}
+
+impl fmt::Display for AiProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AiProvider::OpenAi => write!(f, "OpenAI"),
+            AiProvider::DeepSeek => write!(f, "DeepSeek"),
+            AiProvider::Mistral => write!(f, "Mistral"),
+        }
+    }
+}
+
+// One AI request as seen by the bot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallSample {
+    pub at: i64,
+    pub latency_ms: u64,
+    pub ok: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProviderStatus {
+    pub provider: AiProvider,
+    // Probe latency, or why the API could not be reached. None when no key is configured
+    pub reachability: Option<Result<Duration, String>>,
+    pub requests: usize,
+    pub error_rate: f64,
+    pub p50_latency_ms: Option<u64>,
+}
+
+// Remember the outcome of an AI request for /aistatus, failing silently when storage is unavailable
+pub async fn record_ai_call(model: &str, ok: bool, latency: Duration) {
+    count_event(WatchEvent::AiRequest).await;
+    if !ok {
+        count_event(WatchEvent::AiError).await;
+    }
+    let provider = AiProvider::for_model(model);
+    let result = async {
+        let storage = create_storage().await?;
+        let mut samples = load_samples(&storage, provider).await?;
+        samples.push(CallSample {
+            at: chrono::Utc::now().timestamp(),
+            latency_ms: latency.as_millis() as u64,
+            ok,
+        });
+        if samples.len() > MAX_METRIC_SAMPLES {
+            samples.drain(..samples.len() - MAX_METRIC_SAMPLES);
+        }
+        let json = serde_json::to_string(&samples)?;
+        storage.set_ai_metrics(provider.metrics_key(), &json).await?;
+        Ok::<_, Box<dyn Error + Send + Sync>>(())
+    }
+    .await;
+    if let Err(e) = result {
+        warn!("⚠️ Failed to record AI metrics for {provider}: {e}");
+    }
+}
+
+async fn load_samples(
+    storage: &DynamoDbStorage,
+    provider: AiProvider,
+) -> Result<Vec<CallSample>, Box<dyn Error + Send + Sync>> {
+    match storage.get_ai_metrics(provider.metrics_key()).await? {
+        Some(json) => Ok(serde_json::from_str(&json)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+// List the provider's models as a cheap authenticated request
+async fn probe(provider: AiProvider, api_key: String) -> Result<Duration, String> {
+    let mut config = async_openai::config::OpenAIConfig::new().with_api_key(api_key);
+    if let Some(api_base) = provider.api_base() {
+        config = config.with_api_base(api_base);
+    }
+    let client = Client::with_config(config);
+
+    let started = Instant::now();
+    match tokio::time::timeout(PROBE_TIMEOUT, client.models().list()).await {
+        Ok(Ok(_)) => Ok(started.elapsed()),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(format!("no response within {}s", PROBE_TIMEOUT.as_secs())),
+    }
+}
+
+pub async fn check_provider(provider: AiProvider) -> ProviderStatus {
+    let api_key = provider.api_key();
+    let configured = api_key.is_some();
+    let reachability = match api_key {
+        Some(api_key) => Some(probe(provider, api_key).await),
+        None => None,
+    };
+
+    let since = chrono::Utc::now().timestamp() - METRICS_WINDOW_SECS;
+    let samples = match create_storage().await {
+        Ok(storage) => load_samples(&storage, provider).await.unwrap_or_else(|e| {
+            warn!("⚠️ Failed to load AI metrics for {provider}: {e}");
+            Vec::new()
+        }),
+        Err(e) => {
+            warn!("⚠️ Failed to create storage client: {e}");
+            Vec::new()
+        }
+    };
+    let recent: Vec<&CallSample> = samples.iter().filter(|sample| sample.at >= since).collect();
+
+    let errors = recent.iter().filter(|sample| !sample.ok).count();
+    let error_rate = if recent.is_empty() { 0.0 } else { errors as f64 / recent.len() as f64 };
+    // Latency of failed requests mostly measures timeouts, so the median only covers successful ones
+    let mut latencies: Vec<u64> = recent.iter().filter(|sample| sample.ok).map(|sample| sample.latency_ms).collect();
+    latencies.sort_unstable();
+    let p50_latency_ms = latencies.get(latencies.len() / 2).copied();
+
+    info!("🩺 Checked {provider}: configured={configured}, {} recent requests", recent.len());
+    ProviderStatus {
+        provider,
+        reachability,
+        requests: recent.len(),
+        error_rate,
+        p50_latency_ms,
+    }
+}
+
+// Status report for every built-in provider, checked concurrently
+pub async fn format_ai_status(current_model: &str) -> String {
+    let (openai, deepseek, mistral) = tokio::join!(
+        check_provider(AiProvider::OpenAi),
+        check_provider(AiProvider::DeepSeek),
+        check_provider(AiProvider::Mistral)
+    );
+    let current_provider = AiProvider::for_model(current_model);
+
+    let mut report = format!("🩺 AI backend status\n\nCurrent model: {current_model} ({current_provider})\n");
+    for status in [openai, deepseek, mistral] {
+        let line = match &status.reachability {
+            None => format!("⚪ {} - {} not set", status.provider, status.provider.key_var()),
+            Some(Ok(latency)) => format!("✅ {} - reachable ({} ms)", status.provider, latency.as_millis()),
+            Some(Err(e)) => format!("❌ {} - unreachable: {e}", status.provider),
+        };
+        report.push_str(&format!("\n{line}"));
+        if status.requests > 0 {
+            report.push_str(&format!(
+                "\n   Last 24h: {} requests, {:.1}% errors{}",
+                status.requests,
+                status.error_rate * 100.0,
+                status
+                    .p50_latency_ms
+                    .map(|p50| format!(", p50 {p50} ms"))
+                    .unwrap_or_default()
+            ));
+        }
+    }
+    report
+}
@@ -0,0 +1,617 @@
+use hmac::{Hmac, Mac};
+use log::{info, warn};
+use minijinja::context;
+use serde_json::Value;
+use sha2::Sha256;
+use std::error::Error;
+use std::fmt;
+use teloxide::prelude::*;
+use teloxide::types::{MessageId, ThreadId};
+
+use crate::disclaimer::append_disclaimer;
+use crate::pause::is_paused;
+use crate::storage::{create_storage, AlertDigest, DynamoDbStorage, IngestBinding, StorageError};
+use crate::templates::render;
+use crate::watchdog::{count_event, WatchEvent};
+
+// Telegram rejects messages above 4096 characters, leave room for the header
+const MAX_INGEST_BODY_CHARS: usize = 3500;
+// Commits listed individually in a push notification
+const MAX_PUSH_COMMITS: usize = 5;
+// Telegram's limit for a single message, a digest that would outgrow it starts a new message
+const MAX_MESSAGE_CHARS: usize = 4096;
+
+// Request metadata the ingest endpoint needs besides the body
+#[derive(Debug, Default)]
+pub struct IngestRequest {
+    pub source_hint: Option<String>,
+    pub github_event: Option<String>,
+    pub signature: Option<String>, // X-Hub-Signature-256 header value
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IngestSource {
+    Generic,
+    Grafana,
+    TradingView,
+    GitHub,
+}
+
+impl IngestSource {
+    // GitHub is identified by its event header, then the explicit `?source=` hint, then the payload shape
+    pub fn detect(request: &IngestRequest, payload: &Value) -> Self {
+        if request.github_event.is_some() {
+            return IngestSource::GitHub;
+        }
+        match request.source_hint.as_deref().map(str::to_lowercase).as_deref() {
+            Some("grafana") => IngestSource::Grafana,
+            Some("tradingview") => IngestSource::TradingView,
+            Some(_) => IngestSource::Generic,
+            None if payload.get("alerts").is_some_and(Value::is_array) && payload.get("receiver").is_some() => {
+                IngestSource::Grafana
+            }
+            None if payload.get("ticker").is_some() => IngestSource::TradingView,
+            None => IngestSource::Generic,
+        }
+    }
+}
+
+impl fmt::Display for IngestSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IngestSource::Generic => write!(f, "generic"),
+            IngestSource::Grafana => write!(f, "grafana"),
+            IngestSource::TradingView => write!(f, "tradingview"),
+            IngestSource::GitHub => write!(f, "github"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum IngestError {
+    UnknownToken,
+    InvalidPayload(String),
+    Unauthorized(String),
+    Storage(String),
+    Telegram(String),
+}
+
+impl fmt::Display for IngestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IngestError::UnknownToken => write!(f, "Unknown ingest token"),
+            IngestError::InvalidPayload(e) => write!(f, "Invalid payload: {e}"),
+            IngestError::Unauthorized(e) => write!(f, "Unauthorized: {e}"),
+            IngestError::Storage(e) => write!(f, "Storage error: {e}"),
+            IngestError::Telegram(e) => write!(f, "Telegram error: {e}"),
+        }
+    }
+}
+
+impl Error for IngestError {}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((idx, _)) => format!("{}…", &text[..idx]),
+        None => text.to_string(),
+    }
+}
+
+fn format_generic(payload: &Value) -> String {
+    let field = |name: &str| payload.get(name).and_then(Value::as_str).filter(|s| !s.trim().is_empty());
+
+    let title = field("title").or_else(|| field("subject"));
+    let body = field("message").or_else(|| field("text")).or_else(|| field("body"));
+
+    // Without a recognisable title or body the whole payload is shown
+    let body = match (title, body) {
+        (None, None) => {
+            let pretty = serde_json::to_string_pretty(payload).unwrap_or_else(|_| payload.to_string());
+            Some(truncate(&pretty, MAX_INGEST_BODY_CHARS))
+        }
+        (_, body) => body.map(|body| truncate(body, MAX_INGEST_BODY_CHARS)),
+    };
+    render("alert.generic", None, context! { title, body })
+}
+
+fn format_grafana(payload: &Value) -> String {
+    let status = payload.get("status").and_then(Value::as_str).unwrap_or("firing");
+    let icon = if status == "resolved" { "✅" } else { "🔥" };
+    let title = payload.get("title").and_then(Value::as_str).unwrap_or("Grafana alert");
+
+    let mut message = format!("{icon} [{}] {title}", status.to_uppercase());
+
+    if let Some(alerts) = payload.get("alerts").and_then(Value::as_array) {
+        for alert in alerts {
+            let name = alert
+                .pointer("/labels/alertname")
+                .and_then(Value::as_str)
+                .unwrap_or("alert");
+            let alert_status = alert.get("status").and_then(Value::as_str).unwrap_or(status);
+            let summary = alert
+                .pointer("/annotations/summary")
+                .or_else(|| alert.pointer("/annotations/description"))
+                .and_then(Value::as_str);
+            match summary {
+                Some(summary) => message.push_str(&format!("\n• {name} ({alert_status}): {summary}")),
+                None => message.push_str(&format!("\n• {name} ({alert_status})")),
+            }
+        }
+    } else if let Some(body) = payload.get("message").and_then(Value::as_str) {
+        message.push_str("\n\n");
+        message.push_str(body);
+    }
+
+    truncate(&message, MAX_INGEST_BODY_CHARS)
+}
+
+// TradingView placeholders arrive as strings or numbers depending on how the alert message was written
+fn field_text(payload: &Value, pointer: &str) -> Option<String> {
+    match payload.pointer(pointer)? {
+        Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+fn format_tradingview(payload: &Value) -> String {
+    let ticker = field_text(payload, "/ticker")
+        .or_else(|| field_text(payload, "/symbol"))
+        .unwrap_or_else(|| "Unknown symbol".to_string());
+    let symbol = match field_text(payload, "/exchange") {
+        Some(exchange) if !ticker.contains(':') => format!("{exchange}:{ticker}"),
+        _ => ticker,
+    };
+
+    let mut message = format!("📈 TradingView alert: {symbol}");
+
+    if let Some(price) = field_text(payload, "/price").or_else(|| field_text(payload, "/close")) {
+        message.push_str(&format!("\n💵 Price: {price}"));
+    }
+    if let Some(action) = field_text(payload, "/strategy/order_action").or_else(|| field_text(payload, "/action")) {
+        let contracts = field_text(payload, "/strategy/order_contracts");
+        match contracts {
+            Some(contracts) => message.push_str(&format!("\n🧾 Order: {} {contracts}", action.to_uppercase())),
+            None => message.push_str(&format!("\n🧾 Order: {}", action.to_uppercase())),
+        }
+    }
+    if let Some(condition) = field_text(payload, "/condition")
+        .or_else(|| field_text(payload, "/message"))
+        .or_else(|| field_text(payload, "/alert_name"))
+    {
+        message.push_str(&format!("\n📋 Condition: {condition}"));
+    }
+    if let Some(interval) = field_text(payload, "/interval") {
+        message.push_str(&format!("\n⏱ Interval: {interval}"));
+    }
+    if let Some(time) = field_text(payload, "/time").or_else(|| field_text(payload, "/timenow")) {
+        message.push_str(&format!("\n🕒 Time: {time}"));
+    }
+
+    truncate(&message, MAX_INGEST_BODY_CHARS)
+}
+
+fn first_line(text: &str) -> &str {
+    text.lines().next().unwrap_or_default()
+}
+
+// Returns None for GitHub events and actions that are too noisy to forward
+fn format_github(event: &str, payload: &Value) -> Option<String> {
+    let text = |pointer: &str| payload.pointer(pointer).and_then(Value::as_str).unwrap_or_default();
+    let repo = text("/repository/full_name");
+    let sender = text("/sender/login");
+    let action = text("/action");
+
+    let message = match event {
+        "ping" => format!("🏓 GitHub webhook connected for {repo}"),
+        "push" => {
+            let branch = text("/ref").trim_start_matches("refs/heads/");
+            let commits = payload.get("commits").and_then(Value::as_array)?;
+            if commits.is_empty() {
+                return None;
+            }
+            let mut message = format!(
+                "📦 {} pushed {} commit(s) to {repo}:{branch}",
+                text("/pusher/name"),
+                commits.len()
+            );
+            for commit in commits.iter().take(MAX_PUSH_COMMITS) {
+                let id = commit.get("id").and_then(Value::as_str).unwrap_or_default();
+                let summary = first_line(commit.get("message").and_then(Value::as_str).unwrap_or_default());
+                message.push_str(&format!("\n• {} {summary}", id.get(..7).unwrap_or(id)));
+            }
+            if commits.len() > MAX_PUSH_COMMITS {
+                message.push_str(&format!("\n… and {} more", commits.len() - MAX_PUSH_COMMITS));
+            }
+            message.push_str(&format!("\n{}", text("/compare")));
+            message
+        }
+        "pull_request" => {
+            let action = match action {
+                "closed" if payload.pointer("/pull_request/merged") == Some(&Value::Bool(true)) => "merged",
+                "opened" | "closed" | "reopened" | "ready_for_review" => action,
+                _ => return None,
+            };
+            format!(
+                "🔀 PR #{} {action} by {sender} in {repo}: {}\n{}",
+                payload.pointer("/pull_request/number").and_then(Value::as_u64).unwrap_or(0),
+                text("/pull_request/title"),
+                text("/pull_request/html_url")
+            )
+        }
+        "issues" => {
+            if !matches!(action, "opened" | "closed" | "reopened") {
+                return None;
+            }
+            format!(
+                "🐛 Issue #{} {action} by {sender} in {repo}: {}\n{}",
+                payload.pointer("/issue/number").and_then(Value::as_u64).unwrap_or(0),
+                text("/issue/title"),
+                text("/issue/html_url")
+            )
+        }
+        "release" => {
+            if action != "published" {
+                return None;
+            }
+            let tag = text("/release/tag_name");
+            let name = payload
+                .pointer("/release/name")
+                .and_then(Value::as_str)
+                .filter(|name| !name.is_empty())
+                .unwrap_or(tag);
+            format!("🚀 Release {tag} published in {repo}: {name}\n{}", text("/release/html_url"))
+        }
+        _ => return None,
+    };
+
+    Some(truncate(&message, MAX_INGEST_BODY_CHARS))
+}
+
+// Check GitHub's X-Hub-Signature-256 header against the binding's secret
+fn verify_signature(secret: &str, signature: Option<&str>, body: &[u8]) -> Result<(), IngestError> {
+    let signature = signature
+        .and_then(|s| s.strip_prefix("sha256="))
+        .ok_or_else(|| IngestError::Unauthorized("missing X-Hub-Signature-256 header".to_string()))?;
+    let expected = hex::decode(signature)
+        .map_err(|_| IngestError::Unauthorized("malformed signature".to_string()))?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .map_err(|e| IngestError::Unauthorized(e.to_string()))?;
+    mac.update(body);
+    mac.verify_slice(&expected)
+        .map_err(|_| IngestError::Unauthorized("signature mismatch".to_string()))
+}
+
+// Render an inbound payload with the template for its source type, None means nothing to forward
+pub fn format_ingest_message(source: IngestSource, request: &IngestRequest, payload: &Value) -> Option<String> {
+    match source {
+        IngestSource::Generic => Some(format_generic(payload)),
+        IngestSource::Grafana => Some(format_grafana(payload)),
+        IngestSource::TradingView => Some(format_tradingview(payload)),
+        IngestSource::GitHub => format_github(request.github_event.as_deref().unwrap_or_default(), payload),
+    }
+}
+
+// Resolve the token to its bound chat, authenticate the request and forward the formatted payload
+pub async fn deliver_ingest_payload(
+    bot: &Bot,
+    token: &str,
+    request: &IngestRequest,
+    body: &[u8],
+) -> Result<(), IngestError> {
+    let storage = create_storage()
+        .await
+        .map_err(|e| IngestError::Storage(e.to_string()))?;
+    let binding = storage
+        .get_ingest_binding(token)
+        .await
+        .map_err(|e| IngestError::Storage(e.to_string()))?
+        .ok_or(IngestError::UnknownToken)?;
+
+    if let Some(secret) = &binding.secret {
+        verify_signature(secret, request.signature.as_deref(), body)?;
+    }
+
+    let payload: Value = serde_json::from_slice(body)
+        .map_err(|e| IngestError::InvalidPayload(e.to_string()))?;
+    let source = IngestSource::detect(request, &payload);
+
+    if let Some(repository) = &binding.repository {
+        let payload_repo = payload.pointer("/repository/full_name").and_then(Value::as_str);
+        if !payload_repo.is_some_and(|repo| repo.eq_ignore_ascii_case(repository)) {
+            return Err(IngestError::Unauthorized(format!(
+                "binding only accepts events for {repository}"
+            )));
+        }
+    }
+    info!("📥 Delivering {source} ingest payload to chat {}", binding.chat_id);
+
+    let chat_id: i64 = binding
+        .chat_id
+        .parse()
+        .map_err(|_| IngestError::Storage(format!("Invalid chat_id in binding: {}", binding.chat_id)))?;
+    let Some(message) = format_ingest_message(source, request, &payload) else {
+        info!("🔕 Skipping {source} event that is not forwarded");
+        return Ok(());
+    };
+
+    let result = send_alert(bot, &storage, ChatId(chat_id), binding.thread_id, message)
+        .await
+        .map_err(|e| {
+            warn!("❌ Failed to forward {source} ingest payload to chat {chat_id}: {e}");
+            IngestError::Telegram(e.to_string())
+        });
+    if result.is_err() {
+        count_event(WatchEvent::DeliveryFailure).await;
+    }
+    result?;
+    info!("✅ Delivered {source} ingest payload to chat {chat_id}");
+    Ok(())
+}
+
+// Helper function to get the coalescing window for chats that have not chosen one, 0 (the default) disables it
+pub fn get_default_alert_digest_window() -> u64 {
+    std::env::var("ALERT_DIGEST_WINDOW")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0)
+}
+
+// Alerts bound inside a forum topic are posted there, and each topic keeps its own digest and window
+pub fn alert_scope(chat_id: &str, thread_id: Option<i32>) -> String {
+    match thread_id {
+        Some(thread_id) => format!("{chat_id}#{thread_id}"),
+        None => chat_id.to_string(),
+    }
+}
+
+// A topic without its own window uses the chat's, then the default
+async fn resolve_alert_digest_window(
+    storage: &DynamoDbStorage,
+    chat_id: &str,
+    thread_id: Option<i32>,
+) -> Result<u64, StorageError> {
+    let mut window = storage.get_alert_digest_window(&alert_scope(chat_id, thread_id)).await?;
+    if window.is_none() && thread_id.is_some() {
+        window = storage.get_alert_digest_window(chat_id).await?;
+    }
+    Ok(window.unwrap_or_else(get_default_alert_digest_window))
+}
+
+pub async fn get_alert_digest_window(chat_id: &str, thread_id: Option<i32>) -> Result<u64, Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    Ok(resolve_alert_digest_window(&storage, chat_id, thread_id).await?)
+}
+
+pub async fn set_alert_digest_window(
+    chat_id: &str,
+    thread_id: Option<i32>,
+    seconds: u64,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    storage.set_alert_digest_window(&alert_scope(chat_id, thread_id), seconds).await?;
+    Ok(())
+}
+
+// Kinds of alert message a chat can receive silently
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryType {
+    Alert,
+    Digest,
+}
+
+impl DeliveryType {
+    pub const ALL: [DeliveryType; 2] = [DeliveryType::Alert, DeliveryType::Digest];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeliveryType::Alert => "alerts",
+            DeliveryType::Digest => "digests",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|delivery| delivery.name() == value)
+    }
+}
+
+pub async fn get_silent_deliveries(chat_id: &str) -> Result<Vec<DeliveryType>, Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    let names = storage.get_silent_deliveries(chat_id).await?;
+    Ok(DeliveryType::ALL.into_iter().filter(|delivery| names.iter().any(|name| name == delivery.name())).collect())
+}
+
+pub async fn set_delivery_silent(
+    chat_id: &str,
+    delivery: DeliveryType,
+    silent: bool,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    let mut names = storage.get_silent_deliveries(chat_id).await?;
+    names.retain(|name| name != delivery.name());
+    if silent {
+        names.push(delivery.name().to_string());
+    }
+    storage.set_silent_deliveries(chat_id, names).await?;
+    Ok(())
+}
+
+// Opt the user in or out of being mentioned in alerts. Only the user can do this for themselves, and the
+// time of consent is kept with the entry
+pub async fn set_alert_mention(
+    chat_id: &str,
+    thread_id: Option<i32>,
+    user_id: u64,
+    username: &str,
+    enabled: bool,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    let scope = alert_scope(chat_id, thread_id);
+    // Drop earlier entries first, the username may have changed since
+    let previous: Vec<String> = storage
+        .get_alert_mentions(&scope)
+        .await?
+        .into_iter()
+        .filter(|entry| entry.split(':').next() == Some(user_id.to_string().as_str()))
+        .collect();
+    storage.remove_alert_mentions(&scope, previous).await?;
+    if enabled {
+        let entry = format!("{user_id}:{username}:{}", chrono::Utc::now().timestamp());
+        storage.add_alert_mention(&scope, entry).await?;
+    }
+    Ok(())
+}
+
+pub async fn count_alert_mentions(chat_id: &str, thread_id: Option<i32>) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    Ok(storage.get_alert_mentions(&alert_scope(chat_id, thread_id)).await?.len())
+}
+
+// "🔔 @alice @bob" for the members who opted in, failing open to no mentions
+async fn mention_line(storage: &DynamoDbStorage, scope: &str) -> Option<String> {
+    let entries = match storage.get_alert_mentions(scope).await {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("⚠️ Failed to load alert mentions for {scope}: {e}");
+            return None;
+        }
+    };
+    let mut usernames: Vec<String> = entries
+        .iter()
+        .filter_map(|entry| entry.split(':').nth(1))
+        .map(|username| format!("@{username}"))
+        .collect();
+    usernames.sort();
+    (!usernames.is_empty()).then(|| format!("🔔 {}", usernames.join(" ")))
+}
+
+// Send an alert, or fold it into the chat's open digest by editing that message. Edits do not notify,
+// so a burst of alerts produces a single notification. Digest bookkeeping fails open to a plain message
+async fn send_alert(
+    bot: &Bot,
+    storage: &DynamoDbStorage,
+    chat_id: ChatId,
+    thread_id: Option<i32>,
+    message: String,
+) -> ResponseResult<()> {
+    if is_paused(bot, chat_id).await {
+        info!("⏸️ Dropping alert for paused chat {chat_id}");
+        return Ok(());
+    }
+    let key = chat_id.to_string();
+    let scope = alert_scope(&key, thread_id);
+    let thread = thread_id.map(|thread_id| ThreadId(MessageId(thread_id)));
+    let window = match resolve_alert_digest_window(storage, &key, thread_id).await {
+        Ok(window) => window,
+        Err(e) => {
+            warn!("⚠️ Failed to load alert digest window for chat {chat_id}: {e}");
+            0
+        }
+    };
+    let silent = match storage.get_silent_deliveries(&key).await {
+        Ok(names) => names,
+        Err(e) => {
+            warn!("⚠️ Failed to load silent deliveries for chat {chat_id}: {e}");
+            Vec::new()
+        }
+    };
+    let is_silent = |delivery: DeliveryType| silent.iter().any(|name| name == delivery.name());
+    let mentions = mention_line(storage, &scope).await;
+    // Mentions go last and stay on a digest as it grows, though only the first message notifies
+    let finish = |text: String| match &mentions {
+        Some(mentions) => format!("{text}\n\n{mentions}"),
+        None => text,
+    };
+    if window == 0 {
+        let mut request = bot
+            .send_message(chat_id, finish(append_disclaimer(&key, None, message).await))
+            .disable_notification(is_silent(DeliveryType::Alert));
+        if let Some(thread) = thread {
+            request = request.message_thread_id(thread);
+        }
+        request.await?;
+        return Ok(());
+    }
+
+    let open_digest = match storage.get_alert_digest(&scope).await {
+        Ok(digest) => digest,
+        Err(e) => {
+            warn!("⚠️ Failed to load alert digest for chat {chat_id}: {e}");
+            None
+        }
+    };
+    if let Some(mut digest) = open_digest {
+        digest.alerts.push(message.clone());
+        let text = render("alert.digest", None, context! { alerts => digest.alerts });
+        let text = finish(append_disclaimer(&key, None, text).await);
+        if text.chars().count() <= MAX_MESSAGE_CHARS {
+            match bot.edit_message_text(chat_id, MessageId(digest.message_id), text).await {
+                Ok(_) => {
+                    info!("🗂️ Added alert to digest in chat {chat_id} ({} alerts)", digest.alerts.len());
+                    if let Err(e) = storage.set_alert_digest(&scope, &digest).await {
+                        warn!("⚠️ Failed to save alert digest for chat {chat_id}: {e}");
+                    }
+                    return Ok(());
+                }
+                Err(e) => warn!("⚠️ Failed to extend alert digest in chat {chat_id}, sending a new message: {e}"),
+            }
+        }
+    }
+
+    // The message that opens a digest is the only one of it that notifies
+    let mut request = bot
+        .send_message(chat_id, finish(append_disclaimer(&key, None, message.clone()).await))
+        .disable_notification(is_silent(DeliveryType::Digest));
+    if let Some(thread) = thread {
+        request = request.message_thread_id(thread);
+    }
+    let sent = request.await?;
+    let digest = AlertDigest {
+        message_id: sent.id.0,
+        alerts: vec![message],
+        expires_at: chrono::Utc::now().timestamp() + window as i64,
+    };
+    if let Err(e) = storage.set_alert_digest(&scope, &digest).await {
+        warn!("⚠️ Failed to save alert digest for chat {chat_id}: {e}");
+    }
+    Ok(())
+}
+
+// Public URL external systems should POST to for a given token
+pub fn ingest_url(token: &str) -> String {
+    let base = std::env::var("WEBHOOK_URL").unwrap_or_default();
+    let base = base.trim_end_matches('/').trim_end_matches("/webhook");
+    format!("{base}/ingest/{token}")
+}
+
+pub async fn create_ingest_token(
+    chat_id: &str,
+    thread_id: Option<i32>,
+    created_by: u64,
+) -> Result<IngestBinding, Box<dyn Error + Send + Sync>> {
+    let binding = IngestBinding { thread_id, ..IngestBinding::new(chat_id.to_string(), created_by) };
+    let storage = create_storage().await?;
+    storage.create_ingest_binding(&binding).await?;
+    Ok(binding)
+}
+
+pub async fn create_github_ingest_token(
+    chat_id: &str,
+    thread_id: Option<i32>,
+    created_by: u64,
+    repository: &str,
+) -> Result<IngestBinding, Box<dyn Error + Send + Sync>> {
+    let binding = IngestBinding {
+        thread_id,
+        ..IngestBinding::for_github(chat_id.to_string(), created_by, repository.to_string())
+    };
+    let storage = create_storage().await?;
+    storage.create_ingest_binding(&binding).await?;
+    Ok(binding)
+}
+
+pub async fn revoke_ingest_token(token: &str, chat_id: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    Ok(storage.delete_ingest_binding(token, chat_id).await?)
+}
@@ -0,0 +1,205 @@
+use async_openai::{types::CreateEmbeddingRequestArgs, Client};
+use log::{info, warn};
+use std::error::Error;
+
+use crate::ai::get_openai_api_key;
+use crate::storage::{create_storage, KnowledgeChunk};
+
+// Reduced embedding size keeps each stored chunk, and the read of a whole knowledge base, small
+const EMBEDDING_DIMENSIONS: u32 = 256;
+// Chunk length in characters, roughly a few paragraphs
+const CHUNK_CHARS: usize = 1000;
+pub const MAX_KNOWLEDGE_CHUNKS: usize = 100;
+// Largest text document /teach accepts
+pub const MAX_DOCUMENT_BYTES: u32 = 50 * 1024;
+// Chunks added to the system prompt per question, and how similar they must be to count
+const TOP_K: usize = 3;
+const MIN_SIMILARITY: f32 = 0.3;
+
+// Helper function to get the configured embedding model
+pub fn get_embedding_model() -> String {
+    std::env::var("EMBEDDING_MODEL").unwrap_or_else(|_| "text-embedding-3-small".to_string())
+}
+
+async fn embed(inputs: Vec<String>) -> Result<Vec<Vec<f32>>, Box<dyn Error + Send + Sync>> {
+    let client = Client::with_config(async_openai::config::OpenAIConfig::new().with_api_key(get_openai_api_key()?));
+    let request = CreateEmbeddingRequestArgs::default()
+        .model(get_embedding_model())
+        .input(inputs)
+        .dimensions(EMBEDDING_DIMENSIONS)
+        .build()?;
+    let mut response = client.embeddings().create(request).await?;
+    response.data.sort_by_key(|embedding| embedding.index);
+    Ok(response.data.into_iter().map(|embedding| embedding.embedding).collect())
+}
+
+// Embeddings are stored as hex of little-endian f32s, far smaller than a JSON number list
+fn encode_embedding(embedding: &[f32]) -> String {
+    hex::encode(embedding.iter().flat_map(|value| value.to_le_bytes()).collect::<Vec<u8>>())
+}
+
+fn decode_embedding(encoded: &str) -> Option<Vec<f32>> {
+    let bytes = hex::decode(encoded).ok()?;
+    Some(
+        bytes
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect(),
+    )
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 { 0.0 } else { dot / (norm_a * norm_b) }
+}
+
+// Split text on paragraph boundaries into chunks of at most CHUNK_CHARS characters
+fn split_into_chunks(text: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for paragraph in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        if !current.is_empty() && current.chars().count() + paragraph.chars().count() + 2 > CHUNK_CHARS {
+            chunks.push(std::mem::take(&mut current));
+        }
+        // Paragraphs longer than a chunk are cut at character boundaries
+        let chars: Vec<char> = paragraph.chars().collect();
+        for piece in chars.chunks(CHUNK_CHARS) {
+            if !current.is_empty() {
+                if current.chars().count() + piece.len() + 2 > CHUNK_CHARS {
+                    chunks.push(std::mem::take(&mut current));
+                } else {
+                    current.push_str("\n\n");
+                }
+            }
+            current.extend(piece);
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+// Embed and store text taught to a chat, returning the entry id and how many chunks it became
+pub async fn teach(
+    chat_id: &str,
+    source: &str,
+    text: &str,
+    added_by: u64,
+) -> Result<(String, usize), Box<dyn Error + Send + Sync>> {
+    let pieces = split_into_chunks(text);
+    if pieces.is_empty() {
+        return Err("Nothing to learn from an empty text".into());
+    }
+
+    let storage = create_storage().await?;
+    let too_large = |held: usize| {
+        format!(
+            "The knowledge base is limited to {MAX_KNOWLEDGE_CHUNKS} chunks and holds {held}, remove entries with /teach forget <id>"
+        )
+    };
+    // Checked before paying for embeddings, and again by the conditional write
+    let held = storage.get_knowledge_chunk_count(chat_id).await?;
+    if held + pieces.len() > MAX_KNOWLEDGE_CHUNKS {
+        return Err(too_large(held).into());
+    }
+
+    let embeddings = embed(pieces.clone()).await?;
+    let id = uuid::Uuid::new_v4().simple().to_string()[..8].to_string();
+    let added_at = chrono::Utc::now().to_rfc3339();
+    let chunks: Vec<KnowledgeChunk> = pieces
+        .into_iter()
+        .zip(embeddings)
+        .map(|(text, embedding)| KnowledgeChunk {
+            id: id.clone(),
+            source: source.to_string(),
+            text,
+            embedding: encode_embedding(&embedding),
+            added_by,
+            added_at: added_at.clone(),
+        })
+        .collect();
+    if !storage.add_knowledge(chat_id, &chunks, MAX_KNOWLEDGE_CHUNKS).await? {
+        return Err(too_large(storage.get_knowledge_chunk_count(chat_id).await?).into());
+    }
+
+    info!("📚 Taught chat {chat_id} entry {id} from {source} ({} chunks)", chunks.len());
+    Ok((id, chunks.len()))
+}
+
+// One line per taught entry: id, source, chunk count and the start of its text
+pub async fn list_knowledge(chat_id: &str) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    let knowledge = storage.get_knowledge(chat_id).await?;
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut seen: Vec<&str> = Vec::new();
+    for chunk in &knowledge {
+        if seen.contains(&chunk.id.as_str()) {
+            continue;
+        }
+        seen.push(&chunk.id);
+        let chunks = knowledge.iter().filter(|other| other.id == chunk.id).count();
+        let preview: String = chunk.text.chars().take(60).collect();
+        lines.push(format!("{} ({}, {chunks} chunk(s)): {preview}…", chunk.id, chunk.source));
+    }
+    Ok(lines)
+}
+
+// Remove an entry and all its chunks, returning whether it existed
+pub async fn forget(chat_id: &str, id: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    Ok(storage.remove_knowledge(chat_id, id).await?)
+}
+
+pub async fn clear_knowledge(chat_id: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    storage.clear_knowledge(chat_id).await?;
+    Ok(())
+}
+
+// Chunks most relevant to a question, empty when the chat taught nothing. Fails open so a broken
+// knowledge base never blocks an answer
+pub async fn retrieve_knowledge(chat_id: &str, question: &str) -> Vec<String> {
+    let knowledge = match create_storage().await {
+        Ok(storage) => match storage.get_knowledge(chat_id).await {
+            Ok(knowledge) => knowledge,
+            Err(e) => {
+                warn!("⚠️ Failed to load knowledge base, continuing without it: {e}");
+                return Vec::new();
+            }
+        },
+        Err(e) => {
+            warn!("⚠️ Failed to create storage client, continuing without knowledge base: {e}");
+            return Vec::new();
+        }
+    };
+    if knowledge.is_empty() {
+        return Vec::new();
+    }
+
+    let query = match embed(vec![question.to_string()]).await {
+        Ok(mut embeddings) if !embeddings.is_empty() => embeddings.remove(0),
+        Ok(_) => return Vec::new(),
+        Err(e) => {
+            warn!("⚠️ Failed to embed question for knowledge lookup: {e}");
+            return Vec::new();
+        }
+    };
+
+    let mut scored: Vec<(f32, &KnowledgeChunk)> = knowledge
+        .iter()
+        .filter_map(|chunk| {
+            let embedding = decode_embedding(&chunk.embedding)?;
+            Some((cosine_similarity(&query, &embedding), chunk))
+        })
+        .filter(|(score, _)| *score >= MIN_SIMILARITY)
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.truncate(TOP_K);
+
+    info!("📚 Retrieved {} knowledge chunks for chat {chat_id}", scored.len());
+    scored.into_iter().map(|(_, chunk)| chunk.text.clone()).collect()
+}
@@ -0,0 +1,44 @@
+pub mod ai;
+pub mod app;
+mod autodelete;
+mod cache;
+mod chaos;
+pub mod commands;
+mod confirm;
+mod conversation;
+mod convert;
+pub mod deployment;
+mod dialogue;
+mod disclaimer;
+mod flair;
+mod formatter;
+pub mod handlers;
+mod health;
+pub mod ingest;
+mod knowledge;
+mod moderation;
+mod outbound;
+mod pause;
+mod persona;
+pub mod plugins;
+mod progress;
+mod qr;
+mod quota;
+mod risk;
+mod search;
+mod settings;
+mod setup;
+pub mod storage;
+mod summarize;
+mod templates;
+mod transcript;
+mod translate;
+mod typing;
+mod untrusted;
+mod usage;
+mod watchdog;
+#[cfg(feature = "wasm-plugins")]
+mod wasm_plugins;
+
+pub use app::{BotApp, BotAppBuilder};
+pub use plugins::{CommandPlugin, PluginContext};
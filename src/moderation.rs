@@ -0,0 +1,191 @@
+use async_openai::Client;
+use log::{info, warn};
+use std::error::Error;
+use std::fmt;
+
+use crate::ai::get_openai_api_key;
+use crate::storage::create_storage;
+
+// Placeholder for flagged paragraphs of an AI reply in redact mode
+pub const REDACTED_PARAGRAPH: &str = "[redacted]";
+
+// How a chat treats content flagged by the moderation endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModerationMode {
+    Off,
+    // Flagged prompts and flagged replies are refused outright
+    Refuse,
+    // Flagged prompts are refused, flagged paragraphs of a reply are replaced
+    Redact,
+}
+
+impl ModerationMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "off" => Some(ModerationMode::Off),
+            "refuse" | "on" => Some(ModerationMode::Refuse),
+            "redact" => Some(ModerationMode::Redact),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModerationMode::Off => "off",
+            ModerationMode::Refuse => "refuse",
+            ModerationMode::Redact => "redact",
+        }
+    }
+}
+
+impl fmt::Display for ModerationMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+// Outcome of moderating a prompt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptModeration {
+    Clean,
+    Flagged(Vec<String>),
+    // The moderation endpoint could not be reached, so the prompt is not sent
+    Unavailable,
+}
+
+// Outcome of moderating an AI reply
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplyModeration {
+    Clean,
+    Redacted(String),
+    Withheld(Vec<String>),
+    // The moderation endpoint could not be reached, so the reply is not shown
+    Unavailable,
+}
+
+// Helper function to get the moderation mode for chats that have not chosen one
+pub fn get_default_moderation_mode() -> ModerationMode {
+    std::env::var("MODERATION_DEFAULT")
+        .ok()
+        .and_then(|v| ModerationMode::parse(&v))
+        .unwrap_or(ModerationMode::Off)
+}
+
+// Helper function to get the configured moderation model
+pub fn get_moderation_model() -> String {
+    std::env::var("MODERATION_MODEL").unwrap_or_else(|_| "omni-moderation-latest".to_string())
+}
+
+pub async fn get_moderation_mode(chat_id: &str) -> ModerationMode {
+    let mode = match create_storage().await {
+        Ok(storage) => match storage.get_moderation_mode(chat_id).await {
+            Ok(mode) => mode.as_deref().and_then(ModerationMode::parse),
+            Err(e) => {
+                warn!("⚠️ Failed to get moderation mode from storage: {e}");
+                None
+            }
+        },
+        Err(e) => {
+            warn!("⚠️ Failed to create storage client: {e}");
+            None
+        }
+    };
+    mode.unwrap_or_else(get_default_moderation_mode)
+}
+
+// Moderation fails closed, so turning it on without an OpenAI key would refuse every AI reply
+pub fn validate_moderation_mode(mode: ModerationMode) -> Result<ModerationMode, String> {
+    if mode != ModerationMode::Off && get_openai_api_key().is_err() {
+        return Err("Moderation needs OPENAI_API_KEY, which is not configured for this bot.".to_string());
+    }
+    Ok(mode)
+}
+
+pub async fn set_moderation_mode(chat_id: &str, mode: ModerationMode) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    storage.set_moderation_mode(chat_id, mode.as_str()).await?;
+    Ok(())
+}
+
+// Classify each input, returning the flagged category names per input (empty when clean)
+async fn classify(inputs: &[&str]) -> Result<Vec<Vec<String>>, Box<dyn Error + Send + Sync>> {
+    let api_key = get_openai_api_key()?;
+    let client = Client::with_config(async_openai::config::OpenAIConfig::new().with_api_key(api_key));
+
+    // The category set differs between moderation models, so the raw JSON response is used here
+    let request = serde_json::json!({
+        "model": get_moderation_model(),
+        "input": inputs,
+    });
+    let response: serde_json::Value = client.moderations().create_byot(request).await?;
+    let Some(results) = response["results"].as_array() else {
+        return Err("No results in moderation response".into());
+    };
+
+    Ok(results
+        .iter()
+        .map(|result| {
+            result["categories"]
+                .as_object()
+                .map(|categories| {
+                    categories
+                        .iter()
+                        .filter(|(_, flagged)| flagged.as_bool() == Some(true))
+                        .map(|(category, _)| category.clone())
+                        .collect()
+                })
+                .unwrap_or_default()
+        })
+        .collect())
+}
+
+// Check a prompt, failing closed when the moderation endpoint is unavailable since the chat asked for filtering
+pub async fn moderate_prompt(chat_id: &str, prompt: &str) -> PromptModeration {
+    match classify(&[prompt]).await {
+        Ok(mut results) => {
+            let categories = results.pop().unwrap_or_default();
+            if categories.is_empty() {
+                return PromptModeration::Clean;
+            }
+            info!("🛡️ Prompt in chat {chat_id} flagged for {}", categories.join(", "));
+            PromptModeration::Flagged(categories)
+        }
+        Err(e) => {
+            warn!("⚠️ Prompt moderation failed for chat {chat_id}, refusing it: {e}");
+            PromptModeration::Unavailable
+        }
+    }
+}
+
+// Check a reply paragraph by paragraph, failing closed when the moderation endpoint is unavailable
+pub async fn moderate_reply(chat_id: &str, reply: &str, mode: ModerationMode) -> ReplyModeration {
+    let paragraphs: Vec<&str> = reply.split("\n\n").collect();
+    let results = match classify(&paragraphs).await {
+        Ok(results) => results,
+        Err(e) => {
+            warn!("⚠️ Reply moderation failed for chat {chat_id}, withholding it: {e}");
+            return ReplyModeration::Unavailable;
+        }
+    };
+
+    let mut flagged: Vec<String> = results.iter().flatten().cloned().collect();
+    if flagged.is_empty() {
+        return ReplyModeration::Clean;
+    }
+    flagged.sort();
+    flagged.dedup();
+    info!("🛡️ AI reply in chat {chat_id} flagged for {}", flagged.join(", "));
+
+    match mode {
+        ModerationMode::Redact => {
+            let redacted = paragraphs
+                .iter()
+                .zip(&results)
+                .map(|(paragraph, categories)| if categories.is_empty() { *paragraph } else { REDACTED_PARAGRAPH })
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            ReplyModeration::Redacted(redacted)
+        }
+        _ => ReplyModeration::Withheld(flagged),
+    }
+}
@@ -0,0 +1,123 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use teloxide::{
+    prelude::*,
+    types::{MessageEntity, ParseMode},
+};
+
+use crate::storage::create_storage;
+
+// Sends kept when OUTBOUND_DEBUG_SIZE is unset, and the most that can be kept, one storage read's worth
+const DEFAULT_DEBUG_SIZE: usize = 50;
+const MAX_DEBUG_SIZE: usize = 100;
+// Longest text kept per captured field, so every record fits in its storage item
+const MAX_CAPTURED_BYTES: usize = 64 * 1024;
+
+// Helper function to check whether AI replies are captured for /outbound, off by default since
+// the captures hold message contents. Fixed bot messages, edits, documents and keyboards are not captured
+pub fn outbound_debug_enabled() -> bool {
+    std::env::var("OUTBOUND_DEBUG")
+        .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "on"))
+        .unwrap_or(false)
+}
+
+// Helper function to get how many of the latest sends are kept
+pub fn get_outbound_debug_size() -> usize {
+    std::env::var("OUTBOUND_DEBUG_SIZE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|size| *size > 0)
+        .unwrap_or(DEFAULT_DEBUG_SIZE)
+        .min(MAX_DEBUG_SIZE)
+}
+
+// Cut text to at most MAX_CAPTURED_BYTES on a character boundary
+fn truncate_captured(text: &str) -> String {
+    if text.len() <= MAX_CAPTURED_BYTES {
+        return text.to_string();
+    }
+    let mut end = MAX_CAPTURED_BYTES;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}… [truncated]", &text[..end])
+}
+
+// One AI reply sent to Telegram: the model's text, the payload built from it, and how Telegram took it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboundRecord {
+    // Position in the capture sequence, telling a record apart from an older one left in the same slot
+    #[serde(default)]
+    pub seq: u64,
+    pub at: i64,
+    pub chat_id: i64,
+    pub source: String,
+    pub parse_mode: Option<String>,
+    pub text: String,
+    // The entities Telegram parsed out of the text, empty when the send failed
+    #[serde(default)]
+    pub entities: Vec<MessageEntity>,
+    pub error: Option<String>,
+}
+
+// Capture a send when OUTBOUND_DEBUG is on, failing silently when storage is unavailable
+pub async fn capture_outbound(
+    chat_id: ChatId,
+    source: &str,
+    text: &str,
+    parse_mode: Option<ParseMode>,
+    result: &ResponseResult<Message>,
+) {
+    if !outbound_debug_enabled() {
+        return;
+    }
+    let mut record = OutboundRecord {
+        seq: 0,
+        at: chrono::Utc::now().timestamp(),
+        chat_id: chat_id.0,
+        source: truncate_captured(source),
+        parse_mode: parse_mode.map(|mode| format!("{mode:?}")),
+        text: truncate_captured(text),
+        entities: result
+            .as_ref()
+            .ok()
+            .and_then(|message| message.entities())
+            .map(<[MessageEntity]>::to_vec)
+            .unwrap_or_default(),
+        error: result.as_ref().err().map(|e| e.to_string()),
+    };
+    // Each send takes the next slot of the ring, so concurrent captures never overwrite each other
+    let result = async {
+        let storage = create_storage().await?;
+        record.seq = storage.next_outbound_sequence().await?;
+        let slot = record.seq % get_outbound_debug_size() as u64;
+        storage.set_outbound_record(slot, &serde_json::to_string(&record)?).await?;
+        Ok::<_, Box<dyn Error + Send + Sync>>(())
+    }
+    .await;
+    if let Err(e) = result {
+        warn!("⚠️ Failed to capture outbound message for chat {chat_id}: {e}");
+    }
+}
+
+// The latest captured sends, newest last
+pub async fn recent_outbound(count: usize) -> Result<Vec<OutboundRecord>, Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    let size = get_outbound_debug_size() as u64;
+    let latest = storage.get_outbound_sequence().await?;
+    let first = latest.saturating_sub((count as u64).min(size)) + 1;
+    let wanted: Vec<u64> = (first..=latest).collect();
+    let slots: Vec<u64> = wanted.iter().map(|seq| seq % size).collect();
+
+    let mut records = Vec::new();
+    for json in storage.get_outbound_records(&slots).await? {
+        let record: OutboundRecord = serde_json::from_str(&json)?;
+        // Slots still holding an older capture, e.g. after OUTBOUND_DEBUG_SIZE changed, are skipped
+        if wanted.contains(&record.seq) {
+            records.push(record);
+        }
+    }
+    records.sort_by_key(|record| record.seq);
+    Ok(records)
+}
@@ -0,0 +1,141 @@
+use log::{info, warn};
+use std::error::Error;
+use std::time::Duration;
+use teloxide::prelude::*;
+
+use crate::storage::create_storage;
+
+// Long pauses are more likely forgotten than intended
+const MAX_PAUSE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+// Parse a duration like 90s, 30m, 2h or 1d
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim().to_lowercase();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("'{value}' is not a duration, use e.g. 30m, 2h or 1d"))?;
+    let unit_seconds: u64 = match unit {
+        "s" => 1,
+        "m" | "min" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("Unknown unit in '{value}', use s, m, h or d")),
+    };
+    let seconds = amount
+        .checked_mul(unit_seconds)
+        .ok_or_else(|| format!("'{value}' is too long"))?;
+    Ok(Duration::from_secs(seconds))
+}
+
+// Format seconds in the largest unit that divides them, so parse_duration reads it back
+pub fn format_duration(seconds: u64) -> String {
+    match seconds {
+        s if s % 3600 == 0 => format!("{}h", s / 3600),
+        s if s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{s}s"),
+    }
+}
+
+pub fn parse_pause_duration(value: &str) -> Result<Duration, String> {
+    let duration = parse_duration(value)?;
+    if duration < Duration::from_secs(60) || duration > MAX_PAUSE {
+        return Err("A pause must last between 1 minute and 7 days.".to_string());
+    }
+    Ok(duration)
+}
+
+// Pause the chat and return when it ends, as a unix timestamp
+pub async fn pause_chat(bot: &Bot, chat_id: ChatId, duration: Duration) -> Result<i64, Box<dyn Error + Send + Sync>> {
+    let until = chrono::Utc::now().timestamp() + duration.as_secs() as i64;
+    let storage = create_storage().await?;
+    storage.set_paused_until(&chat_id.to_string(), Some(until)).await?;
+    info!("⏸️ Chat {chat_id} paused until {until}");
+
+    // Announce the end on time where the process keeps running; on Lambda the next update or alert does it
+    let bot = bot.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(duration).await;
+        is_paused(&bot, chat_id).await;
+    });
+    Ok(until)
+}
+
+// End a pause early, returning whether the chat was paused
+pub async fn resume_chat(chat_id: ChatId) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    let key = chat_id.to_string();
+    let paused = storage
+        .get_paused_until(&key)
+        .await?
+        .is_some_and(|until| until > chrono::Utc::now().timestamp());
+    storage.set_paused_until(&key, None).await?;
+    Ok(paused)
+}
+
+// When a paused chat resumes, as a unix timestamp
+pub async fn get_paused_until(chat_id: ChatId) -> Option<i64> {
+    let storage = create_storage().await.ok()?;
+    storage
+        .get_paused_until(&chat_id.to_string())
+        .await
+        .ok()
+        .flatten()
+        .filter(|until| *until > chrono::Utc::now().timestamp())
+}
+
+// Whether the bot should hold back unprompted output in a chat. Whoever first sees an expired pause
+// clears it and tells the chat. Fails open to not paused
+pub async fn is_paused(bot: &Bot, chat_id: ChatId) -> bool {
+    let storage = match create_storage().await {
+        Ok(storage) => storage,
+        Err(e) => {
+            warn!("⚠️ Failed to create storage client, treating chat as not paused: {e}");
+            return false;
+        }
+    };
+    let key = chat_id.to_string();
+    let until = match storage.get_paused_until(&key).await {
+        Ok(Some(until)) => until,
+        Ok(None) => return false,
+        Err(e) => {
+            warn!("⚠️ Failed to load pause for chat {chat_id}, treating it as not paused: {e}");
+            return false;
+        }
+    };
+    if until > chrono::Utc::now().timestamp() {
+        return true;
+    }
+
+    match storage.clear_pause_if(&key, until).await {
+        Ok(true) => {
+            info!("▶️ Pause expired in chat {chat_id}");
+            if let Err(e) = bot.send_message(chat_id, "▶️ The pause is over, alerts and replies are back on.").await {
+                warn!("⚠️ Failed to announce the end of the pause in chat {chat_id}: {e}");
+            }
+        }
+        Ok(false) => {}
+        Err(e) => warn!("⚠️ Failed to clear expired pause for chat {chat_id}: {e}"),
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_units() {
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("30m"), Ok(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_duration("2H"), Ok(Duration::from_secs(2 * 60 * 60)));
+        assert_eq!(parse_duration("1d"), Ok(Duration::from_secs(24 * 60 * 60)));
+    }
+
+    #[test]
+    fn rejects_durations_that_overflow() {
+        assert_eq!(parse_duration("999999999999999d"), Err("'999999999999999d' is too long".to_string()));
+        assert!(parse_pause_duration("999999999999999d").is_err());
+    }
+}
@@ -0,0 +1,194 @@
+use log::{info, warn};
+use std::error::Error;
+use std::fmt;
+
+use crate::storage::{create_storage, PersonaSettings};
+
+// Custom personas a single chat may define
+const MAX_CUSTOM_PERSONAS: usize = 10;
+const MAX_PERSONA_NAME_LEN: usize = 32;
+
+#[derive(Debug, Clone)]
+pub struct Persona {
+    pub name: String,
+    pub description: String,
+    pub system_prompt: String,
+    pub temperature: Option<f32>,
+}
+
+impl Persona {
+    fn built_in(name: &str, description: &str, system_prompt: &str, temperature: f32) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            system_prompt: system_prompt.to_string(),
+            temperature: Some(temperature),
+        }
+    }
+
+    fn custom(name: &str, system_prompt: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: "custom persona".to_string(),
+            system_prompt: system_prompt.to_string(),
+            temperature: None,
+        }
+    }
+}
+
+pub fn built_in_personas() -> Vec<Persona> {
+    vec![
+        Persona::built_in(
+            "translator",
+            "translates messages between languages",
+            "You are a professional translator. Detect the language of each message and translate it into English, \
+             or into the language the user asks for. Preserve meaning, tone and formatting, and reply with the translation only.",
+            0.3,
+        ),
+        Persona::built_in(
+            "reviewer",
+            "reviews code for bugs, style and design",
+            "You are a senior software engineer doing code review. Point out bugs, security issues, unclear naming and \
+             design problems in the code you are given, ordered by severity, and suggest concrete fixes.",
+            0.2,
+        ),
+        Persona::built_in(
+            "analyst",
+            "answers like a financial analyst",
+            "You are a careful financial analyst. Explain markets, companies and financial concepts with numbers and \
+             clear reasoning, state your assumptions, and never present your answer as personal investment advice.",
+            0.4,
+        ),
+        Persona::built_in(
+            "eli5",
+            "explains anything like you're five",
+            "Explain everything as if talking to a curious five-year-old: short sentences, everyday analogies, no jargon.",
+            0.7,
+        ),
+    ]
+}
+
+#[derive(Debug)]
+pub enum PersonaError {
+    InvalidName(String),
+    UnknownPersona(String),
+    ReservedName(String),
+    TooMany,
+    Storage(String),
+}
+
+impl fmt::Display for PersonaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PersonaError::InvalidName(name) => write!(
+                f,
+                "Invalid persona name '{name}': use up to {MAX_PERSONA_NAME_LEN} lowercase letters, digits, '-' or '_'"
+            ),
+            PersonaError::UnknownPersona(name) => write!(f, "Unknown persona: {name}"),
+            PersonaError::ReservedName(name) => write!(f, "'{name}' is a built-in persona and cannot be redefined"),
+            PersonaError::TooMany => write!(f, "A chat can define at most {MAX_CUSTOM_PERSONAS} custom personas"),
+            PersonaError::Storage(e) => write!(f, "Storage error: {e}"),
+        }
+    }
+}
+
+impl Error for PersonaError {}
+
+fn validate_name(name: &str) -> Result<String, PersonaError> {
+    let name = name.trim().to_lowercase();
+    let valid = !name.is_empty()
+        && name.len() <= MAX_PERSONA_NAME_LEN
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid { Ok(name) } else { Err(PersonaError::InvalidName(name)) }
+}
+
+fn find_persona(settings: &PersonaSettings, name: &str) -> Option<Persona> {
+    built_in_personas()
+        .into_iter()
+        .find(|persona| persona.name == name)
+        .or_else(|| settings.custom.get(name).map(|prompt| Persona::custom(name, prompt)))
+}
+
+async fn load_settings(chat_id: &str) -> Result<PersonaSettings, PersonaError> {
+    let storage = create_storage().await.map_err(|e| PersonaError::Storage(e.to_string()))?;
+    storage
+        .get_persona_settings(chat_id)
+        .await
+        .map_err(|e| PersonaError::Storage(e.to_string()))
+}
+
+async fn save_settings(chat_id: &str, settings: &PersonaSettings) -> Result<(), PersonaError> {
+    let storage = create_storage().await.map_err(|e| PersonaError::Storage(e.to_string()))?;
+    storage
+        .set_persona_settings(chat_id, settings)
+        .await
+        .map_err(|e| PersonaError::Storage(e.to_string()))
+}
+
+// Active persona for a chat, falling back to none when storage is unavailable
+pub async fn get_chat_persona(chat_id: &str) -> Option<Persona> {
+    match load_settings(chat_id).await {
+        Ok(settings) => {
+            let persona = settings.active.as_deref().and_then(|name| find_persona(&settings, name));
+            if let Some(persona) = &persona {
+                info!("🎭 Using persona '{}' for chat {chat_id}", persona.name);
+            }
+            persona
+        }
+        Err(e) => {
+            warn!("⚠️ Failed to load persona settings, using no persona: {e}");
+            None
+        }
+    }
+}
+
+// All personas available to a chat plus the name of the active one
+pub async fn list_chat_personas(chat_id: &str) -> Result<(Vec<Persona>, Option<String>), PersonaError> {
+    let settings = load_settings(chat_id).await?;
+    let mut personas = built_in_personas();
+    personas.extend(settings.custom.iter().map(|(name, prompt)| Persona::custom(name, prompt)));
+    Ok((personas, settings.active))
+}
+
+// Switch the active persona, `None` returns to the model's default behaviour
+pub async fn set_chat_persona(chat_id: &str, name: Option<&str>) -> Result<Option<Persona>, PersonaError> {
+    let mut settings = load_settings(chat_id).await?;
+    let persona = match name {
+        Some(name) => {
+            let name = validate_name(name)?;
+            Some(find_persona(&settings, &name).ok_or(PersonaError::UnknownPersona(name))?)
+        }
+        None => None,
+    };
+    settings.active = persona.as_ref().map(|persona| persona.name.clone());
+    save_settings(chat_id, &settings).await?;
+    Ok(persona)
+}
+
+pub async fn add_custom_persona(chat_id: &str, name: &str, system_prompt: &str) -> Result<(), PersonaError> {
+    let name = validate_name(name)?;
+    if built_in_personas().iter().any(|persona| persona.name == name) {
+        return Err(PersonaError::ReservedName(name));
+    }
+
+    let mut settings = load_settings(chat_id).await?;
+    if !settings.custom.contains_key(&name) && settings.custom.len() >= MAX_CUSTOM_PERSONAS {
+        return Err(PersonaError::TooMany);
+    }
+    settings.custom.insert(name, system_prompt.trim().to_string());
+    save_settings(chat_id, &settings).await
+}
+
+// Returns whether a custom persona was removed, deactivating it if it was in use
+pub async fn remove_custom_persona(chat_id: &str, name: &str) -> Result<bool, PersonaError> {
+    let name = validate_name(name)?;
+    let mut settings = load_settings(chat_id).await?;
+    if settings.custom.remove(&name).is_none() {
+        return Ok(false);
+    }
+    if settings.active.as_deref() == Some(name.as_str()) {
+        settings.active = None;
+    }
+    save_settings(chat_id, &settings).await?;
+    Ok(true)
+}
@@ -0,0 +1,154 @@
+use async_trait::async_trait;
+use log::{info, warn};
+use serde::{de::DeserializeOwned, Serialize};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use teloxide::{prelude::*, types::BotCommand, utils::command::BotCommands};
+
+use crate::commands::{command_available, run_ai_task, Command};
+use crate::dialogue::{clear_dialogue_state, load_dialogue_state, save_dialogue_state};
+
+pub use crate::dialogue::DEFAULT_DIALOGUE_TTL;
+
+// Services a plugin command can use while handling a message
+pub struct PluginContext {
+    pub bot: Bot,
+    pub message: Message,
+}
+
+impl PluginContext {
+    pub fn chat_id(&self) -> ChatId {
+        self.message.chat.id
+    }
+
+    pub fn user_id(&self) -> u64 {
+        self.message.from.as_ref().map(|user| user.id.0).unwrap_or(0)
+    }
+
+    // Reply in the chat the command came from
+    pub async fn reply(&self, text: impl Into<String>) -> ResponseResult<Message> {
+        self.bot.send_message(self.chat_id(), text).await
+    }
+
+    // Run a one-off request on the chat's AI model, subject to the chat's settings and budgets
+    pub async fn ask_ai(&self, instructions: &str, input: &str) -> Result<String, String> {
+        let locale = self.message.from.as_ref().and_then(|user| user.language_code.as_deref());
+        run_ai_task(&self.chat_id().to_string(), self.user_id(), locale, instructions, input).await
+    }
+
+    // State a multi-step plugin command keeps between messages of this user in this chat.
+    // It is stored rather than held in the plugin, as the next message may reach another replica
+    pub async fn load_state<T: DeserializeOwned>(&self, scope: &str) -> Result<Option<T>, String> {
+        load_dialogue_state(&self.chat_id().to_string(), self.user_id(), scope)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn save_state<T: Serialize>(&self, scope: &str, state: &T, ttl: Duration) -> Result<(), String> {
+        save_dialogue_state(&self.chat_id().to_string(), self.user_id(), scope, state, ttl)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn clear_state(&self, scope: &str) -> Result<(), String> {
+        clear_dialogue_state(&self.chat_id().to_string(), self.user_id(), scope)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+// A command contributed from outside commands.rs, registered through BotAppBuilder::plugin
+#[async_trait]
+pub trait CommandPlugin: Send + Sync {
+    // Command name without the leading slash, e.g. "weather"
+    fn name(&self) -> &str;
+    fn description(&self) -> &str;
+    async fn handle(&self, context: PluginContext, args: String) -> ResponseResult<()>;
+}
+
+static PLUGINS: OnceLock<Vec<Arc<dyn CommandPlugin>>> = OnceLock::new();
+
+fn is_valid_command_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 32
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+// Install the plugin set, skipping invalid names and names taken by built-in commands
+pub fn register_plugins(plugins: Vec<Arc<dyn CommandPlugin>>) -> bool {
+    let built_in: Vec<String> = Command::bot_commands().into_iter().map(|command| command.command).collect();
+    let mut accepted: Vec<Arc<dyn CommandPlugin>> = Vec::with_capacity(plugins.len());
+    for plugin in plugins {
+        let name = plugin.name();
+        let taken = built_in.iter().any(|command| command.trim_start_matches('/') == name)
+            || accepted.iter().any(|other| other.name() == name);
+        if !is_valid_command_name(name) {
+            warn!("⚠️ Ignoring plugin with invalid command name '{name}'");
+        } else if taken {
+            warn!("⚠️ Ignoring plugin '{name}': the command already exists");
+        } else {
+            info!("🧩 Registered plugin command /{name}");
+            accepted.push(plugin);
+        }
+    }
+    PLUGINS.set(accepted).is_ok()
+}
+
+pub fn registered_plugins() -> &'static [Arc<dyn CommandPlugin>] {
+    PLUGINS.get().map(Vec::as_slice).unwrap_or_default()
+}
+
+// Match "/name[@bot] args" against the registered plugins
+pub fn find_plugin_command(text: &str) -> Option<(Arc<dyn CommandPlugin>, String)> {
+    let text = text.trim();
+    let (command, args) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let name = command.strip_prefix('/')?;
+    let name = name.split_once('@').map_or(name, |(name, _)| name).to_lowercase();
+    registered_plugins()
+        .iter()
+        .find(|plugin| plugin.name() == name)
+        .map(|plugin| (plugin.clone(), args.trim().to_string()))
+}
+
+// Built-in commands whose integrations are not configured, without the leading slash
+fn unavailable_commands() -> Vec<String> {
+    Command::bot_commands()
+        .into_iter()
+        .map(|command| command.command.trim_start_matches('/').to_string())
+        .filter(|name| !command_available(name))
+        .collect()
+}
+
+// Built-in command help followed by the plugin commands
+pub fn command_descriptions() -> String {
+    let unavailable = unavailable_commands();
+    let mut descriptions = Command::descriptions()
+        .to_string()
+        .lines()
+        .filter(|line| !unavailable.iter().any(|name| line.starts_with(&format!("/{name} "))))
+        .collect::<Vec<_>>()
+        .join("\n");
+    for plugin in registered_plugins() {
+        descriptions.push_str(&format!("\n/{} — {}", plugin.name(), plugin.description()));
+    }
+    descriptions
+}
+
+// Telegram rejects command menu descriptions longer than this
+const MAX_MENU_DESCRIPTION_CHARS: usize = 256;
+
+fn truncate_description(description: &str) -> String {
+    description.chars().take(MAX_MENU_DESCRIPTION_CHARS).collect()
+}
+
+// Command list for Telegram's command menu
+pub fn bot_commands() -> Vec<BotCommand> {
+    let mut commands = Command::bot_commands();
+    commands.retain(|command| command_available(command.command.trim_start_matches('/')));
+    commands.extend(
+        registered_plugins()
+            .iter()
+            .map(|plugin| BotCommand::new(plugin.name(), truncate_description(plugin.description()))),
+    );
+    commands
+}
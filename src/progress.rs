@@ -0,0 +1,321 @@
+use log::{info, warn};
+use std::error::Error;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup, MessageId},
+};
+use tokio::sync::{watch, Notify};
+use tokio::task::JoinHandle;
+
+use crate::storage::{create_storage, DynamoDbStorage};
+
+// Operations finishing sooner than this never show a progress message
+const PROGRESS_DELAY: Duration = Duration::from_secs(5);
+// How often a running task checks whether it was stopped, the request may land on another replica
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_secs(2);
+// Running entries older than this belong to a process that died without cleaning up
+const MAX_TASK_AGE_SECS: i64 = 60 * 60;
+const CALLBACK_PREFIX: &str = "progress:";
+
+// Reply for an operation the user stopped from its progress message or with /cancel
+pub const TASK_CANCELLED: &str = "⏹ Stopped, nothing else will be sent for this request.";
+
+// A task registered in storage while it runs, stored as "<task_id>:<user_id>:<started_at>"
+struct RunningTask {
+    id: String,
+    user_id: u64,
+    started_at: i64,
+}
+
+impl RunningTask {
+    fn parse(entry: &str) -> Option<Self> {
+        let mut parts = entry.splitn(3, ':');
+        Some(Self {
+            id: parts.next()?.to_string(),
+            user_id: parts.next()?.parse().ok()?,
+            started_at: parts.next()?.parse().ok()?,
+        })
+    }
+
+    fn entry(&self) -> String {
+        format!("{}:{}:{}", self.id, self.user_id, self.started_at)
+    }
+}
+
+// The task's storage client once built, reused by every poll and the cleanup, and the progress message once posted
+#[derive(Default)]
+struct Shown {
+    storage: Option<Arc<DynamoDbStorage>>,
+    message_id: Option<MessageId>,
+}
+
+// A slow operation that /cancel can stop from the start, with a progress message posted once it has run
+// for a few seconds and edited as it moves through stages. The message carries a stop button and is deleted
+// when dropped
+pub struct TaskProgress {
+    stage: watch::Sender<String>,
+    cancel: Arc<Notify>,
+    task: JoinHandle<()>,
+    shown: Arc<Mutex<Shown>>,
+    // Running entry of this task, removed on drop whether or not registering was seen to finish
+    entry: String,
+    bot: Bot,
+    chat_id: ChatId,
+}
+
+impl TaskProgress {
+    pub fn start(bot: &Bot, chat_id: ChatId, user_id: u64, title: &str) -> Self {
+        let id = uuid::Uuid::new_v4().simple().to_string()[..12].to_string();
+        let cancel = Arc::new(Notify::new());
+        let (stage, mut stages) = watch::channel("Starting…".to_string());
+        let shown = Arc::new(Mutex::new(Shown::default()));
+        let running = RunningTask { id: id.clone(), user_id, started_at: chrono::Utc::now().timestamp() };
+        let entry = running.entry();
+        let task = {
+            let bot = bot.clone();
+            let title = title.to_string();
+            let cancel = cancel.clone();
+            let shown = shown.clone();
+            let keyboard = InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback(
+                "⏹ Stop",
+                format!("{CALLBACK_PREFIX}{id}"),
+            )]]);
+            tokio::spawn(async move {
+                // Registered straight away so /cancel finds the task before its progress message shows
+                let storage = match create_storage().await {
+                    Ok(storage) => {
+                        let storage = Arc::new(storage);
+                        shown.lock().expect("progress message lock poisoned").storage = Some(storage.clone());
+                        Some(storage)
+                    }
+                    Err(e) => {
+                        warn!("⚠️ Failed to create storage client for task {id} in chat {chat_id}: {e}");
+                        None
+                    }
+                };
+                let storage = match storage {
+                    Some(storage) => match storage.add_running_task(&chat_id.to_string(), running.entry()).await {
+                        Ok(()) => Some(storage),
+                        Err(e) => {
+                            warn!("⚠️ Failed to register task {id} in chat {chat_id}, it can't be stopped: {e}");
+                            None
+                        }
+                    },
+                    None => None,
+                };
+                let stoppable = storage.is_some();
+
+                let mut poll = tokio::time::interval_at(
+                    tokio::time::Instant::now() + CANCEL_POLL_INTERVAL,
+                    CANCEL_POLL_INTERVAL,
+                );
+                // Only the visible message waits, quick replies never show one
+                let delay = tokio::time::sleep(PROGRESS_DELAY);
+                tokio::pin!(delay);
+                loop {
+                    tokio::select! {
+                        _ = &mut delay => break,
+                        _ = poll.tick(), if stoppable => {
+                            if let Some(storage) = &storage
+                                && stop_if_requested(storage, &id, chat_id, &cancel).await
+                            {
+                                return;
+                            }
+                        }
+                    }
+                }
+
+                let text = format!("⏳ {title}\n\n{}", *stages.borrow_and_update());
+                let mut request = bot.send_message(chat_id, text);
+                if stoppable {
+                    request = request.reply_markup(keyboard.clone());
+                }
+                let message = match request.await {
+                    Ok(message) => message,
+                    Err(e) => {
+                        warn!("⚠️ Failed to send progress message to chat {chat_id}: {e}");
+                        return;
+                    }
+                };
+                shown.lock().expect("progress message lock poisoned").message_id = Some(message.id);
+
+                loop {
+                    tokio::select! {
+                        changed = stages.changed() => {
+                            // The sender is dropped with the TaskProgress
+                            if changed.is_err() {
+                                return;
+                            }
+                            let text = format!("⏳ {title}\n\n{}", *stages.borrow_and_update());
+                            let mut request = bot.edit_message_text(chat_id, message.id, text);
+                            if stoppable {
+                                request = request.reply_markup(keyboard.clone());
+                            }
+                            // A missed edit only leaves an older stage showing
+                            if let Err(e) = request.await {
+                                warn!("⚠️ Failed to update progress message in chat {chat_id}: {e}");
+                            }
+                        }
+                        _ = poll.tick(), if stoppable => {
+                            if let Some(storage) = &storage
+                                && stop_if_requested(storage, &id, chat_id, &cancel).await
+                            {
+                                return;
+                            }
+                        }
+                    }
+                }
+            })
+        };
+
+        Self {
+            stage,
+            cancel,
+            task,
+            shown,
+            entry,
+            bot: bot.clone(),
+            chat_id,
+        }
+    }
+
+    // Move on to the next stage, shown on the progress message if it is already posted
+    pub fn stage(&self, stage: &str) {
+        self.stage.send_replace(stage.to_string());
+    }
+
+    // Run one step of the operation, returning None when the user stopped it before it finished
+    pub async fn run<T>(&self, step: impl Future<Output = T>) -> Option<T> {
+        tokio::select! {
+            result = step => Some(result),
+            _ = self.cancel.notified() => None,
+        }
+    }
+}
+
+impl Drop for TaskProgress {
+    fn drop(&mut self) {
+        self.task.abort();
+        let shown = std::mem::take(&mut *self.shown.lock().expect("progress message lock poisoned"));
+        let entry = std::mem::take(&mut self.entry);
+        let bot = self.bot.clone();
+        let chat_id = self.chat_id;
+        // The entry is removed even when registering looked unfinished, the write may have landed before the abort
+        tokio::spawn(async move {
+            if let Some(message_id) = shown.message_id
+                && let Err(e) = bot.delete_message(chat_id, message_id).await
+            {
+                warn!("⚠️ Failed to delete progress message in chat {chat_id}: {e}");
+            }
+            if let Err(e) = unregister_task(shown.storage, chat_id, entry).await {
+                warn!("⚠️ Failed to unregister finished task in chat {chat_id}: {e}");
+            }
+        });
+    }
+}
+
+async fn unregister_task(
+    storage: Option<Arc<DynamoDbStorage>>,
+    chat_id: ChatId,
+    entry: String,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let storage = match storage {
+        Some(storage) => storage,
+        None => Arc::new(create_storage().await?),
+    };
+    storage.remove_running_task(&chat_id.to_string(), entry).await?;
+    Ok(())
+}
+
+// Tasks still running in a chat, dropping entries left behind by processes that died mid-task
+async fn running_tasks(chat_id: ChatId) -> Result<Vec<RunningTask>, Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    let chat = chat_id.to_string();
+    let cutoff = chrono::Utc::now().timestamp() - MAX_TASK_AGE_SECS;
+    let mut running = Vec::new();
+    for entry in storage.get_running_tasks(&chat).await? {
+        match RunningTask::parse(&entry) {
+            Some(task) if task.started_at >= cutoff => running.push(task),
+            _ => storage.remove_running_task(&chat, entry).await?,
+        }
+    }
+    Ok(running)
+}
+
+// The stop request lives in storage, so whichever replica runs the task sees it on its next poll
+async fn request_cancel(task: &RunningTask) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    let expires_at = task.started_at + MAX_TASK_AGE_SECS;
+    storage.add_to_counter(&format!("cancel#{}", task.id), 1, expires_at).await?;
+    Ok(())
+}
+
+async fn is_cancel_requested(storage: &DynamoDbStorage, task_id: &str) -> bool {
+    match storage.get_counter(&format!("cancel#{task_id}")).await {
+        Ok(count) => count > 0,
+        Err(e) => {
+            warn!("⚠️ Failed to check whether task {task_id} was stopped: {e}");
+            false
+        }
+    }
+}
+
+// Wake the running step when the task was stopped, returning whether it was
+async fn stop_if_requested(storage: &DynamoDbStorage, task_id: &str, chat_id: ChatId, cancel: &Notify) -> bool {
+    if !is_cancel_requested(storage, task_id).await {
+        return false;
+    }
+    info!("✖️ Task {task_id} in chat {chat_id} was stopped");
+    // Stores a permit when the task is between steps, so the next step stops straight away
+    cancel.notify_one();
+    true
+}
+
+// Stop the running tasks in a chat, only the given user's unless None, returning how many were stopped
+pub async fn cancel_chat_tasks(chat_id: ChatId, user_id: Option<u64>) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let mut cancelled = 0;
+    for task in running_tasks(chat_id).await? {
+        if user_id.is_none_or(|user_id| task.user_id == user_id) {
+            request_cancel(&task).await?;
+            cancelled += 1;
+        }
+    }
+    info!("⏹ Stopping {cancelled} tasks in chat {chat_id}");
+    Ok(cancelled)
+}
+
+// Handle a press on a progress message's stop button, returning false for callbacks that are not ours
+pub async fn handle_progress_cancel(bot: &Bot, query: &CallbackQuery) -> ResponseResult<bool> {
+    let Some(id) = query.data.as_deref().and_then(|data| data.strip_prefix(CALLBACK_PREFIX)) else {
+        return Ok(false);
+    };
+    let Some(message) = query.message.as_ref() else {
+        return Ok(false);
+    };
+    let chat_id = message.chat().id;
+
+    let response = match running_tasks(chat_id).await {
+        Ok(running) => match running.iter().find(|task| task.id == id) {
+            None => "This task already finished.",
+            // Only the person who started a task may stop it
+            Some(task) if task.user_id != query.from.id.0 => "Only the person who started this can stop it.",
+            Some(task) => match request_cancel(task).await {
+                Ok(()) => "Stopping…",
+                Err(e) => {
+                    warn!("❌ Failed to stop task {id} in chat {chat_id}: {e}");
+                    "Failed to stop this task, try again."
+                }
+            },
+        },
+        Err(e) => {
+            warn!("❌ Failed to load running tasks for chat {chat_id}: {e}");
+            "Failed to stop this task, try again."
+        }
+    };
+    info!("⏹ Stop pressed for task {id} by user {}: {response}", query.from.id);
+    bot.answer_callback_query(query.id.clone()).text(response).await?;
+    Ok(true)
+}
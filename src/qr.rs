@@ -0,0 +1,23 @@
+use image::{ImageFormat, Luma};
+use log::info;
+use qrcode::QrCode;
+use std::error::Error;
+use std::io::Cursor;
+
+// Smallest rendered QR code edge in pixels, keeps the photo readable after Telegram compression
+const QR_MIN_SIZE: u32 = 512;
+
+// Render text or a URL as a PNG-encoded QR code
+pub fn generate_qr_png(content: &str) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let code = QrCode::new(content.as_bytes())?;
+    let image = code
+        .render::<Luma<u8>>()
+        .min_dimensions(QR_MIN_SIZE, QR_MIN_SIZE)
+        .build();
+
+    let mut png = Cursor::new(Vec::new());
+    image.write_to(&mut png, ImageFormat::Png)?;
+
+    info!("🔳 Generated QR code ({} bytes) for {} chars of input", png.get_ref().len(), content.len());
+    Ok(png.into_inner())
+}
@@ -0,0 +1,234 @@
+use chrono::{Datelike, Timelike};
+use log::{info, warn};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+use teloxide::prelude::*;
+
+use crate::health::AiProvider;
+use crate::search::create_search_provider;
+use crate::storage::create_storage;
+use crate::usage::next_budget_reset;
+use crate::watchdog::get_admin_chat_id;
+
+// UTC hour after which the nightly report goes out when QUOTA_REPORT_HOUR is unset
+const DEFAULT_REPORT_HOUR: u32 = 23;
+// How often long-running instances check whether the report is due
+const REPORT_CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+// Share of a quota after which the report flags the provider
+const WARN_SHARE: f64 = 0.8;
+
+// A paid API the bot calls, tracked per calendar month
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaSource {
+    Ai(AiProvider),
+    Search(&'static str),
+}
+
+impl QuotaSource {
+    fn key(&self) -> String {
+        match self {
+            QuotaSource::Ai(provider) => provider.metrics_key().to_string(),
+            QuotaSource::Search(name) => name.to_lowercase(),
+        }
+    }
+
+    // AI quotas are counted in tokens, search quotas in requests
+    fn quota_unit(&self) -> &'static str {
+        match self {
+            QuotaSource::Ai(_) => "token",
+            QuotaSource::Search(_) => "request",
+        }
+    }
+}
+
+impl fmt::Display for QuotaSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuotaSource::Ai(provider) => write!(f, "{provider}"),
+            QuotaSource::Search(name) => write!(f, "{name} search"),
+        }
+    }
+}
+
+// Parse "provider=value,provider=value" settings, keyed by lowercase provider name
+fn parse_provider_map(var: &str) -> BTreeMap<String, f64> {
+    std::env::var(var)
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .filter_map(|(provider, value)| Some((provider.trim().to_lowercase(), value.trim().parse().ok()?)))
+        .collect()
+}
+
+// Helper function to get monthly quotas from PROVIDER_QUOTAS, tokens for AI providers and requests for search
+pub fn get_provider_quotas() -> BTreeMap<String, f64> {
+    parse_provider_map("PROVIDER_QUOTAS")
+}
+
+// Helper function to get USD prices per million tokens from AI_TOKEN_PRICES, used to estimate spend
+pub fn get_token_prices() -> BTreeMap<String, f64> {
+    parse_provider_map("AI_TOKEN_PRICES")
+}
+
+// Helper function to get the UTC hour the nightly report is sent after
+pub fn get_report_hour() -> u32 {
+    std::env::var("QUOTA_REPORT_HOUR")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|hour| *hour < 24)
+        .unwrap_or(DEFAULT_REPORT_HOUR)
+}
+
+fn monthly_key(source: &QuotaSource, counter: &str) -> String {
+    format!("quota#{}#{}#{counter}", source.key(), chrono::Utc::now().format("%Y-%m"))
+}
+
+fn daily_key(source: &QuotaSource) -> String {
+    format!("quota#{}#{}#requests", source.key(), chrono::Utc::now().format("%Y-%m-%d"))
+}
+
+// Count one request, and the tokens it used, against the provider's monthly totals
+pub async fn record_provider_usage(source: QuotaSource, tokens: u64) {
+    let storage = match create_storage().await {
+        Ok(storage) => storage,
+        Err(e) => {
+            warn!("⚠️ Failed to record provider usage: {e}");
+            return;
+        }
+    };
+    // Monthly counters are kept a little past the month they count
+    let expires_at = chrono::Utc::now().timestamp() + 62 * 24 * 60 * 60;
+    let mut counters = vec![(monthly_key(&source, "requests"), 1), (daily_key(&source), 1)];
+    if tokens > 0 {
+        counters.push((monthly_key(&source, "tokens"), tokens));
+    }
+    for (key, amount) in counters {
+        if let Err(e) = storage.add_to_counter(&key, amount, expires_at).await {
+            warn!("⚠️ Failed to record provider usage for {key}: {e}");
+        }
+    }
+}
+
+// Providers worth reporting: AI providers with a key and the configured search provider
+fn tracked_sources() -> Vec<QuotaSource> {
+    let mut sources: Vec<QuotaSource> = AiProvider::ALL
+        .into_iter()
+        .filter(AiProvider::is_configured)
+        .map(QuotaSource::Ai)
+        .collect();
+    if let Ok(provider) = create_search_provider() {
+        sources.push(QuotaSource::Search(provider.name()));
+    }
+    sources
+}
+
+// Usage of every tracked provider this month against its quota, with spend and a projection for the month
+pub async fn format_quota_report() -> Result<String, Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    let quotas = get_provider_quotas();
+    let prices = get_token_prices();
+    let today = chrono::Utc::now().date_naive();
+    let days_in_month = next_budget_reset().pred_opt().map_or(30, |last| last.day());
+
+    let mut report = format!("📊 Provider usage for {}\n", today.format("%B %Y"));
+    let sources = tracked_sources();
+    if sources.is_empty() {
+        report.push_str("\nNo AI or search providers are configured.");
+        return Ok(report);
+    }
+    for source in sources {
+        let requests = storage.get_counter(&monthly_key(&source, "requests")).await?;
+        let requests_today = storage.get_counter(&daily_key(&source)).await?;
+        let tokens = storage.get_counter(&monthly_key(&source, "tokens")).await?;
+
+        let mut line = format!("{source}: {requests} requests ({requests_today} today)");
+        if let QuotaSource::Ai(_) = source {
+            line.push_str(&format!(", {tokens} tokens"));
+            if let Some(price) = prices.get(&source.key()) {
+                line.push_str(&format!(", ~${:.2} spent", tokens as f64 / 1_000_000.0 * price));
+            }
+        }
+
+        let mut flag = "✅";
+        if let Some(&quota) = quotas.get(&source.key()) {
+            let used = match source {
+                QuotaSource::Ai(_) => tokens,
+                QuotaSource::Search(_) => requests,
+            } as f64;
+            let unit = source.quota_unit();
+            line.push_str(&format!(
+                "\n   {:.0}% of the {quota:.0} {unit} quota, ~{:.0} left",
+                used / quota * 100.0,
+                (quota - used).max(0.0)
+            ));
+            // Straight-line projection from the days elapsed so far
+            let projected = used / today.day() as f64 * days_in_month as f64;
+            if used >= quota {
+                flag = "🚨";
+                line.push_str(" - quota used up");
+            } else if projected > quota {
+                flag = "⚠️";
+                let rate = used / today.day() as f64;
+                let days_left = ((quota - used) / rate).floor() as u64;
+                let runs_out = today + chrono::Days::new(days_left);
+                line.push_str(&format!(" - on pace to run out around {}", runs_out.format("%Y-%m-%d")));
+            } else if used / quota >= WARN_SHARE {
+                flag = "⚠️";
+            }
+        }
+        report.push_str(&format!("\n{flag} {line}"));
+    }
+    Ok(report)
+}
+
+// Send the report to the admin chat once a day after the report hour, even with several replicas running
+pub async fn send_quota_report_if_due(bot: &Bot) {
+    let Some(admin_chat) = get_admin_chat_id() else {
+        return;
+    };
+    let now = chrono::Utc::now();
+    if now.hour() < get_report_hour() {
+        return;
+    }
+    let claim = format!("quota#reported#{}", now.format("%Y-%m-%d"));
+    let claimed = match create_storage().await {
+        Ok(storage) => storage.try_increment_counter(&claim, 1, now.timestamp() + 2 * 24 * 60 * 60).await,
+        Err(e) => Err(e),
+    };
+    match claimed {
+        Ok(Some(_)) => {}
+        Ok(None) => return,
+        Err(e) => {
+            warn!("⚠️ Failed to claim the quota report: {e}");
+            return;
+        }
+    }
+
+    let report = match format_quota_report().await {
+        Ok(report) => report,
+        Err(e) => {
+            warn!("⚠️ Failed to build the quota report: {e}");
+            return;
+        }
+    };
+    info!("📊 Sending the nightly quota report to chat {admin_chat}");
+    if let Err(e) = bot.send_message(admin_chat, report).await {
+        warn!("⚠️ Failed to send the quota report to chat {admin_chat}: {e}");
+    }
+}
+
+// Check for the nightly report in the background. Lambda runs the check from scheduled invocations instead
+pub fn spawn_quota_report(bot: Bot) {
+    if get_admin_chat_id().is_none() {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(REPORT_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            send_quota_report_if_due(&bot).await;
+        }
+    });
+}
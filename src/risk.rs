@@ -0,0 +1,158 @@
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum RiskError {
+    Usage,
+    InvalidValue(String, String),
+    UnknownParameter(String),
+    MissingParameter(&'static str),
+    MissingAccount,
+    StopEqualsEntry,
+    TargetWrongSide,
+    RiskTooSmall,
+}
+
+impl fmt::Display for RiskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RiskError::Usage => write!(
+                f,
+                "Usage: /risk account=<size> risk=<percent%|amount> entry=<price> stop=<price> [target=<price>], e.g. /risk account=10000 risk=1% entry=150 stop=145"
+            ),
+            RiskError::InvalidValue(key, value) => write!(f, "'{value}' is not a valid value for {key}"),
+            RiskError::UnknownParameter(key) => write!(f, "Unknown parameter: {key}"),
+            RiskError::MissingParameter(key) => write!(f, "Missing parameter: {key}"),
+            RiskError::MissingAccount => write!(f, "A percentage risk needs account=<size>"),
+            RiskError::StopEqualsEntry => write!(f, "The stop must differ from the entry price"),
+            RiskError::TargetWrongSide => write!(f, "The target must be on the opposite side of the entry from the stop"),
+            RiskError::RiskTooSmall => write!(f, "The risk amount is smaller than the risk of a single share"),
+        }
+    }
+}
+
+impl std::error::Error for RiskError {}
+
+// Risk per trade, either a share of the account or a fixed amount
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RiskAmount {
+    Percent(f64),
+    Fixed(f64),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PositionSize {
+    pub long: bool,
+    pub shares: u64,
+    pub entry: f64,
+    pub stop: f64,
+    pub risk_per_share: f64,
+    pub dollar_risk: f64,
+    pub position_value: f64,
+    pub account: Option<f64>,
+    pub target: Option<f64>,
+}
+
+impl fmt::Display for PositionSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = if self.long { "Long" } else { "Short" };
+        writeln!(f, "{side} {} shares @ {:.2}, stop {:.2}", self.shares, self.entry, self.stop)?;
+        writeln!(f, "Risk per share: {:.2}", self.risk_per_share)?;
+        write!(f, "Dollar risk: {:.2}", self.dollar_risk)?;
+        if let Some(account) = self.account {
+            write!(f, " ({:.2}% of account)", self.dollar_risk / account * 100.0)?;
+        }
+        write!(f, "\nPosition value: {:.2}", self.position_value)?;
+        if let Some(account) = self.account {
+            write!(f, " ({:.1}% of account)", self.position_value / account * 100.0)?;
+            if self.position_value > account {
+                write!(f, "\n⚠️ Position exceeds the account size and needs margin")?;
+            }
+        }
+        if let Some(target) = self.target {
+            let reward = (target - self.entry).abs() * self.shares as f64;
+            write!(
+                f,
+                "\nTarget {target:.2}: reward {reward:.2}, {:.2}R",
+                (target - self.entry).abs() / self.risk_per_share
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_price(key: &str, value: &str) -> Result<f64, RiskError> {
+    value
+        .trim_start_matches('$')
+        .replace(',', "")
+        .parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite() && *v > 0.0)
+        .ok_or_else(|| RiskError::InvalidValue(key.to_string(), value.to_string()))
+}
+
+// Parse "key=value" arguments and size a position so a stop-out loses the chosen risk
+pub fn calculate_position(input: &str) -> Result<PositionSize, RiskError> {
+    if input.trim().is_empty() {
+        return Err(RiskError::Usage);
+    }
+    let mut account = None;
+    let mut risk = None;
+    let mut entry = None;
+    let mut stop = None;
+    let mut target = None;
+
+    for token in input.split_whitespace() {
+        let (key, value) = token.split_once('=').ok_or(RiskError::Usage)?;
+        let key = key.to_lowercase();
+        match key.as_str() {
+            "account" | "acct" | "balance" => account = Some(parse_price(&key, value)?),
+            "risk" => {
+                risk = Some(match value.strip_suffix('%') {
+                    Some(percent) => RiskAmount::Percent(parse_price(&key, percent)?),
+                    None => RiskAmount::Fixed(parse_price(&key, value)?),
+                })
+            }
+            "entry" | "price" => entry = Some(parse_price(&key, value)?),
+            "stop" | "sl" => stop = Some(parse_price(&key, value)?),
+            "target" | "tp" => target = Some(parse_price(&key, value)?),
+            _ => return Err(RiskError::UnknownParameter(key)),
+        }
+    }
+
+    let risk = risk.ok_or(RiskError::MissingParameter("risk"))?;
+    let entry = entry.ok_or(RiskError::MissingParameter("entry"))?;
+    let stop = stop.ok_or(RiskError::MissingParameter("stop"))?;
+
+    let dollar_budget = match risk {
+        RiskAmount::Percent(percent) => account.ok_or(RiskError::MissingAccount)? * percent / 100.0,
+        RiskAmount::Fixed(amount) => amount,
+    };
+    let risk_per_share = (entry - stop).abs();
+    if risk_per_share == 0.0 {
+        return Err(RiskError::StopEqualsEntry);
+    }
+    let long = stop < entry;
+    if let Some(target) = target
+        && (target > entry) != long
+    {
+        return Err(RiskError::TargetWrongSide);
+    }
+
+    // Round down so the loss at the stop never exceeds the budget
+    let shares = (dollar_budget / risk_per_share).floor() as u64;
+    if shares == 0 {
+        return Err(RiskError::RiskTooSmall);
+    }
+
+    Ok(PositionSize {
+        long,
+        shares,
+        entry,
+        stop,
+        risk_per_share,
+        dollar_risk: shares as f64 * risk_per_share,
+        position_value: shares as f64 * entry,
+        account,
+        target,
+    })
+}
@@ -0,0 +1,258 @@
+use async_trait::async_trait;
+use log::info;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::time::Duration;
+
+use crate::quota::{record_provider_usage, QuotaSource};
+use crate::untrusted::wrap_untrusted;
+
+const DEFAULT_RESULT_COUNT: usize = 5;
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub const SEARCH_PROMPT: &str = "You answer questions using the web search results you are given. \
+    Base the answer on the results, cite them inline with their numbers like [1] or [2][3], and say so when \
+    the results do not answer the question instead of guessing. Keep the answer concise and do not list the sources, \
+    they are added after your answer.";
+
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+}
+
+#[async_trait]
+pub trait SearchProvider: Send + Sync {
+    async fn search(&self, query: &str, count: usize) -> Result<Vec<SearchResult>, Box<dyn Error + Send + Sync>>;
+    fn name(&self) -> &'static str;
+}
+
+fn http_client() -> Result<reqwest::Client, reqwest::Error> {
+    reqwest::Client::builder().timeout(SEARCH_TIMEOUT).build()
+}
+
+// Pull title, url and snippet out of each entry of a provider's result list
+fn parse_results(results: Option<&Value>, url_field: &str, snippet_field: &str) -> Vec<SearchResult> {
+    results
+        .and_then(Value::as_array)
+        .map(|results| {
+            results
+                .iter()
+                .filter_map(|result| {
+                    Some(SearchResult {
+                        title: result.get("title")?.as_str()?.to_string(),
+                        url: result.get(url_field)?.as_str()?.to_string(),
+                        snippet: result.get(snippet_field).and_then(Value::as_str).unwrap_or_default().to_string(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub struct BraveSearch {
+    api_key: String,
+}
+
+#[async_trait]
+impl SearchProvider for BraveSearch {
+    async fn search(&self, query: &str, count: usize) -> Result<Vec<SearchResult>, Box<dyn Error + Send + Sync>> {
+        let response: Value = http_client()?
+            .get("https://api.search.brave.com/res/v1/web/search")
+            .header("X-Subscription-Token", &self.api_key)
+            .header("Accept", "application/json")
+            .query(&[("q", query), ("count", count.to_string().as_str())])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(parse_results(response.pointer("/web/results"), "url", "description"))
+    }
+
+    fn name(&self) -> &'static str {
+        "Brave"
+    }
+}
+
+pub struct SerpApiSearch {
+    api_key: String,
+}
+
+#[async_trait]
+impl SearchProvider for SerpApiSearch {
+    async fn search(&self, query: &str, count: usize) -> Result<Vec<SearchResult>, Box<dyn Error + Send + Sync>> {
+        let response: Value = http_client()?
+            .get("https://serpapi.com/search.json")
+            .query(&[
+                ("engine", "google"),
+                ("q", query),
+                ("num", count.to_string().as_str()),
+                ("api_key", self.api_key.as_str()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(parse_results(response.get("organic_results"), "link", "snippet"))
+    }
+
+    fn name(&self) -> &'static str {
+        "SerpAPI"
+    }
+}
+
+pub struct TavilySearch {
+    api_key: String,
+}
+
+#[async_trait]
+impl SearchProvider for TavilySearch {
+    async fn search(&self, query: &str, count: usize) -> Result<Vec<SearchResult>, Box<dyn Error + Send + Sync>> {
+        let response: Value = http_client()?
+            .post("https://api.tavily.com/search")
+            .bearer_auth(&self.api_key)
+            .json(&json!({ "query": query, "max_results": count }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(parse_results(response.get("results"), "url", "content"))
+    }
+
+    fn name(&self) -> &'static str {
+        "Tavily"
+    }
+}
+
+// Helper function to get the search provider from SEARCH_PROVIDER and SEARCH_API_KEY
+pub fn create_search_provider() -> Result<Box<dyn SearchProvider>, &'static str> {
+    search_provider_from(std::env::var("SEARCH_PROVIDER").ok(), std::env::var("SEARCH_API_KEY").ok())
+}
+
+fn search_provider_from(
+    provider: Option<String>,
+    api_key: Option<String>,
+) -> Result<Box<dyn SearchProvider>, &'static str> {
+    let provider = provider.ok_or("SEARCH_PROVIDER not set")?;
+    let api_key = api_key.filter(|key| !key.trim().is_empty()).ok_or("SEARCH_API_KEY not set")?;
+    match provider.to_lowercase().as_str() {
+        "brave" => Ok(Box::new(BraveSearch { api_key })),
+        "serpapi" => Ok(Box::new(SerpApiSearch { api_key })),
+        "tavily" => Ok(Box::new(TavilySearch { api_key })),
+        _ => Err("SEARCH_PROVIDER must be brave, serpapi or tavily"),
+    }
+}
+
+// Helper function to get how many results are passed to the AI
+pub fn get_search_result_count() -> usize {
+    std::env::var("SEARCH_RESULT_COUNT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|count| (1..=20).contains(count))
+        .unwrap_or(DEFAULT_RESULT_COUNT)
+}
+
+pub async fn web_search(query: &str) -> Result<Vec<SearchResult>, Box<dyn Error + Send + Sync>> {
+    let provider = create_search_provider()?;
+    let results = provider.search(query, get_search_result_count()).await;
+    // Failed requests usually still count against the provider's quota
+    record_provider_usage(QuotaSource::Search(provider.name()), 0).await;
+    let results = results?;
+    info!("🔎 {} returned {} results for '{query}'", provider.name(), results.len());
+    Ok(results)
+}
+
+// Numbered results for the model, with today's date so it can judge how fresh they are
+pub fn format_search_context(query: &str, results: &[SearchResult]) -> String {
+    let sources = results
+        .iter()
+        .enumerate()
+        .map(|(i, result)| format!("[{}] {}\n{}\n{}", i + 1, result.title, result.url, result.snippet))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    format!(
+        "Today is {}.\n\nQuestion: {query}\n\nSearch results:\n\n{}",
+        chrono::Utc::now().format("%Y-%m-%d"),
+        wrap_untrusted(&sources)
+    )
+}
+
+// Source list appended after the answer, numbered like the citations
+pub fn format_sources(results: &[SearchResult]) -> String {
+    results
+        .iter()
+        .enumerate()
+        .map(|(i, result)| format!("[{}] {} - {}", i + 1, result.title, result.url))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(title: &str, url: &str, snippet: &str) -> SearchResult {
+        SearchResult {
+            title: title.to_string(),
+            url: url.to_string(),
+            snippet: snippet.to_string(),
+        }
+    }
+
+    #[test]
+    fn missing_api_key_is_reported() {
+        let error = search_provider_from(Some("brave".to_string()), None).err();
+        assert_eq!(error, Some("SEARCH_API_KEY not set"));
+        let error = search_provider_from(Some("brave".to_string()), Some(" ".to_string())).err();
+        assert_eq!(error, Some("SEARCH_API_KEY not set"));
+    }
+
+    #[test]
+    fn missing_or_unknown_provider_is_reported() {
+        let error = search_provider_from(None, Some("key".to_string())).err();
+        assert_eq!(error, Some("SEARCH_PROVIDER not set"));
+        let error = search_provider_from(Some("bing".to_string()), Some("key".to_string())).err();
+        assert_eq!(error, Some("SEARCH_PROVIDER must be brave, serpapi or tavily"));
+    }
+
+    #[test]
+    fn provider_names_are_case_insensitive() {
+        let provider = search_provider_from(Some("Tavily".to_string()), Some("key".to_string()));
+        assert_eq!(provider.map(|provider| provider.name()).ok(), Some("Tavily"));
+    }
+
+    #[test]
+    fn parses_results_and_skips_incomplete_entries() {
+        let response = json!([
+            { "title": "Rust", "link": "https://rust-lang.org", "snippet": "A language" },
+            { "title": "No link" },
+            { "title": "No snippet", "link": "https://example.com" },
+        ]);
+        let results = parse_results(Some(&response), "link", "snippet");
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].url, "https://rust-lang.org");
+        assert_eq!(results[0].snippet, "A language");
+        assert_eq!(results[1].snippet, "");
+        assert!(parse_results(None, "link", "snippet").is_empty());
+    }
+
+    #[test]
+    fn formats_numbered_sources() {
+        let results = [result("First", "https://a.example", "one"), result("Second", "https://b.example", "two")];
+        assert_eq!(
+            format_sources(&results),
+            "[1] First - https://a.example\n[2] Second - https://b.example"
+        );
+    }
+
+    #[test]
+    fn formats_fenced_context() {
+        let context = format_search_context("why?", &[result("First", "https://a.example", "one")]);
+        assert!(context.contains("Question: why?"));
+        assert!(context.contains("<untrusted_content>\n[1] First\nhttps://a.example\none\n</untrusted_content>"));
+    }
+}
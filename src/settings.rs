@@ -0,0 +1,175 @@
+use std::error::Error;
+
+use crate::ai::{get_ai_enabled, get_voice_replies, set_ai_enabled, set_voice_replies};
+use crate::autodelete::{get_autodelete, set_autodelete, validate_autodelete};
+use crate::flair::{get_flair_enabled, set_flair_enabled};
+use crate::ingest::{get_silent_deliveries, set_delivery_silent, DeliveryType};
+use crate::moderation::{
+    get_default_moderation_mode, get_moderation_mode, set_moderation_mode, validate_moderation_mode, ModerationMode,
+};
+use crate::pause::{format_duration, parse_duration};
+use crate::translate::{get_translate_language, set_translate_language, validate_language, DEFAULT_TRANSLATE_LANGUAGE};
+
+// Chat settings that /set and /get know about. Values are stored where the feature keeps them,
+// this registry only names, validates and describes them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatSetting {
+    Ai,
+    AutoDelete,
+    Flair,
+    Moderation,
+    SilentAlerts,
+    SilentDigests,
+    TranslateLanguage,
+    Voice,
+}
+
+// The shape of a setting's value, which decides how it is parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingKind {
+    Toggle,
+    Duration,
+    Choice(&'static [&'static str]),
+    Language,
+}
+
+impl SettingKind {
+    fn hint(&self) -> String {
+        match self {
+            SettingKind::Toggle => "on|off".to_string(),
+            SettingKind::Duration => "<duration>|off, e.g. 30m".to_string(),
+            SettingKind::Choice(choices) => choices.join("|"),
+            SettingKind::Language => "<language>|default, e.g. es".to_string(),
+        }
+    }
+}
+
+fn parse_toggle(value: &str) -> Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "on" | "true" | "yes" => Ok(true),
+        "off" | "false" | "no" => Ok(false),
+        _ => Err(format!("'{value}' is not on or off")),
+    }
+}
+
+fn toggle_name(enabled: bool) -> String {
+    if enabled { "on" } else { "off" }.to_string()
+}
+
+impl ChatSetting {
+    pub const ALL: [ChatSetting; 8] = [
+        ChatSetting::Ai,
+        ChatSetting::AutoDelete,
+        ChatSetting::Flair,
+        ChatSetting::Moderation,
+        ChatSetting::SilentAlerts,
+        ChatSetting::SilentDigests,
+        ChatSetting::TranslateLanguage,
+        ChatSetting::Voice,
+    ];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            ChatSetting::Ai => "ai",
+            ChatSetting::AutoDelete => "autodelete",
+            ChatSetting::Flair => "flair",
+            ChatSetting::Moderation => "moderation",
+            ChatSetting::SilentAlerts => "silent.alerts",
+            ChatSetting::SilentDigests => "silent.digests",
+            ChatSetting::TranslateLanguage => "translate.language",
+            ChatSetting::Voice => "voice",
+        }
+    }
+
+    pub fn parse(key: &str) -> Option<Self> {
+        let key = key.trim().to_lowercase();
+        Self::ALL.into_iter().find(|setting| setting.key() == key)
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ChatSetting::Ai => "all AI features in this chat",
+            ChatSetting::AutoDelete => "delete the bot's replies after this long",
+            ChatSetting::Flair => "message effects on generated images and QR codes (private chats)",
+            ChatSetting::Moderation => "filter AI prompts and replies",
+            ChatSetting::SilentAlerts => "deliver inbound alerts without a sound",
+            ChatSetting::SilentDigests => "deliver alert digests without a sound",
+            ChatSetting::TranslateLanguage => "target language of /translate",
+            ChatSetting::Voice => "answer every AI reply as a voice message",
+        }
+    }
+
+    pub fn kind(&self) -> SettingKind {
+        match self {
+            ChatSetting::AutoDelete => SettingKind::Duration,
+            ChatSetting::Moderation => SettingKind::Choice(&["off", "refuse", "redact"]),
+            ChatSetting::TranslateLanguage => SettingKind::Language,
+            _ => SettingKind::Toggle,
+        }
+    }
+
+    // Voice replies and the translation language are personal conveniences, the rest affect everyone
+    pub fn admin_only(&self) -> bool {
+        !matches!(self, ChatSetting::TranslateLanguage | ChatSetting::Voice)
+    }
+
+    pub fn default_value(&self) -> String {
+        match self {
+            ChatSetting::Ai => "on".to_string(),
+            ChatSetting::Moderation => get_default_moderation_mode().as_str().to_string(),
+            ChatSetting::TranslateLanguage => DEFAULT_TRANSLATE_LANGUAGE.to_string(),
+            _ => "off".to_string(),
+        }
+    }
+
+    pub fn usage(&self) -> String {
+        format!("/set {} {}", self.key(), self.kind().hint())
+    }
+
+    // The chat's current value, as /set accepts it
+    pub async fn read(&self, chat_id: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+        Ok(match self {
+            ChatSetting::Ai => toggle_name(get_ai_enabled(chat_id).await),
+            ChatSetting::AutoDelete => get_autodelete(chat_id).await.map_or("off".to_string(), format_duration),
+            ChatSetting::Flair => toggle_name(get_flair_enabled(chat_id).await),
+            ChatSetting::Moderation => get_moderation_mode(chat_id).await.as_str().to_string(),
+            ChatSetting::SilentAlerts => toggle_name(get_silent_deliveries(chat_id).await?.contains(&DeliveryType::Alert)),
+            ChatSetting::SilentDigests => toggle_name(get_silent_deliveries(chat_id).await?.contains(&DeliveryType::Digest)),
+            ChatSetting::TranslateLanguage => get_translate_language(chat_id).await,
+            ChatSetting::Voice => toggle_name(get_voice_replies(chat_id).await),
+        })
+    }
+
+    // Validate and store a value, returning it as it now reads. Invalid values are user-facing errors
+    pub async fn write(&self, chat_id: &str, value: &str) -> Result<String, String> {
+        let value = value.trim();
+        let stored = match self {
+            ChatSetting::Ai => set_ai_enabled(chat_id, parse_toggle(value)?).await,
+            ChatSetting::AutoDelete => {
+                let seconds = if value.eq_ignore_ascii_case("off") {
+                    None
+                } else {
+                    Some(parse_duration(value).and_then(validate_autodelete)?)
+                };
+                set_autodelete(chat_id, seconds).await
+            }
+            ChatSetting::Flair => set_flair_enabled(chat_id, parse_toggle(value)?).await,
+            ChatSetting::Moderation => {
+                let mode = ModerationMode::parse(value).ok_or_else(|| format!("'{value}' is not off, refuse or redact"))?;
+                set_moderation_mode(chat_id, validate_moderation_mode(mode)?).await
+            }
+            ChatSetting::SilentAlerts => set_delivery_silent(chat_id, DeliveryType::Alert, parse_toggle(value)?).await,
+            ChatSetting::SilentDigests => set_delivery_silent(chat_id, DeliveryType::Digest, parse_toggle(value)?).await,
+            ChatSetting::TranslateLanguage => {
+                let language = match value {
+                    "default" | "off" => None,
+                    language => Some(validate_language(language)?),
+                };
+                set_translate_language(chat_id, language.as_deref()).await
+            }
+            ChatSetting::Voice => set_voice_replies(chat_id, parse_toggle(value)?).await,
+        };
+        stored.map_err(|e| format!("Failed to save {}: {e}", self.key()))?;
+        self.read(chat_id).await.map_err(|e| format!("Saved, but failed to read {} back: {e}", self.key()))
+    }
+}
@@ -0,0 +1,323 @@
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hmac::{Hmac, Mac};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use teloxide::prelude::*;
+
+use crate::ai::get_available_models;
+use crate::dialogue::{clear_dialogue_state, load_dialogue_state, save_dialogue_state};
+use crate::plugins::bot_commands;
+use crate::storage::create_storage;
+
+// How often a running instance picks up settings stored by another replica
+const RUNTIME_CONFIG_REFRESH: Duration = Duration::from_secs(60);
+const SETUP_DIALOGUE_TTL: Duration = Duration::from_secs(30 * 60);
+const SETUP_SCOPE: &str = "setup";
+const NONCE_LEN: usize = 12;
+
+// Optional settings the owner can complete over Telegram when they are missing from the environment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SetupItem {
+    OpenAiApiKey,
+    DefaultModel,
+}
+
+const SETUP_ITEMS: [SetupItem; 2] = [SetupItem::OpenAiApiKey, SetupItem::DefaultModel];
+
+impl SetupItem {
+    fn variable(&self) -> &'static str {
+        match self {
+            SetupItem::OpenAiApiKey => "OPENAI_API_KEY",
+            SetupItem::DefaultModel => "AI_MODEL",
+        }
+    }
+
+    fn from_variable(variable: &str) -> Option<Self> {
+        SETUP_ITEMS.into_iter().find(|item| item.variable() == variable)
+    }
+
+    fn is_secret(&self) -> bool {
+        matches!(self, SetupItem::OpenAiApiKey)
+    }
+
+    fn prompt(&self) -> String {
+        match self {
+            SetupItem::OpenAiApiKey => {
+                "🔑 Paste your OpenAI API key. I will delete your message once it is stored.".to_string()
+            }
+            SetupItem::DefaultModel => format!(
+                "🤖 Choose a default AI model for chats that have not picked one:\n\n• {}",
+                get_available_models().join("\n• ")
+            ),
+        }
+    }
+
+    fn validate(&self, value: &str) -> Result<String, String> {
+        let value = value.trim();
+        match self {
+            SetupItem::OpenAiApiKey if value.is_empty() || value.contains(char::is_whitespace) => {
+                Err("That does not look like an API key, paste it without spaces.".to_string())
+            }
+            SetupItem::DefaultModel if !get_available_models().iter().any(|model| model == value) => {
+                Err(format!("Unknown model '{value}', pick one from the list."))
+            }
+            _ => Ok(value.to_string()),
+        }
+    }
+}
+
+// Settings still waiting for an answer in the owner's setup dialogue
+#[derive(Debug, Serialize, Deserialize)]
+struct SetupDialogue {
+    pending: Vec<String>,
+}
+
+struct RuntimeConfig {
+    loaded_at: Instant,
+    values: BTreeMap<String, String>,
+}
+
+// Decrypted settings from storage; configuration, not conversation state, so caching it per process is fine
+static RUNTIME_CONFIG: RwLock<Option<RuntimeConfig>> = RwLock::new(None);
+
+// Helper function to get the Telegram user id allowed to run /setup
+pub fn get_owner_id() -> Option<u64> {
+    std::env::var("BOT_OWNER_ID").ok().and_then(|v| v.trim().parse().ok())
+}
+
+// Environment first, then values stored through /setup
+pub fn get_config(variable: &str) -> Option<String> {
+    if let Ok(value) = std::env::var(variable)
+        && !value.trim().is_empty()
+    {
+        return Some(value);
+    }
+    RUNTIME_CONFIG
+        .read()
+        .ok()?
+        .as_ref()
+        .and_then(|config| config.values.get(variable).cloned())
+}
+
+fn missing_items() -> Vec<SetupItem> {
+    SETUP_ITEMS
+        .into_iter()
+        .filter(|item| get_config(item.variable()).is_none())
+        .collect()
+}
+
+// Stored settings are encrypted with a key derived from the bot token
+fn cipher(bot: &Bot) -> ChaCha20Poly1305 {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(bot.token().as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(b"telegram-bot runtime config");
+    let key = mac.finalize().into_bytes();
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+}
+
+fn encrypt_config(bot: &Bot, values: &BTreeMap<String, String>) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let plaintext = serde_json::to_vec(values)?;
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher(bot)
+        .encrypt(&nonce, plaintext.as_slice())
+        .map_err(|_| "Failed to encrypt settings")?;
+    Ok(hex::encode([nonce.as_slice(), ciphertext.as_slice()].concat()))
+}
+
+fn decrypt_config(bot: &Bot, blob: &str) -> Result<BTreeMap<String, String>, Box<dyn Error + Send + Sync>> {
+    let bytes = hex::decode(blob)?;
+    if bytes.len() < NONCE_LEN {
+        return Err("Stored settings are truncated".into());
+    }
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+    let plaintext = cipher(bot)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Failed to decrypt settings, was the bot token changed?")?;
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+async fn load_stored_config(bot: &Bot) -> Result<BTreeMap<String, String>, Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    match storage.get_runtime_config().await? {
+        Some(blob) => decrypt_config(bot, &blob),
+        None => Ok(BTreeMap::new()),
+    }
+}
+
+fn cache_config(values: BTreeMap<String, String>) {
+    if let Ok(mut config) = RUNTIME_CONFIG.write() {
+        *config = Some(RuntimeConfig {
+            loaded_at: Instant::now(),
+            values,
+        });
+    }
+}
+
+// Reload stored settings when the cached copy is stale, so values set on another replica apply here too
+pub async fn refresh_runtime_config(bot: &Bot) {
+    let fresh = RUNTIME_CONFIG
+        .read()
+        .ok()
+        .and_then(|config| config.as_ref().map(|config| config.loaded_at.elapsed() < RUNTIME_CONFIG_REFRESH))
+        .unwrap_or(false);
+    if fresh {
+        return;
+    }
+    match load_stored_config(bot).await {
+        Ok(values) => cache_config(values),
+        Err(e) => {
+            warn!("⚠️ Failed to load stored settings: {e}");
+            // Keep serving the previous values and retry after the refresh interval
+            let previous = RUNTIME_CONFIG
+                .read()
+                .ok()
+                .and_then(|config| config.as_ref().map(|config| config.values.clone()))
+                .unwrap_or_default();
+            cache_config(previous);
+        }
+    }
+}
+
+async fn store_setting(bot: &Bot, variable: &str, value: String) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut values = load_stored_config(bot).await?;
+    values.insert(variable.to_string(), value);
+    let storage = create_storage().await?;
+    storage.set_runtime_config(&encrypt_config(bot, &values)?).await?;
+    cache_config(values);
+    info!("🔧 Stored {variable} through setup, active immediately");
+    // A new key can make more commands work, so offer them in the menu right away
+    if let Err(e) = bot.set_my_commands(bot_commands()).await {
+        warn!("⚠️ Failed to update the bot command menu: {e}");
+    }
+    Ok(())
+}
+
+pub fn is_owner(msg: &Message) -> bool {
+    msg.chat.is_private() && get_owner_id().is_some_and(|owner| msg.from.as_ref().is_some_and(|user| user.id.0 == owner))
+}
+
+// Tell the owner about missing settings when the bot starts
+pub async fn notify_owner_if_incomplete(bot: &Bot) {
+    let Some(owner) = get_owner_id() else {
+        return;
+    };
+    refresh_runtime_config(bot).await;
+    let missing = missing_items();
+    if missing.is_empty() {
+        return;
+    }
+    let names: Vec<&str> = missing.iter().map(SetupItem::variable).collect();
+    let text = format!(
+        "👋 The bot started without {}. Send /setup here to complete the configuration.",
+        names.join(" and ")
+    );
+    if let Err(e) = bot.send_message(ChatId(owner as i64), text).await {
+        warn!("⚠️ Failed to notify the owner about missing settings: {e}");
+    }
+}
+
+// Start the setup dialogue for the missing settings
+pub async fn start_setup(bot: &Bot, msg: &Message) -> ResponseResult<()> {
+    let response = if get_owner_id().is_none() {
+        "Set BOT_OWNER_ID to your Telegram user id to enable /setup.".to_string()
+    } else if !is_owner(msg) {
+        "⛔ Only the bot owner can run /setup, in a private chat with the bot.".to_string()
+    } else {
+        refresh_runtime_config(bot).await;
+        let missing = missing_items();
+        match missing.first() {
+            None => "✅ Setup is complete, nothing is missing.".to_string(),
+            Some(first) => {
+                let dialogue = SetupDialogue {
+                    pending: missing.iter().map(|item| item.variable().to_string()).collect(),
+                };
+                let user_id = msg.from.as_ref().map(|user| user.id.0).unwrap_or(0);
+                match save_dialogue_state(&msg.chat.id.to_string(), user_id, SETUP_SCOPE, &dialogue, SETUP_DIALOGUE_TTL)
+                    .await
+                {
+                    Ok(()) => format!("🛠️ Let's finish setting up the bot. Send /skip to skip a step or /cancel to stop.\n\n{}", first.prompt()),
+                    Err(e) => format!("❌ Setup needs storage to keep its progress: {e}"),
+                }
+            }
+        }
+    };
+    bot.send_message(msg.chat.id, response).await?;
+    Ok(())
+}
+
+// Treat the owner's next private message as the answer to the pending setup step, returns whether it was consumed
+pub async fn handle_setup_reply(bot: &Bot, msg: &Message, text: &str) -> ResponseResult<bool> {
+    if !is_owner(msg) {
+        return Ok(false);
+    }
+    let text = text.trim();
+    if text.starts_with('/') && text != "/skip" && text != "/cancel" {
+        return Ok(false);
+    }
+
+    let chat_id = msg.chat.id.to_string();
+    let user_id = msg.from.as_ref().map(|user| user.id.0).unwrap_or(0);
+    let mut dialogue: SetupDialogue = match load_dialogue_state(&chat_id, user_id, SETUP_SCOPE).await {
+        Ok(Some(dialogue)) => dialogue,
+        Ok(None) => return Ok(false),
+        Err(e) => {
+            warn!("⚠️ Failed to load setup progress: {e}");
+            return Ok(false);
+        }
+    };
+    let Some(item) = dialogue.pending.first().and_then(|variable| SetupItem::from_variable(variable)) else {
+        let _ = clear_dialogue_state(&chat_id, user_id, SETUP_SCOPE).await;
+        return Ok(false);
+    };
+
+    if text == "/cancel" {
+        let _ = clear_dialogue_state(&chat_id, user_id, SETUP_SCOPE).await;
+        bot.send_message(msg.chat.id, "🛠️ Setup cancelled. Send /setup to start again.").await?;
+        return Ok(true);
+    }
+
+    let mut response = Vec::new();
+    if text != "/skip" {
+        if item.is_secret()
+            && let Err(e) = bot.delete_message(msg.chat.id, msg.id).await
+        {
+            warn!("⚠️ Failed to delete the message holding {}: {e}", item.variable());
+        }
+        match item.validate(text) {
+            Ok(value) => match store_setting(bot, item.variable(), value).await {
+                Ok(()) => response.push(format!("✅ {} saved.", item.variable())),
+                Err(e) => {
+                    warn!("❌ Failed to store {}: {e}", item.variable());
+                    bot.send_message(msg.chat.id, format!("❌ Failed to store {}: {e}", item.variable()))
+                        .await?;
+                    return Ok(true);
+                }
+            },
+            Err(e) => {
+                bot.send_message(msg.chat.id, format!("❌ {e}\n\n{}", item.prompt())).await?;
+                return Ok(true);
+            }
+        }
+    }
+
+    dialogue.pending.remove(0);
+    match dialogue.pending.first().and_then(|variable| SetupItem::from_variable(variable)) {
+        Some(next) => {
+            if let Err(e) = save_dialogue_state(&chat_id, user_id, SETUP_SCOPE, &dialogue, SETUP_DIALOGUE_TTL).await {
+                warn!("⚠️ Failed to save setup progress: {e}");
+            }
+            response.push(next.prompt());
+        }
+        None => {
+            let _ = clear_dialogue_state(&chat_id, user_id, SETUP_SCOPE).await;
+            response.push("🎉 Setup complete, the new settings are active.".to_string());
+        }
+    }
+    bot.send_message(msg.chat.id, response.join("\n\n")).await?;
+    Ok(true)
+}
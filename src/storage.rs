@@ -2,7 +2,7 @@ use aws_config::BehaviorVersion;
 use aws_sdk_dynamodb::{Client as DynamoDbClient, Error as DynamoDbError};
 use log::{info, warn};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
 use std::fmt;
 
@@ -29,10 +29,140 @@ impl UserPreferences {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConversationRole {
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConversationMessage {
+    pub role: ConversationRole,
+    pub content: String,
+    pub timestamp: String,
+}
+
+impl ConversationMessage {
+    pub fn new(role: ConversationRole, content: String) -> Self {
+        Self {
+            role,
+            content,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+// A group message kept so it can be summarized later
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecentMessage {
+    pub author: String,
+    pub text: String,
+    pub timestamp: String,
+}
+
+impl RecentMessage {
+    pub fn new(author: String, text: String) -> Self {
+        Self {
+            author,
+            text,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+// Active persona and user-defined personas (name -> system prompt) for a chat
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PersonaSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active: Option<String>,
+    #[serde(default)]
+    pub custom: BTreeMap<String, String>,
+}
+
+// Per-chat generation overrides, unset fields fall back to the persona or backend defaults
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct AiSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+}
+
+// Monthly AI token caps for a chat, applied to the whole chat and to each user in it
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+pub struct AiBudget {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chat_tokens: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_tokens: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IngestBinding {
+    pub token: String,
+    pub chat_id: String,
+    pub created_by: u64,
+    pub created_at: String,
+    pub repository: Option<String>, // GitHub "owner/repo" this binding accepts events for
+    pub secret: Option<String>,     // Shared secret for payload signature verification
+    pub thread_id: Option<i32>,     // Forum topic the alerts are posted to
+}
+
+impl IngestBinding {
+    pub fn new(chat_id: String, created_by: u64) -> Self {
+        Self {
+            token: uuid::Uuid::new_v4().simple().to_string(),
+            chat_id,
+            created_by,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            repository: None,
+            secret: None,
+            thread_id: None,
+        }
+    }
+
+    pub fn for_github(chat_id: String, created_by: u64, repository: String) -> Self {
+        Self {
+            repository: Some(repository),
+            secret: Some(uuid::Uuid::new_v4().simple().to_string()),
+            ..Self::new(chat_id, created_by)
+        }
+    }
+}
+
+// Alerts delivered to a chat as one message that is edited while the coalescing window is open
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AlertDigest {
+    pub message_id: i32,
+    pub alerts: Vec<String>,
+    pub expires_at: i64,
+}
+
+// A piece of text taught to a chat with /teach, and its embedding as hex of little-endian f32s
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KnowledgeChunk {
+    pub id: String,
+    pub source: String,
+    pub text: String,
+    pub embedding: String,
+    pub added_by: u64,
+    pub added_at: String,
+}
+
+// Captured outbound messages outlive the sends by a week
+const OUTBOUND_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+
+// Preferences item holding bot-wide settings rather than a chat's
+const RUNTIME_CONFIG_KEY: &str = "bot#config";
+
 #[derive(Debug)]
 pub enum StorageError {
     DynamoDb(DynamoDbError),
     Configuration(String),
+    Serialization(String),
 }
 
 impl fmt::Display for StorageError {
@@ -40,6 +170,7 @@ impl fmt::Display for StorageError {
         match self {
             StorageError::DynamoDb(e) => write!(f, "DynamoDB error: {e}"),
             StorageError::Configuration(e) => write!(f, "Configuration error: {e}"),
+            StorageError::Serialization(e) => write!(f, "Serialization error: {e}"),
         }
     }
 }
@@ -55,16 +186,28 @@ impl From<DynamoDbError> for StorageError {
 pub struct DynamoDbStorage {
     client: DynamoDbClient,
     table_name: String,
+    conversation_table_name: Option<String>,
+    ingest_table_name: Option<String>,
+    usage_table_name: Option<String>,
+}
+
+// AWS configuration shared by every client the bot builds, so they all resolve credentials the same way.
+// The behavior version is pinned so SDK defaults only change when this line does, not on a dependency update.
+// Newer SDKs mark it deprecated in favour of versions this crate has not been tested with
+#[allow(deprecated)]
+pub async fn load_aws_config() -> aws_config::SdkConfig {
+    aws_config::defaults(BehaviorVersion::v2025_08_07()).load().await
 }
 
 impl DynamoDbStorage {
     pub async fn new() -> Result<Self, StorageError> {
         let table_name = std::env::var("DYNAMODB_TABLE_NAME")
             .map_err(|_| StorageError::Configuration("DYNAMODB_TABLE_NAME environment variable not set".to_string()))?;
+        let conversation_table_name = std::env::var("CONVERSATION_TABLE_NAME").ok();
+        let ingest_table_name = std::env::var("INGEST_TABLE_NAME").ok();
+        let usage_table_name = std::env::var("USAGE_TABLE_NAME").ok();
 
-        let config = aws_config::defaults(BehaviorVersion::v2025_01_17())
-            .load()
-            .await;
+        let config = load_aws_config().await;
         
         let client = DynamoDbClient::new(&config);
         
@@ -73,9 +216,33 @@ impl DynamoDbStorage {
         Ok(Self {
             client,
             table_name,
+            conversation_table_name,
+            ingest_table_name,
+            usage_table_name,
         })
     }
 
+    #[allow(clippy::result_large_err)]
+    fn conversation_table(&self) -> Result<&str, StorageError> {
+        self.conversation_table_name
+            .as_deref()
+            .ok_or_else(|| StorageError::Configuration("CONVERSATION_TABLE_NAME environment variable not set".to_string()))
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn ingest_table(&self) -> Result<&str, StorageError> {
+        self.ingest_table_name
+            .as_deref()
+            .ok_or_else(|| StorageError::Configuration("INGEST_TABLE_NAME environment variable not set".to_string()))
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn usage_table(&self) -> Result<&str, StorageError> {
+        self.usage_table_name
+            .as_deref()
+            .ok_or_else(|| StorageError::Configuration("USAGE_TABLE_NAME environment variable not set".to_string()))
+    }
+
     pub async fn get_user_model(&self, chat_id: &str) -> Result<Option<String>, StorageError> {
         info!("📖 Getting model preference for chat_id: {chat_id}");
         
@@ -90,13 +257,17 @@ impl DynamoDbStorage {
 
         match result.item {
             Some(item) => {
-                if let Some(model_attr) = item.get("ai_model") {
-                    if let Some(model) = model_attr.as_s().ok() {
-                        info!("✅ Found model preference for {chat_id}: {model}");
-                        return Ok(Some(model.clone()));
-                    }
+                if let Some(model_attr) = item.get("ai_model")
+                    && let Ok(model) = model_attr.as_s()
+                {
+                    info!("✅ Found model preference for {chat_id}: {model}");
+                    return Ok(Some(model.clone()));
+                }
+                if item.contains_key("ai_model") {
+                    warn!("⚠️ Invalid model data format for chat_id: {chat_id}");
+                } else {
+                    info!("🔍 No model preference found for chat_id: {chat_id}");
                 }
-                warn!("⚠️ Invalid model data format for chat_id: {chat_id}");
                 Ok(None)
             }
             None => {
@@ -108,28 +279,349 @@ impl DynamoDbStorage {
 
     pub async fn set_user_model(&self, chat_id: &str, model: &str) -> Result<(), StorageError> {
         info!("💾 Setting model preference for chat_id {chat_id} to: {model}");
-        
-        let preferences = UserPreferences::new(chat_id.to_string(), model.to_string());
-        
-        let mut item = HashMap::new();
-        item.insert("chat_id".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(preferences.chat_id));
-        item.insert("ai_model".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(preferences.ai_model));
-        item.insert("updated_at".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(preferences.updated_at));
-        
+
+        self.update_preference(chat_id, "ai_model", Some(aws_sdk_dynamodb::types::AttributeValue::S(model.to_string())))
+            .await?;
+
+        info!("✅ Successfully saved model preference for chat_id: {chat_id}");
+        Ok(())
+    }
+
+    // Update a single preference attribute in place so other per-chat settings are preserved
+    async fn update_preference(
+        &self,
+        chat_id: &str,
+        attribute: &str,
+        value: Option<aws_sdk_dynamodb::types::AttributeValue>,
+    ) -> Result<(), StorageError> {
+        let mut preferences = UserPreferences::new(chat_id.to_string(), String::new());
+        // Only chats expire. Bot-wide items such as the runtime config must never be removed by the TTL,
+        // and lose any expiry an earlier write gave them
+        let chat_item = chat_id.parse::<i64>().is_ok();
+        if !chat_item {
+            preferences.expires_at = None;
+        }
+
+        let mut request = self
+            .client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S(preferences.chat_id))
+            .expression_attribute_names("#attr", attribute)
+            .expression_attribute_values(":updated_at", aws_sdk_dynamodb::types::AttributeValue::S(preferences.updated_at));
+
         if let Some(expires_at) = preferences.expires_at {
-            item.insert("expires_at".to_string(), aws_sdk_dynamodb::types::AttributeValue::N(expires_at.to_string()));
+            request = request.expression_attribute_values(
+                ":expires_at",
+                aws_sdk_dynamodb::types::AttributeValue::N(expires_at.to_string()),
+            );
         }
+        let ttl_clause = if preferences.expires_at.is_some() { ", expires_at = :expires_at" } else { "" };
+        let mut removed = Vec::new();
+        if value.is_none() {
+            removed.push("#attr");
+        }
+        if !chat_item {
+            removed.push("expires_at");
+        }
+        let remove_clause = if removed.is_empty() { String::new() } else { format!(" REMOVE {}", removed.join(", ")) };
 
-        self.client
-            .put_item()
+        request = match value {
+            Some(value) => request
+                .update_expression(format!("SET #attr = :value, updated_at = :updated_at{ttl_clause}{remove_clause}"))
+                .expression_attribute_values(":value", value),
+            None => request.update_expression(format!("SET updated_at = :updated_at{ttl_clause}{remove_clause}")),
+        };
+
+        request
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+        Ok(())
+    }
+
+    async fn get_preference(
+        &self,
+        chat_id: &str,
+        attribute: &str,
+    ) -> Result<Option<aws_sdk_dynamodb::types::AttributeValue>, StorageError> {
+        let result = self
+            .client
+            .get_item()
             .table_name(&self.table_name)
-            .set_item(Some(item))
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S(chat_id.to_string()))
+            .projection_expression("#attr")
+            .expression_attribute_names("#attr", attribute)
             .send()
             .await
             .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
 
-        info!("✅ Successfully saved model preference for chat_id: {chat_id}");
-        Ok(())
+        Ok(result.item.and_then(|mut item| item.remove(attribute)))
+    }
+
+    pub async fn get_persona_settings(&self, chat_id: &str) -> Result<PersonaSettings, StorageError> {
+        info!("📖 Getting persona settings for chat_id: {chat_id}");
+
+        match self
+            .get_preference(chat_id, "persona_settings")
+            .await?
+            .and_then(|value| value.as_s().ok().cloned())
+        {
+            Some(json) => serde_json::from_str(&json).map_err(|e| StorageError::Serialization(e.to_string())),
+            None => Ok(PersonaSettings::default()),
+        }
+    }
+
+    pub async fn set_persona_settings(&self, chat_id: &str, settings: &PersonaSettings) -> Result<(), StorageError> {
+        info!("💾 Saving persona settings for chat_id: {chat_id}");
+
+        let json = serde_json::to_string(settings).map_err(|e| StorageError::Serialization(e.to_string()))?;
+        self.update_preference(chat_id, "persona_settings", Some(aws_sdk_dynamodb::types::AttributeValue::S(json)))
+            .await
+    }
+
+    pub async fn get_ai_settings(&self, chat_id: &str) -> Result<AiSettings, StorageError> {
+        info!("📖 Getting AI settings for chat_id: {chat_id}");
+
+        match self
+            .get_preference(chat_id, "ai_settings")
+            .await?
+            .and_then(|value| value.as_s().ok().cloned())
+        {
+            Some(json) => serde_json::from_str(&json).map_err(|e| StorageError::Serialization(e.to_string())),
+            None => Ok(AiSettings::default()),
+        }
+    }
+
+    pub async fn set_ai_settings(&self, chat_id: &str, settings: &AiSettings) -> Result<(), StorageError> {
+        info!("💾 Saving AI settings for chat_id {chat_id}: {settings:?}");
+
+        let value = if *settings == AiSettings::default() {
+            None
+        } else {
+            let json = serde_json::to_string(settings).map_err(|e| StorageError::Serialization(e.to_string()))?;
+            Some(aws_sdk_dynamodb::types::AttributeValue::S(json))
+        };
+        self.update_preference(chat_id, "ai_settings", value).await
+    }
+
+    pub async fn get_ai_budget(&self, chat_id: &str) -> Result<AiBudget, StorageError> {
+        info!("📖 Getting AI budget for chat_id: {chat_id}");
+
+        match self
+            .get_preference(chat_id, "ai_budget")
+            .await?
+            .and_then(|value| value.as_s().ok().cloned())
+        {
+            Some(json) => serde_json::from_str(&json).map_err(|e| StorageError::Serialization(e.to_string())),
+            None => Ok(AiBudget::default()),
+        }
+    }
+
+    pub async fn set_ai_budget(&self, chat_id: &str, budget: &AiBudget) -> Result<(), StorageError> {
+        info!("💾 Saving AI budget for chat_id {chat_id}: {budget:?}");
+
+        let value = if budget.chat_tokens.is_none() && budget.user_tokens.is_none() {
+            None
+        } else {
+            let json = serde_json::to_string(budget).map_err(|e| StorageError::Serialization(e.to_string()))?;
+            Some(aws_sdk_dynamodb::types::AttributeValue::S(json))
+        };
+        self.update_preference(chat_id, "ai_budget", value).await
+    }
+
+    pub async fn get_translate_language(&self, chat_id: &str) -> Result<Option<String>, StorageError> {
+        Ok(self
+            .get_preference(chat_id, "translate_language")
+            .await?
+            .and_then(|value| value.as_s().ok().cloned()))
+    }
+
+    pub async fn set_translate_language(&self, chat_id: &str, language: Option<&str>) -> Result<(), StorageError> {
+        info!("💾 Setting translation language for chat_id {chat_id} to {language:?}");
+
+        let value = language.map(|language| aws_sdk_dynamodb::types::AttributeValue::S(language.to_string()));
+        self.update_preference(chat_id, "translate_language", value).await
+    }
+
+    pub async fn get_moderation_mode(&self, chat_id: &str) -> Result<Option<String>, StorageError> {
+        Ok(self
+            .get_preference(chat_id, "moderation_mode")
+            .await?
+            .and_then(|value| value.as_s().ok().cloned()))
+    }
+
+    pub async fn set_moderation_mode(&self, chat_id: &str, mode: &str) -> Result<(), StorageError> {
+        info!("💾 Setting moderation mode for chat_id {chat_id} to {mode}");
+
+        // Stored even when "off" so the chat keeps its choice if MODERATION_DEFAULT changes
+        let value = aws_sdk_dynamodb::types::AttributeValue::S(mode.to_string());
+        self.update_preference(chat_id, "moderation_mode", Some(value)).await
+    }
+
+    pub async fn get_alert_digest_window(&self, chat_id: &str) -> Result<Option<u64>, StorageError> {
+        Ok(self
+            .get_preference(chat_id, "alert_digest_window")
+            .await?
+            .and_then(|value| value.as_n().ok().and_then(|n| n.parse().ok())))
+    }
+
+    pub async fn set_alert_digest_window(&self, chat_id: &str, seconds: u64) -> Result<(), StorageError> {
+        info!("💾 Setting alert digest window for chat_id {chat_id} to {seconds}s");
+
+        // Stored even when 0 so the chat keeps its choice if ALERT_DIGEST_WINDOW changes
+        let value = aws_sdk_dynamodb::types::AttributeValue::N(seconds.to_string());
+        self.update_preference(chat_id, "alert_digest_window", Some(value)).await
+    }
+
+    pub async fn get_disclaimer(&self, chat_id: &str) -> Result<Option<String>, StorageError> {
+        Ok(self
+            .get_preference(chat_id, "disclaimer")
+            .await?
+            .and_then(|value| value.as_s().ok().cloned()))
+    }
+
+    pub async fn set_disclaimer(&self, chat_id: &str, text: Option<&str>) -> Result<(), StorageError> {
+        info!("💾 Setting disclaimer for chat_id {chat_id}");
+
+        let value = text.map(|text| aws_sdk_dynamodb::types::AttributeValue::S(text.to_string()));
+        self.update_preference(chat_id, "disclaimer", value).await
+    }
+
+    pub async fn get_ai_enabled(&self, chat_id: &str) -> Result<bool, StorageError> {
+        Ok(!self
+            .get_preference(chat_id, "ai_disabled")
+            .await?
+            .and_then(|value| value.as_bool().ok().copied())
+            .unwrap_or(false))
+    }
+
+    pub async fn set_ai_enabled(&self, chat_id: &str, enabled: bool) -> Result<(), StorageError> {
+        info!("💾 Setting AI enabled for chat_id {chat_id} to {enabled}");
+
+        // Enabled is the default, so only a disabled chat stores the attribute
+        let value = (!enabled).then_some(aws_sdk_dynamodb::types::AttributeValue::Bool(true));
+        self.update_preference(chat_id, "ai_disabled", value).await
+    }
+
+    pub async fn get_paused_until(&self, chat_id: &str) -> Result<Option<i64>, StorageError> {
+        Ok(self
+            .get_preference(chat_id, "paused_until")
+            .await?
+            .and_then(|value| value.as_n().ok().and_then(|n| n.parse().ok())))
+    }
+
+    pub async fn set_paused_until(&self, chat_id: &str, until: Option<i64>) -> Result<(), StorageError> {
+        info!("💾 Setting paused_until for chat_id {chat_id} to {until:?}");
+
+        let value = until.map(|until| aws_sdk_dynamodb::types::AttributeValue::N(until.to_string()));
+        self.update_preference(chat_id, "paused_until", value).await
+    }
+
+    // Remove an expired pause only if it is still the one that was read, so exactly one caller announces the resume
+    pub async fn clear_pause_if(&self, chat_id: &str, until: i64) -> Result<bool, StorageError> {
+        let result = self
+            .client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S(chat_id.to_string()))
+            .update_expression("REMOVE paused_until")
+            .condition_expression("paused_until = :until")
+            .expression_attribute_values(":until", aws_sdk_dynamodb::types::AttributeValue::N(until.to_string()))
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                let error = DynamoDbError::from(e);
+                if matches!(error, DynamoDbError::ConditionalCheckFailedException(_)) {
+                    Ok(false)
+                } else {
+                    Err(StorageError::DynamoDb(error))
+                }
+            }
+        }
+    }
+
+    pub async fn get_autodelete_seconds(&self, chat_id: &str) -> Result<Option<u64>, StorageError> {
+        Ok(self
+            .get_preference(chat_id, "autodelete_seconds")
+            .await?
+            .and_then(|value| value.as_n().ok().and_then(|n| n.parse().ok())))
+    }
+
+    pub async fn set_autodelete_seconds(&self, chat_id: &str, seconds: Option<u64>) -> Result<(), StorageError> {
+        info!("💾 Setting autodelete for chat_id {chat_id} to {seconds:?}");
+
+        let value = seconds.map(|seconds| aws_sdk_dynamodb::types::AttributeValue::N(seconds.to_string()));
+        self.update_preference(chat_id, "autodelete_seconds", value).await
+    }
+
+    // Delivery types ("alerts", "digests") sent without a notification sound in a chat
+    pub async fn get_silent_deliveries(&self, chat_id: &str) -> Result<Vec<String>, StorageError> {
+        Ok(self
+            .get_preference(chat_id, "silent_deliveries")
+            .await?
+            .and_then(|value| value.as_ss().ok().cloned())
+            .unwrap_or_default())
+    }
+
+    pub async fn set_silent_deliveries(&self, chat_id: &str, deliveries: Vec<String>) -> Result<(), StorageError> {
+        info!("💾 Setting silent deliveries for chat_id {chat_id} to {deliveries:?}");
+
+        // DynamoDB rejects empty sets, so no silent deliveries drops the attribute
+        let value = (!deliveries.is_empty()).then_some(aws_sdk_dynamodb::types::AttributeValue::Ss(deliveries));
+        self.update_preference(chat_id, "silent_deliveries", value).await
+    }
+
+    pub async fn get_flair_enabled(&self, chat_id: &str) -> Result<bool, StorageError> {
+        Ok(self
+            .get_preference(chat_id, "flair")
+            .await?
+            .and_then(|value| value.as_bool().ok().copied())
+            .unwrap_or(false))
+    }
+
+    pub async fn set_flair_enabled(&self, chat_id: &str, enabled: bool) -> Result<(), StorageError> {
+        info!("💾 Setting flair for chat_id {chat_id} to {enabled}");
+
+        // Off is the default, so drop the attribute instead of storing false
+        let value = enabled.then_some(aws_sdk_dynamodb::types::AttributeValue::Bool(true));
+        self.update_preference(chat_id, "flair", value).await
+    }
+
+    pub async fn get_voice_replies(&self, chat_id: &str) -> Result<bool, StorageError> {
+        info!("📖 Getting voice reply setting for chat_id: {chat_id}");
+
+        Ok(self
+            .get_preference(chat_id, "voice_replies")
+            .await?
+            .and_then(|value| value.as_bool().ok().copied())
+            .unwrap_or(false))
+    }
+
+    pub async fn set_voice_replies(&self, chat_id: &str, enabled: bool) -> Result<(), StorageError> {
+        info!("💾 Setting voice replies for chat_id {chat_id} to {enabled}");
+
+        // Disabled is the default, so drop the attribute instead of storing false
+        let value = enabled.then_some(aws_sdk_dynamodb::types::AttributeValue::Bool(true));
+        self.update_preference(chat_id, "voice_replies", value).await
+    }
+
+    // Settings completed through /setup, encrypted by the caller, kept under a reserved preferences key
+    pub async fn get_runtime_config(&self) -> Result<Option<String>, StorageError> {
+        Ok(self
+            .get_preference(RUNTIME_CONFIG_KEY, "runtime_config")
+            .await?
+            .and_then(|value| value.as_s().ok().cloned()))
+    }
+
+    pub async fn set_runtime_config(&self, encrypted: &str) -> Result<(), StorageError> {
+        info!("💾 Saving runtime configuration");
+
+        let value = aws_sdk_dynamodb::types::AttributeValue::S(encrypted.to_string());
+        self.update_preference(RUNTIME_CONFIG_KEY, "runtime_config", Some(value)).await
     }
 
     #[allow(dead_code)]
@@ -170,14 +662,1124 @@ impl DynamoDbStorage {
         info!("📊 Found {} user preferences", preferences.len());
         Ok(preferences)
     }
-}
 
-// Factory function to create storage client
-pub async fn create_storage() -> Result<DynamoDbStorage, StorageError> {
+    pub async fn get_conversation_history(&self, chat_id: &str) -> Result<Vec<ConversationMessage>, StorageError> {
+        info!("📖 Getting conversation history for chat_id: {chat_id}");
+
+        let result = self
+            .client
+            .get_item()
+            .table_name(self.conversation_table()?)
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S(chat_id.to_string()))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+
+        let Some(messages) = result
+            .item
+            .as_ref()
+            .and_then(|item| item.get("messages"))
+            .and_then(|v| v.as_s().ok())
+        else {
+            info!("🔍 No conversation history found for chat_id: {chat_id}");
+            return Ok(Vec::new());
+        };
+
+        let history: Vec<ConversationMessage> = serde_json::from_str(messages)
+            .map_err(|e| StorageError::Serialization(e.to_string()))?;
+        info!("✅ Found {} history messages for chat_id: {chat_id}", history.len());
+        Ok(history)
+    }
+
+    pub async fn set_conversation_history(
+        &self,
+        chat_id: &str,
+        history: &[ConversationMessage],
+    ) -> Result<(), StorageError> {
+        info!("💾 Saving {} history messages for chat_id: {chat_id}", history.len());
+
+        let messages = serde_json::to_string(history)
+            .map_err(|e| StorageError::Serialization(e.to_string()))?;
+        let now = chrono::Utc::now();
+        let expires_at = now.timestamp() + (30 * 24 * 60 * 60); // 30 days from last message
+
+        let mut item = HashMap::new();
+        item.insert("chat_id".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(chat_id.to_string()));
+        item.insert("messages".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(messages));
+        item.insert("updated_at".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(now.to_rfc3339()));
+        item.insert("expires_at".to_string(), aws_sdk_dynamodb::types::AttributeValue::N(expires_at.to_string()));
+
+        self.client
+            .put_item()
+            .table_name(self.conversation_table()?)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+
+        info!("✅ Successfully saved conversation history for chat_id: {chat_id}");
+        Ok(())
+    }
+
+    pub async fn delete_conversation_history(&self, chat_id: &str) -> Result<usize, StorageError> {
+        info!("🗑️ Deleting conversation history for chat_id: {chat_id}");
+
+        let result = self
+            .client
+            .delete_item()
+            .table_name(self.conversation_table()?)
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S(chat_id.to_string()))
+            .return_values(aws_sdk_dynamodb::types::ReturnValue::AllOld)
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+
+        let deleted = result
+            .attributes
+            .as_ref()
+            .and_then(|item| item.get("messages"))
+            .and_then(|v| v.as_s().ok())
+            .and_then(|messages| serde_json::from_str::<Vec<ConversationMessage>>(messages).ok())
+            .map(|history| history.len())
+            .unwrap_or(0);
+
+        self.client
+            .delete_item()
+            .table_name(self.conversation_table()?)
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S(format!("summary#{chat_id}")))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+
+        self.delete_history_snapshots(chat_id).await?;
+
+        info!("✅ Deleted {deleted} history messages for chat_id: {chat_id}");
+        Ok(deleted)
+    }
+
+    // The history right after an AI reply, kept under the reply's message id so a later reply to it can branch from there
+    pub async fn get_history_snapshot(
+        &self,
+        chat_id: &str,
+        message_id: i32,
+    ) -> Result<Option<Vec<ConversationMessage>>, StorageError> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(self.conversation_table()?)
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S(format!("snapshot#{chat_id}#{message_id}")))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+
+        let Some(messages) = result
+            .item
+            .as_ref()
+            .and_then(|item| item.get("messages"))
+            .and_then(|v| v.as_s().ok())
+        else {
+            return Ok(None);
+        };
+        serde_json::from_str(messages)
+            .map(Some)
+            .map_err(|e| StorageError::Serialization(e.to_string()))
+    }
+
+    // Save a snapshot and drop the oldest ones beyond `keep`, so a chat holds a bounded number of branch points
+    pub async fn set_history_snapshot(
+        &self,
+        chat_id: &str,
+        message_id: i32,
+        history: &[ConversationMessage],
+        keep: usize,
+    ) -> Result<(), StorageError> {
+        let messages = serde_json::to_string(history)
+            .map_err(|e| StorageError::Serialization(e.to_string()))?;
+        let now = chrono::Utc::now();
+        let expires_at = now.timestamp() + (30 * 24 * 60 * 60); // Same lifetime as the history it copies
+
+        let mut item = HashMap::new();
+        item.insert(
+            "chat_id".to_string(),
+            aws_sdk_dynamodb::types::AttributeValue::S(format!("snapshot#{chat_id}#{message_id}")),
+        );
+        item.insert("messages".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(messages));
+        item.insert("expires_at".to_string(), aws_sdk_dynamodb::types::AttributeValue::N(expires_at.to_string()));
+
+        self.client
+            .put_item()
+            .table_name(self.conversation_table()?)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+
+        // The index lets /clear find every snapshot of a chat without a scan
+        let index = format!("snapshots#{chat_id}");
+        self.update_string_set(&index, "messages", true, vec![message_id.to_string()]).await?;
+        let mut ids: Vec<i32> = self
+            .get_string_set(&index, "messages")
+            .await?
+            .iter()
+            .filter_map(|id| id.parse().ok())
+            .collect();
+        if ids.len() > keep {
+            // Message ids only grow within a chat, so the smallest are the oldest
+            ids.sort_unstable();
+            let stale = ids[..ids.len() - keep].to_vec();
+            for id in &stale {
+                self.delete_conversation_item(&format!("snapshot#{chat_id}#{id}")).await?;
+            }
+            self.update_string_set(&index, "messages", false, stale.iter().map(i32::to_string).collect())
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn delete_history_snapshots(&self, chat_id: &str) -> Result<(), StorageError> {
+        let index = format!("snapshots#{chat_id}");
+        for id in self.get_string_set(&index, "messages").await? {
+            self.delete_conversation_item(&format!("snapshot#{chat_id}#{id}")).await?;
+        }
+        self.delete_conversation_item(&index).await
+    }
+
+    async fn delete_conversation_item(&self, key: &str) -> Result<(), StorageError> {
+        self.client
+            .delete_item()
+            .table_name(self.conversation_table()?)
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S(key.to_string()))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+        Ok(())
+    }
+
+    // The rolling summary of compacted history shares the conversation table under a prefixed key
+    pub async fn get_conversation_summary(&self, chat_id: &str) -> Result<Option<String>, StorageError> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(self.conversation_table()?)
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S(format!("summary#{chat_id}")))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+
+        Ok(result
+            .item
+            .as_ref()
+            .and_then(|item| item.get("summary"))
+            .and_then(|v| v.as_s().ok())
+            .cloned())
+    }
+
+    pub async fn set_conversation_summary(&self, chat_id: &str, summary: &str) -> Result<(), StorageError> {
+        info!("💾 Saving conversation summary for chat_id: {chat_id} ({} chars)", summary.len());
+
+        let now = chrono::Utc::now();
+        let expires_at = now.timestamp() + (30 * 24 * 60 * 60); // Same lifetime as the history it summarizes
+
+        let mut item = HashMap::new();
+        item.insert("chat_id".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(format!("summary#{chat_id}")));
+        item.insert("summary".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(summary.to_string()));
+        item.insert("updated_at".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(now.to_rfc3339()));
+        item.insert("expires_at".to_string(), aws_sdk_dynamodb::types::AttributeValue::N(expires_at.to_string()));
+
+        self.client
+            .put_item()
+            .table_name(self.conversation_table()?)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+        Ok(())
+    }
+
+    // Recent group messages share the conversation table under a prefixed key
+    pub async fn get_recent_messages(&self, chat_id: &str) -> Result<Vec<RecentMessage>, StorageError> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(self.conversation_table()?)
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S(format!("recent#{chat_id}")))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+
+        match result
+            .item
+            .as_ref()
+            .and_then(|item| item.get("messages"))
+            .and_then(|v| v.as_s().ok())
+        {
+            Some(messages) => serde_json::from_str(messages).map_err(|e| StorageError::Serialization(e.to_string())),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub async fn set_recent_messages(&self, chat_id: &str, messages: &[RecentMessage]) -> Result<(), StorageError> {
+        let json = serde_json::to_string(messages).map_err(|e| StorageError::Serialization(e.to_string()))?;
+        let now = chrono::Utc::now();
+        let expires_at = now.timestamp() + (7 * 24 * 60 * 60); // 7 days from last message
+
+        let mut item = HashMap::new();
+        item.insert("chat_id".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(format!("recent#{chat_id}")));
+        item.insert("messages".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(json));
+        item.insert("updated_at".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(now.to_rfc3339()));
+        item.insert("expires_at".to_string(), aws_sdk_dynamodb::types::AttributeValue::N(expires_at.to_string()));
+
+        self.client
+            .put_item()
+            .table_name(self.conversation_table()?)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+        Ok(())
+    }
+
+    // The open alert digest of a chat is kept in the conversation table under a prefixed key
+    pub async fn get_alert_digest(&self, chat_id: &str) -> Result<Option<AlertDigest>, StorageError> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(self.conversation_table()?)
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S(format!("digest#{chat_id}")))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+
+        let Some(digest) = result
+            .item
+            .as_ref()
+            .and_then(|item| item.get("digest"))
+            .and_then(|v| v.as_s().ok())
+        else {
+            return Ok(None);
+        };
+        let digest: AlertDigest =
+            serde_json::from_str(digest).map_err(|e| StorageError::Serialization(e.to_string()))?;
+        // DynamoDB removes expired items lazily, so check the window here as well
+        if digest.expires_at <= chrono::Utc::now().timestamp() {
+            return Ok(None);
+        }
+        Ok(Some(digest))
+    }
+
+    pub async fn set_alert_digest(&self, chat_id: &str, digest: &AlertDigest) -> Result<(), StorageError> {
+        let json = serde_json::to_string(digest).map_err(|e| StorageError::Serialization(e.to_string()))?;
+
+        let mut item = HashMap::new();
+        item.insert("chat_id".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(format!("digest#{chat_id}")));
+        item.insert("digest".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(json));
+        item.insert(
+            "expires_at".to_string(),
+            aws_sdk_dynamodb::types::AttributeValue::N(digest.expires_at.to_string()),
+        );
+
+        self.client
+            .put_item()
+            .table_name(self.conversation_table()?)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+        Ok(())
+    }
+
+    // AI request samples for /aistatus are kept in the conversation table under a prefixed key
+    pub async fn get_ai_metrics(&self, provider: &str) -> Result<Option<String>, StorageError> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(self.conversation_table()?)
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S(format!("metrics#{provider}")))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+
+        Ok(result
+            .item
+            .as_ref()
+            .and_then(|item| item.get("samples"))
+            .and_then(|v| v.as_s().ok())
+            .cloned())
+    }
+
+    pub async fn set_ai_metrics(&self, provider: &str, samples: &str) -> Result<(), StorageError> {
+        let mut item = HashMap::new();
+        item.insert("chat_id".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(format!("metrics#{provider}")));
+        item.insert("samples".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(samples.to_string()));
+        item.insert(
+            "updated_at".to_string(),
+            aws_sdk_dynamodb::types::AttributeValue::S(chrono::Utc::now().to_rfc3339()),
+        );
+
+        self.client
+            .put_item()
+            .table_name(self.conversation_table()?)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+        Ok(())
+    }
+
+    // Captured outbound messages for /outbound are numbered in sequence and written one item per message into
+    // a ring of slots, kept for a week in case debugging is left on
+    pub async fn next_outbound_sequence(&self) -> Result<u64, StorageError> {
+        let result = self
+            .client
+            .update_item()
+            .table_name(self.conversation_table()?)
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S("outbound#seq".to_string()))
+            .update_expression("SET expires_at = :expires_at ADD #seq :one")
+            .expression_attribute_names("#seq", "seq")
+            .expression_attribute_values(":one", aws_sdk_dynamodb::types::AttributeValue::N("1".to_string()))
+            .expression_attribute_values(
+                ":expires_at",
+                aws_sdk_dynamodb::types::AttributeValue::N(
+                    (chrono::Utc::now().timestamp() + OUTBOUND_TTL_SECS).to_string(),
+                ),
+            )
+            .return_values(aws_sdk_dynamodb::types::ReturnValue::UpdatedNew)
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+
+        result
+            .attributes
+            .as_ref()
+            .and_then(|item| item.get("seq"))
+            .and_then(|v| v.as_n().ok())
+            .and_then(|n| n.parse().ok())
+            .ok_or_else(|| StorageError::Serialization("outbound sequence missing from update".to_string()))
+    }
+
+    pub async fn get_outbound_sequence(&self) -> Result<u64, StorageError> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(self.conversation_table()?)
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S("outbound#seq".to_string()))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+
+        Ok(result
+            .item
+            .as_ref()
+            .and_then(|item| item.get("seq"))
+            .and_then(|v| v.as_n().ok())
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(0))
+    }
+
+    pub async fn set_outbound_record(&self, slot: u64, record: &str) -> Result<(), StorageError> {
+        let now = chrono::Utc::now();
+
+        let mut item = HashMap::new();
+        item.insert("chat_id".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(format!("outbound#{slot}")));
+        item.insert("record".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(record.to_string()));
+        item.insert("updated_at".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(now.to_rfc3339()));
+        item.insert(
+            "expires_at".to_string(),
+            aws_sdk_dynamodb::types::AttributeValue::N((now.timestamp() + OUTBOUND_TTL_SECS).to_string()),
+        );
+
+        self.client
+            .put_item()
+            .table_name(self.conversation_table()?)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+        Ok(())
+    }
+
+    // Captured records in the given slots, skipping empty ones
+    pub async fn get_outbound_records(&self, slots: &[u64]) -> Result<Vec<String>, StorageError> {
+        let keys: Vec<String> = slots.iter().map(|slot| format!("outbound#{slot}")).collect();
+        Ok(self
+            .batch_get_items(&keys)
+            .await?
+            .iter()
+            .filter_map(|item| item.get("record").and_then(|v| v.as_s().ok()).cloned())
+            .collect())
+    }
+
+    // Read conversation table items by key, in batches of the 100 keys BatchGetItem allows, asking again
+    // for keys DynamoDB skipped under load. Items come back in no particular order
+    async fn batch_get_items(
+        &self,
+        keys: &[String],
+    ) -> Result<Vec<HashMap<String, aws_sdk_dynamodb::types::AttributeValue>>, StorageError> {
+        let table = self.conversation_table()?;
+        let mut items = Vec::new();
+        for batch in keys.chunks(100) {
+            let mut keys: Vec<HashMap<String, aws_sdk_dynamodb::types::AttributeValue>> = batch
+                .iter()
+                .map(|key| {
+                    HashMap::from([("chat_id".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(key.clone()))])
+                })
+                .collect();
+            while !keys.is_empty() {
+                let request = aws_sdk_dynamodb::types::KeysAndAttributes::builder()
+                    .set_keys(Some(keys))
+                    .build()
+                    .map_err(|e| StorageError::Configuration(e.to_string()))?;
+                let result = self
+                    .client
+                    .batch_get_item()
+                    .request_items(table, request)
+                    .send()
+                    .await
+                    .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+
+                items.extend(result.responses.and_then(|mut responses| responses.remove(table)).unwrap_or_default());
+                keys = result
+                    .unprocessed_keys
+                    .and_then(|mut unprocessed| unprocessed.remove(table))
+                    .map(|request| request.keys)
+                    .unwrap_or_default();
+            }
+        }
+        Ok(items)
+    }
+
+    // Cached AI replies are kept in the conversation table under a prefixed key
+    pub async fn get_cached_reply(&self, key: &str) -> Result<Option<String>, StorageError> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(self.conversation_table()?)
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S(format!("cache#{key}")))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+
+        let Some(item) = result.item else {
+            return Ok(None);
+        };
+        // DynamoDB removes expired items lazily, so check the TTL here as well
+        let expires_at = item
+            .get("expires_at")
+            .and_then(|v| v.as_n().ok())
+            .and_then(|n| n.parse::<i64>().ok());
+        if expires_at.is_some_and(|expires_at| expires_at <= chrono::Utc::now().timestamp()) {
+            return Ok(None);
+        }
+        Ok(item.get("reply").and_then(|v| v.as_s().ok()).cloned())
+    }
+
+    pub async fn set_cached_reply(&self, key: &str, reply: &str, ttl_seconds: i64) -> Result<(), StorageError> {
+        let now = chrono::Utc::now();
+
+        let mut item = HashMap::new();
+        item.insert("chat_id".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(format!("cache#{key}")));
+        item.insert("reply".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(reply.to_string()));
+        item.insert("updated_at".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(now.to_rfc3339()));
+        item.insert(
+            "expires_at".to_string(),
+            aws_sdk_dynamodb::types::AttributeValue::N((now.timestamp() + ttl_seconds).to_string()),
+        );
+
+        self.client
+            .put_item()
+            .table_name(self.conversation_table()?)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+        Ok(())
+    }
+
+    // Add or remove string set members of a conversation table item; DynamoDB applies both atomically,
+    // so concurrent writers never lose each other's entries
+    async fn update_string_set(&self, key: &str, attribute: &str, add: bool, values: Vec<String>) -> Result<(), StorageError> {
+        if values.is_empty() {
+            return Ok(());
+        }
+        let action = if add { "ADD" } else { "DELETE" };
+        self.client
+            .update_item()
+            .table_name(self.conversation_table()?)
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S(key.to_string()))
+            .update_expression(format!("{action} #attr :values"))
+            .expression_attribute_names("#attr", attribute)
+            .expression_attribute_values(":values", aws_sdk_dynamodb::types::AttributeValue::Ss(values))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+        Ok(())
+    }
+
+    async fn get_string_set(&self, key: &str, attribute: &str) -> Result<Vec<String>, StorageError> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(self.conversation_table()?)
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S(key.to_string()))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+        Ok(result
+            .item
+            .as_ref()
+            .and_then(|item| item.get(attribute))
+            .and_then(|v| v.as_ss().ok())
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    // Chats with auto-delete turned on, so the sweeper knows whose queues to check
+    pub async fn get_autodelete_chats(&self) -> Result<Vec<String>, StorageError> {
+        self.get_string_set("delete#chats", "chats").await
+    }
+
+    pub async fn set_autodelete_chat(&self, chat_id: &str, enabled: bool) -> Result<(), StorageError> {
+        self.update_string_set("delete#chats", "chats", enabled, vec![chat_id.to_string()]).await
+    }
+
+    // Pending deletions of a chat are "<delete_at>:<message_id>" entries
+    pub async fn get_pending_deletes(&self, chat_id: &str) -> Result<Vec<String>, StorageError> {
+        self.get_string_set(&format!("delete#{chat_id}"), "pending").await
+    }
+
+    pub async fn add_pending_delete(&self, chat_id: &str, entry: String) -> Result<(), StorageError> {
+        self.update_string_set(&format!("delete#{chat_id}"), "pending", true, vec![entry]).await
+    }
+
+    pub async fn remove_pending_deletes(&self, chat_id: &str, entries: Vec<String>) -> Result<(), StorageError> {
+        self.update_string_set(&format!("delete#{chat_id}"), "pending", false, entries).await
+    }
+
+    // Members who opted in to be mentioned in a chat's or topic's alerts, as "<user_id>:<username>:<consented_at>"
+    pub async fn get_alert_mentions(&self, scope: &str) -> Result<Vec<String>, StorageError> {
+        self.get_string_set(&format!("mentions#{scope}"), "users").await
+    }
+
+    pub async fn add_alert_mention(&self, scope: &str, entry: String) -> Result<(), StorageError> {
+        self.update_string_set(&format!("mentions#{scope}"), "users", true, vec![entry]).await
+    }
+
+    pub async fn remove_alert_mentions(&self, scope: &str, entries: Vec<String>) -> Result<(), StorageError> {
+        self.update_string_set(&format!("mentions#{scope}"), "users", false, entries).await
+    }
+
+    // Tasks in a chat still running past their progress delay, as "<task_id>:<user_id>"
+    pub async fn get_running_tasks(&self, chat_id: &str) -> Result<Vec<String>, StorageError> {
+        self.get_string_set(&format!("tasks#{chat_id}"), "running").await
+    }
+
+    pub async fn add_running_task(&self, chat_id: &str, entry: String) -> Result<(), StorageError> {
+        self.update_string_set(&format!("tasks#{chat_id}"), "running", true, vec![entry]).await
+    }
+
+    pub async fn remove_running_task(&self, chat_id: &str, entry: String) -> Result<(), StorageError> {
+        self.update_string_set(&format!("tasks#{chat_id}"), "running", false, vec![entry]).await
+    }
+
+    // A chat's knowledge base is an index item listing its chunks as "<entry_id>#<n>" plus one item per chunk,
+    // so no single item grows towards DynamoDB's size limit
+    fn knowledge_index_key(chat_id: &str) -> String {
+        format!("kb#{chat_id}")
+    }
+
+    fn knowledge_chunk_key(chat_id: &str, chunk_key: &str) -> String {
+        format!("kb#{chat_id}#{chunk_key}")
+    }
+
+    pub async fn get_knowledge_chunk_count(&self, chat_id: &str) -> Result<usize, StorageError> {
+        Ok(self.get_string_set(&Self::knowledge_index_key(chat_id), "chunk_keys").await?.len())
+    }
+
+    // Every chunk of a chat's knowledge base, oldest entry first and each entry's chunks in order
+    pub async fn get_knowledge(&self, chat_id: &str) -> Result<Vec<KnowledgeChunk>, StorageError> {
+        let chunk_keys: Vec<String> = self
+            .get_string_set(&Self::knowledge_index_key(chat_id), "chunk_keys")
+            .await?
+            .iter()
+            .map(|chunk_key| Self::knowledge_chunk_key(chat_id, chunk_key))
+            .collect();
+
+        let mut chunks: Vec<(String, KnowledgeChunk)> = Vec::new();
+        for item in self.batch_get_items(&chunk_keys).await? {
+            let (Some(key), Some(chunk)) = (
+                item.get("chat_id").and_then(|v| v.as_s().ok()),
+                item.get("chunk").and_then(|v| v.as_s().ok()),
+            ) else {
+                continue;
+            };
+            let chunk = serde_json::from_str(chunk).map_err(|e| StorageError::Serialization(e.to_string()))?;
+            chunks.push((key.clone(), chunk));
+        }
+        chunks.sort_by(|(a_key, a), (b_key, b)| a.added_at.cmp(&b.added_at).then_with(|| a_key.cmp(b_key)));
+        Ok(chunks.into_iter().map(|(_, chunk)| chunk).collect())
+    }
+
+    // Store an entry's chunks, returning false without storing anything when the knowledge base would then
+    // hold more than max_chunks. The index update is conditional, so concurrent /teach calls can't overshoot
+    // the limit and never overwrite each other
+    pub async fn add_knowledge(
+        &self,
+        chat_id: &str,
+        chunks: &[KnowledgeChunk],
+        max_chunks: usize,
+    ) -> Result<bool, StorageError> {
+        info!("💾 Saving {} knowledge chunks for chat_id: {chat_id}", chunks.len());
+        if chunks.len() > max_chunks {
+            return Ok(false);
+        }
+        let table = self.conversation_table()?;
+        let chunk_keys: Vec<String> = chunks
+            .iter()
+            .enumerate()
+            .map(|(n, chunk)| format!("{}#{n:03}", chunk.id))
+            .collect();
+
+        // Unlike history, taught knowledge does not expire
+        for (chunk_key, chunk) in chunk_keys.iter().zip(chunks) {
+            let json = serde_json::to_string(chunk).map_err(|e| StorageError::Serialization(e.to_string()))?;
+            let mut item = HashMap::new();
+            item.insert(
+                "chat_id".to_string(),
+                aws_sdk_dynamodb::types::AttributeValue::S(Self::knowledge_chunk_key(chat_id, chunk_key)),
+            );
+            item.insert("chunk".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(json));
+            item.insert(
+                "updated_at".to_string(),
+                aws_sdk_dynamodb::types::AttributeValue::S(chrono::Utc::now().to_rfc3339()),
+            );
+            self.client
+                .put_item()
+                .table_name(table)
+                .set_item(Some(item))
+                .send()
+                .await
+                .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+        }
+
+        // Chunks only become part of the knowledge base once the index lists them
+        let result = self
+            .client
+            .update_item()
+            .table_name(table)
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S(Self::knowledge_index_key(chat_id)))
+            .update_expression("ADD chunk_keys :keys")
+            .condition_expression("attribute_not_exists(chunk_keys) OR size(chunk_keys) <= :room")
+            .expression_attribute_values(":keys", aws_sdk_dynamodb::types::AttributeValue::Ss(chunk_keys.clone()))
+            .expression_attribute_values(
+                ":room",
+                aws_sdk_dynamodb::types::AttributeValue::N((max_chunks - chunks.len()).to_string()),
+            )
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                let error = DynamoDbError::from(e);
+                if !matches!(error, DynamoDbError::ConditionalCheckFailedException(_)) {
+                    return Err(StorageError::DynamoDb(error));
+                }
+                self.delete_knowledge_chunks(chat_id, &chunk_keys).await?;
+                Ok(false)
+            }
+        }
+    }
+
+    // Remove an entry's chunks, returning whether the entry existed
+    pub async fn remove_knowledge(&self, chat_id: &str, entry_id: &str) -> Result<bool, StorageError> {
+        let index_key = Self::knowledge_index_key(chat_id);
+        let prefix = format!("{entry_id}#");
+        let chunk_keys: Vec<String> = self
+            .get_string_set(&index_key, "chunk_keys")
+            .await?
+            .into_iter()
+            .filter(|chunk_key| chunk_key.starts_with(&prefix))
+            .collect();
+        if chunk_keys.is_empty() {
+            return Ok(false);
+        }
+        self.update_string_set(&index_key, "chunk_keys", false, chunk_keys.clone()).await?;
+        self.delete_knowledge_chunks(chat_id, &chunk_keys).await?;
+        Ok(true)
+    }
+
+    pub async fn clear_knowledge(&self, chat_id: &str) -> Result<(), StorageError> {
+        let index_key = Self::knowledge_index_key(chat_id);
+        let chunk_keys = self.get_string_set(&index_key, "chunk_keys").await?;
+        self.client
+            .delete_item()
+            .table_name(self.conversation_table()?)
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S(index_key))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+        self.delete_knowledge_chunks(chat_id, &chunk_keys).await
+    }
+
+    async fn delete_knowledge_chunks(&self, chat_id: &str, chunk_keys: &[String]) -> Result<(), StorageError> {
+        let table = self.conversation_table()?;
+        for chunk_key in chunk_keys {
+            self.client
+                .delete_item()
+                .table_name(table)
+                .key(
+                    "chat_id",
+                    aws_sdk_dynamodb::types::AttributeValue::S(Self::knowledge_chunk_key(chat_id, chunk_key)),
+                )
+                .send()
+                .await
+                .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+        }
+        Ok(())
+    }
+
+    // Plugin key/value namespaces are kept in the conversation table under a prefixed key
+    pub async fn get_plugin_kv(&self, namespace: &str) -> Result<BTreeMap<String, String>, StorageError> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(self.conversation_table()?)
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S(format!("plugin#{namespace}")))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+
+        match result
+            .item
+            .as_ref()
+            .and_then(|item| item.get("entries"))
+            .and_then(|v| v.as_s().ok())
+        {
+            Some(entries) => serde_json::from_str(entries).map_err(|e| StorageError::Serialization(e.to_string())),
+            None => Ok(BTreeMap::new()),
+        }
+    }
+
+    pub async fn set_plugin_kv(&self, namespace: &str, entries: &BTreeMap<String, String>) -> Result<(), StorageError> {
+        let key = aws_sdk_dynamodb::types::AttributeValue::S(format!("plugin#{namespace}"));
+        if entries.is_empty() {
+            self.client
+                .delete_item()
+                .table_name(self.conversation_table()?)
+                .key("chat_id", key)
+                .send()
+                .await
+                .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+            return Ok(());
+        }
+
+        let json = serde_json::to_string(entries).map_err(|e| StorageError::Serialization(e.to_string()))?;
+        let now = chrono::Utc::now();
+        let expires_at = now.timestamp() + (365 * 24 * 60 * 60); // 1 year from last write
+
+        let mut item = HashMap::new();
+        item.insert("chat_id".to_string(), key);
+        item.insert("entries".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(json));
+        item.insert("updated_at".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(now.to_rfc3339()));
+        item.insert("expires_at".to_string(), aws_sdk_dynamodb::types::AttributeValue::N(expires_at.to_string()));
+
+        self.client
+            .put_item()
+            .table_name(self.conversation_table()?)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+        Ok(())
+    }
+
+    // Pending dialogue state shares the conversation table under a prefixed key, expired entries read as None
+    pub async fn get_dialogue_state(&self, key: &str) -> Result<Option<String>, StorageError> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(self.conversation_table()?)
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S(format!("dialogue#{key}")))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+
+        let Some(item) = result.item else {
+            return Ok(None);
+        };
+        // DynamoDB removes expired items lazily, so check the TTL here as well
+        let expires_at = item
+            .get("expires_at")
+            .and_then(|v| v.as_n().ok())
+            .and_then(|n| n.parse::<i64>().ok());
+        if expires_at.is_some_and(|expires_at| expires_at <= chrono::Utc::now().timestamp()) {
+            return Ok(None);
+        }
+        Ok(item.get("state").and_then(|v| v.as_s().ok()).cloned())
+    }
+
+    pub async fn set_dialogue_state(&self, key: &str, state: &str, ttl_seconds: i64) -> Result<(), StorageError> {
+        let now = chrono::Utc::now();
+
+        let mut item = HashMap::new();
+        item.insert("chat_id".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(format!("dialogue#{key}")));
+        item.insert("state".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(state.to_string()));
+        item.insert("updated_at".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(now.to_rfc3339()));
+        item.insert(
+            "expires_at".to_string(),
+            aws_sdk_dynamodb::types::AttributeValue::N((now.timestamp() + ttl_seconds).to_string()),
+        );
+
+        self.client
+            .put_item()
+            .table_name(self.conversation_table()?)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+        Ok(())
+    }
+
+    pub async fn delete_dialogue_state(&self, key: &str) -> Result<(), StorageError> {
+        self.client
+            .delete_item()
+            .table_name(self.conversation_table()?)
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S(format!("dialogue#{key}")))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+        Ok(())
+    }
+
+    // Delete the state and return it in one request, only while it is unexpired and contains the marker.
+    // Concurrent callers race on the delete, so exactly one of them gets the state
+    pub async fn take_dialogue_state(&self, key: &str, marker: &str) -> Result<Option<String>, StorageError> {
+        let result = self
+            .client
+            .delete_item()
+            .table_name(self.conversation_table()?)
+            .key("chat_id", aws_sdk_dynamodb::types::AttributeValue::S(format!("dialogue#{key}")))
+            .condition_expression("contains(#state, :marker) AND expires_at > :now")
+            .expression_attribute_names("#state", "state")
+            .expression_attribute_values(":marker", aws_sdk_dynamodb::types::AttributeValue::S(marker.to_string()))
+            .expression_attribute_values(
+                ":now",
+                aws_sdk_dynamodb::types::AttributeValue::N(chrono::Utc::now().timestamp().to_string()),
+            )
+            .return_values(aws_sdk_dynamodb::types::ReturnValue::AllOld)
+            .send()
+            .await;
+
+        match result {
+            Ok(output) => Ok(output
+                .attributes
+                .as_ref()
+                .and_then(|item| item.get("state"))
+                .and_then(|v| v.as_s().ok())
+                .cloned()),
+            Err(e) => {
+                let error = DynamoDbError::from(e);
+                if matches!(error, DynamoDbError::ConditionalCheckFailedException(_)) {
+                    Ok(None)
+                } else {
+                    Err(StorageError::DynamoDb(error))
+                }
+            }
+        }
+    }
+
+    pub async fn create_ingest_binding(&self, binding: &IngestBinding) -> Result<(), StorageError> {
+        info!("💾 Creating ingest binding for chat_id: {}", binding.chat_id);
+
+        let mut item = HashMap::new();
+        item.insert("token".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(binding.token.clone()));
+        item.insert("chat_id".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(binding.chat_id.clone()));
+        item.insert("created_by".to_string(), aws_sdk_dynamodb::types::AttributeValue::N(binding.created_by.to_string()));
+        item.insert("created_at".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(binding.created_at.clone()));
+        if let Some(repository) = &binding.repository {
+            item.insert("repository".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(repository.clone()));
+        }
+        if let Some(secret) = &binding.secret {
+            item.insert("secret".to_string(), aws_sdk_dynamodb::types::AttributeValue::S(secret.clone()));
+        }
+        if let Some(thread_id) = binding.thread_id {
+            item.insert("thread_id".to_string(), aws_sdk_dynamodb::types::AttributeValue::N(thread_id.to_string()));
+        }
+
+        self.client
+            .put_item()
+            .table_name(self.ingest_table()?)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+
+        info!("✅ Ingest binding created for chat_id: {}", binding.chat_id);
+        Ok(())
+    }
+
+    pub async fn get_ingest_binding(&self, token: &str) -> Result<Option<IngestBinding>, StorageError> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(self.ingest_table()?)
+            .key("token", aws_sdk_dynamodb::types::AttributeValue::S(token.to_string()))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+
+        let binding = result.item.and_then(|item| {
+            Some(IngestBinding {
+                token: token.to_string(),
+                chat_id: item.get("chat_id")?.as_s().ok()?.clone(),
+                created_by: item
+                    .get("created_by")
+                    .and_then(|v| v.as_n().ok())
+                    .and_then(|n| n.parse().ok())
+                    .unwrap_or(0),
+                created_at: item
+                    .get("created_at")
+                    .and_then(|v| v.as_s().ok())
+                    .cloned()
+                    .unwrap_or_default(),
+                repository: item.get("repository").and_then(|v| v.as_s().ok()).cloned(),
+                secret: item.get("secret").and_then(|v| v.as_s().ok()).cloned(),
+                thread_id: item
+                    .get("thread_id")
+                    .and_then(|v| v.as_n().ok())
+                    .and_then(|n| n.parse().ok()),
+            })
+        });
+        Ok(binding)
+    }
+
+    // Only deletes the binding when it belongs to the given chat, returns whether one was removed
+    pub async fn delete_ingest_binding(&self, token: &str, chat_id: &str) -> Result<bool, StorageError> {
+        info!("🗑️ Revoking ingest binding for chat_id: {chat_id}");
+
+        let result = self
+            .client
+            .delete_item()
+            .table_name(self.ingest_table()?)
+            .key("token", aws_sdk_dynamodb::types::AttributeValue::S(token.to_string()))
+            .condition_expression("chat_id = :chat_id")
+            .expression_attribute_values(":chat_id", aws_sdk_dynamodb::types::AttributeValue::S(chat_id.to_string()))
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                let error = DynamoDbError::from(e);
+                if matches!(error, DynamoDbError::ConditionalCheckFailedException(_)) {
+                    Ok(false)
+                } else {
+                    Err(StorageError::DynamoDb(error))
+                }
+            }
+        }
+    }
+
+    // Atomically increment a usage counter unless it already reached `limit`, returns the new count or None
+    pub async fn try_increment_counter(
+        &self,
+        key: &str,
+        limit: u64,
+        expires_at: i64,
+    ) -> Result<Option<u64>, StorageError> {
+        let result = self
+            .client
+            .update_item()
+            .table_name(self.usage_table()?)
+            .key("usage_key", aws_sdk_dynamodb::types::AttributeValue::S(key.to_string()))
+            .update_expression("SET expires_at = if_not_exists(expires_at, :expires_at) ADD #count :one")
+            .condition_expression("attribute_not_exists(#count) OR #count < :limit")
+            .expression_attribute_names("#count", "count")
+            .expression_attribute_values(":one", aws_sdk_dynamodb::types::AttributeValue::N("1".to_string()))
+            .expression_attribute_values(":limit", aws_sdk_dynamodb::types::AttributeValue::N(limit.to_string()))
+            .expression_attribute_values(":expires_at", aws_sdk_dynamodb::types::AttributeValue::N(expires_at.to_string()))
+            .return_values(aws_sdk_dynamodb::types::ReturnValue::UpdatedNew)
+            .send()
+            .await;
+
+        match result {
+            Ok(output) => Ok(output
+                .attributes
+                .as_ref()
+                .and_then(|item| item.get("count"))
+                .and_then(|v| v.as_n().ok())
+                .and_then(|n| n.parse().ok())),
+            Err(e) => {
+                let error = DynamoDbError::from(e);
+                if matches!(error, DynamoDbError::ConditionalCheckFailedException(_)) {
+                    Ok(None)
+                } else {
+                    Err(StorageError::DynamoDb(error))
+                }
+            }
+        }
+    }
+
+    pub async fn add_to_counter(&self, key: &str, amount: u64, expires_at: i64) -> Result<(), StorageError> {
+        self.client
+            .update_item()
+            .table_name(self.usage_table()?)
+            .key("usage_key", aws_sdk_dynamodb::types::AttributeValue::S(key.to_string()))
+            .update_expression("SET expires_at = if_not_exists(expires_at, :expires_at) ADD #count :amount")
+            .expression_attribute_names("#count", "count")
+            .expression_attribute_values(":amount", aws_sdk_dynamodb::types::AttributeValue::N(amount.to_string()))
+            .expression_attribute_values(":expires_at", aws_sdk_dynamodb::types::AttributeValue::N(expires_at.to_string()))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+        Ok(())
+    }
+
+    pub async fn get_counter(&self, key: &str) -> Result<u64, StorageError> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(self.usage_table()?)
+            .key("usage_key", aws_sdk_dynamodb::types::AttributeValue::S(key.to_string()))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+
+        Ok(result
+            .item
+            .as_ref()
+            .and_then(|item| item.get("count"))
+            .and_then(|v| v.as_n().ok())
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(0))
+    }
+
+    pub async fn decrement_counter(&self, key: &str) -> Result<(), StorageError> {
+        self.client
+            .update_item()
+            .table_name(self.usage_table()?)
+            .key("usage_key", aws_sdk_dynamodb::types::AttributeValue::S(key.to_string()))
+            .update_expression("ADD #count :minus_one")
+            .condition_expression("#count > :zero")
+            .expression_attribute_names("#count", "count")
+            .expression_attribute_values(":minus_one", aws_sdk_dynamodb::types::AttributeValue::N("-1".to_string()))
+            .expression_attribute_values(":zero", aws_sdk_dynamodb::types::AttributeValue::N("0".to_string()))
+            .send()
+            .await
+            .map_err(|e| StorageError::DynamoDb(DynamoDbError::from(e)))?;
+        Ok(())
+    }
+}
+
+// Factory function to create storage client
+pub async fn create_storage() -> Result<DynamoDbStorage, StorageError> {
+    // Every storage access starts here, so injected failures cover all of them
+    if crate::chaos::inject_storage_fault() {
+        return Err(StorageError::Configuration("Injected storage failure (CHAOS_STORAGE_ERROR_RATE)".to_string()));
+    }
     DynamoDbStorage::new().await
 }
 
 // Helper function to get default model
 pub fn get_default_model() -> String {
-    std::env::var("AI_MODEL").unwrap_or_else(|_| "gpt-4o".to_string())
+    crate::setup::get_config("AI_MODEL").unwrap_or_else(|| "gpt-4o".to_string())
 }
\ No newline at end of file
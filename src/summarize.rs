@@ -0,0 +1,341 @@
+use log::{info, warn};
+use scraper::{Html, Selector};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::storage::{create_storage, RecentMessage};
+
+// Group messages kept per chat when no override is configured
+const DEFAULT_RECENT_MESSAGE_CACHE_SIZE: usize = 100;
+// Messages summarized by a bare /summarize in a group
+pub const DEFAULT_SUMMARY_MESSAGES: usize = 50;
+
+pub const SUMMARIZE_PROMPT: &str = "You summarize text for a Telegram chat. Write a concise summary of the \
+    content you are given: a one-sentence overview followed by up to five bullet points with the key facts, \
+    decisions and open questions. Reply in the language of the content and do not add information that is not in it.";
+
+pub const SUMMARIZE_CHAT_PROMPT: &str = "You summarize group chat conversations. The messages are given as \
+    'author: text' lines, oldest first. Summarize the main topics, who said what where it matters, decisions made \
+    and open questions, as a short overview followed by bullet points. Reply in the language of the conversation.";
+
+pub const SUMMARIZE_PAGE_PROMPT: &str = "You summarize web pages for a Telegram chat. You are given the page title \
+    and its extracted text, which may contain leftover navigation or cookie notices; ignore those. Reply with a \
+    one-sentence overview followed by up to five bullet points with the key points. Reply in the language of the \
+    page and do not add information that is not in it.";
+
+const PAGE_FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+const MAX_PAGE_BYTES: usize = 2 * 1024 * 1024;
+// Text passed to the model, enough for a long article
+const MAX_PAGE_CHARS: usize = 12_000;
+// Less readable text than this usually means a paywall, consent wall or a JavaScript-only page
+const MIN_READABLE_CHARS: usize = 300;
+
+// Helper function to get the configured cache size, 0 disables caching
+pub fn get_recent_message_cache_size() -> usize {
+    std::env::var("RECENT_MESSAGE_CACHE_SIZE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_RECENT_MESSAGE_CACHE_SIZE)
+}
+
+// Remember a group message for later summaries, keeping only the most recent ones
+pub async fn cache_group_message(chat_id: &str, author: &str, text: &str) {
+    let max_messages = get_recent_message_cache_size();
+    if max_messages == 0 {
+        return;
+    }
+
+    let storage = match create_storage().await {
+        Ok(storage) => storage,
+        Err(e) => {
+            warn!("⚠️ Failed to create storage client, not caching message: {e}");
+            return;
+        }
+    };
+    let mut messages = match storage.get_recent_messages(chat_id).await {
+        Ok(messages) => messages,
+        Err(e) => {
+            warn!("⚠️ Failed to load recent messages for chat {chat_id}: {e}");
+            return;
+        }
+    };
+
+    messages.push(RecentMessage::new(author.to_string(), text.to_string()));
+    if messages.len() > max_messages {
+        messages.drain(..messages.len() - max_messages);
+    }
+    if let Err(e) = storage.set_recent_messages(chat_id, &messages).await {
+        warn!("⚠️ Failed to cache message for chat {chat_id}: {e}");
+    } else {
+        info!("🗂️ Cached group message for chat {chat_id} ({} stored)", messages.len());
+    }
+}
+
+// Format the last `count` cached messages as "author: text" lines, oldest first
+pub async fn load_recent_transcript(chat_id: &str, count: usize) -> Result<Option<String>, String> {
+    let storage = create_storage().await.map_err(|e| e.to_string())?;
+    let messages = storage.get_recent_messages(chat_id).await.map_err(|e| e.to_string())?;
+    if messages.is_empty() {
+        return Ok(None);
+    }
+
+    let start = messages.len().saturating_sub(count);
+    let transcript = messages[start..]
+        .iter()
+        .map(|message| format!("{}: {}", message.author, message.text))
+        .collect::<Vec<_>>()
+        .join("\n");
+    info!("🗂️ Loaded {} cached messages for chat {chat_id}", messages.len() - start);
+    Ok(Some(transcript))
+}
+
+// A message that is nothing but an http(s) link
+pub fn parse_page_url(text: &str) -> Option<url::Url> {
+    let text = text.trim();
+    if text.contains(char::is_whitespace) {
+        return None;
+    }
+    url::Url::parse(text)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+// Keep the bot from being used to reach internal services such as cloud metadata endpoints. Domains are
+// checked again once resolved, by PublicResolver
+fn is_public_host(url: &url::Url) -> bool {
+    match url.host() {
+        Some(url::Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_lowercase();
+            domain != "localhost" && !domain.ends_with(".localhost") && !domain.ends_with(".internal")
+        }
+        Some(url::Host::Ipv4(ip)) => is_public_ip(IpAddr::V4(ip)),
+        Some(url::Host::Ipv6(ip)) => is_public_ip(IpAddr::V6(ip)),
+        None => false,
+    }
+}
+
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => {
+            // IPv4-mapped (::ffff:a.b.c.d) and IPv4-compatible (::a.b.c.d) addresses reach the IPv4 host
+            if let Some(v4) = ip.to_ipv4() {
+                return is_public_ipv4(v4);
+            }
+            // Unique local (fc00::/7) and link-local (fe80::/10) addresses
+            let unique_local = (ip.segments()[0] & 0xfe00) == 0xfc00;
+            let link_local = (ip.segments()[0] & 0xffc0) == 0xfe80;
+            !(ip.is_loopback() || ip.is_unspecified() || ip.is_multicast() || unique_local || link_local)
+        }
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    // 0.0.0.0/8 "this network" and 100.64.0.0/10 carrier-grade NAT
+    let this_network = a == 0;
+    let shared = a == 100 && (b & 0xc0) == 64;
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || this_network
+        || shared)
+}
+
+// Returned by PublicResolver when a name points at a private address
+#[derive(Debug)]
+struct PrivateAddress;
+
+impl std::fmt::Display for PrivateAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "host resolves to a private address")
+    }
+}
+
+impl std::error::Error for PrivateAddress {}
+
+// Resolves page hosts, including redirect targets, and refuses names where any address is private, so a
+// public-looking domain can't point the fetch at an internal service
+struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0)).await?.collect();
+            if addrs.iter().any(|addr| !is_public_ip(addr.ip())) {
+                warn!("❌ Refused to fetch {}, it resolves to a private address", name.as_str());
+                return Err(PrivateAddress.into());
+            }
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+// Whether a request failed because PublicResolver refused the host
+fn is_private_address_error(e: &reqwest::Error) -> bool {
+    let mut source = std::error::Error::source(e);
+    while let Some(error) = source {
+        if error.is::<PrivateAddress>() {
+            return true;
+        }
+        source = error.source();
+    }
+    false
+}
+
+// Paragraph-level text of the page's main content, preferring article and main elements over the whole body
+fn extract_readable_text(html: &str) -> (Option<String>, String) {
+    let document = Html::parse_document(html);
+    let title = Selector::parse("title").ok().and_then(|selector| {
+        document
+            .select(&selector)
+            .next()
+            .map(|title| title.text().collect::<String>().trim().to_string())
+            .filter(|title| !title.is_empty())
+    });
+
+    let Ok(blocks) = Selector::parse("h1, h2, h3, p, li, blockquote, pre") else {
+        return (title, String::new());
+    };
+    let mut text = String::new();
+    for container in ["article", "main", "[role=main]", "body"] {
+        let Ok(selector) = Selector::parse(container) else {
+            continue;
+        };
+        let Some(root) = document.select(&selector).next() else {
+            continue;
+        };
+        text = root
+            .select(&blocks)
+            .map(|block| block.text().collect::<Vec<_>>().join(" ").split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|block| !block.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        if text.chars().count() >= MIN_READABLE_CHARS {
+            break;
+        }
+    }
+    (title, text)
+}
+
+// Download a page and return its title and readable text, with a user-facing error when that is not possible
+pub async fn fetch_page_text(url: &url::Url) -> Result<(Option<String>, String), String> {
+    if !is_public_host(url) {
+        return Err("❌ That address is not a public web page.".to_string());
+    }
+    // Redirects are followed only to public hosts as well, and every host is resolved by PublicResolver
+    let client = reqwest::Client::builder()
+        .timeout(PAGE_FETCH_TIMEOUT)
+        .dns_resolver(Arc::new(PublicResolver))
+        .redirect(reqwest::redirect::Policy::custom(|attempt| {
+            if attempt.previous().len() >= 5 || !is_public_host(attempt.url()) {
+                attempt.stop()
+            } else {
+                attempt.follow()
+            }
+        }))
+        .user_agent("Mozilla/5.0 (compatible; TelegramBot/1.0)")
+        .build()
+        .map_err(|e| format!("❌ Failed to fetch the page: {e}"))?;
+
+    let mut response = client.get(url.clone()).send().await.map_err(|e| {
+        warn!("❌ Failed to fetch {url}: {e}");
+        if is_private_address_error(&e) {
+            "❌ That address is not a public web page.".to_string()
+        } else if e.is_timeout() {
+            "⌛ The page took too long to respond.".to_string()
+        } else {
+            format!("❌ Failed to fetch the page: {e}")
+        }
+    })?;
+    let status = response.status();
+    if matches!(status.as_u16(), 401..=403) {
+        return Err(format!("🔒 The page needs a login or subscription (HTTP {}).", status.as_u16()));
+    }
+    if !status.is_success() {
+        return Err(format!("❌ The page returned HTTP {}.", status.as_u16()));
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("text/html")
+        .to_lowercase();
+    if !content_type.starts_with("text/") && !content_type.contains("html") {
+        return Err(format!("❌ Only web pages can be summarized, this link is {content_type}."));
+    }
+
+    let mut body = Vec::new();
+    loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) => {
+                body.extend_from_slice(&chunk);
+                // Anything past this is almost never article text
+                if body.len() >= MAX_PAGE_BYTES {
+                    break;
+                }
+            }
+            Ok(None) => break,
+            Err(e) if e.is_timeout() => return Err("⌛ The page took too long to load.".to_string()),
+            Err(e) => return Err(format!("❌ Failed to read the page: {e}")),
+        }
+    }
+    let body = String::from_utf8_lossy(&body);
+
+    let (title, text) = if content_type.contains("html") {
+        extract_readable_text(&body)
+    } else {
+        (None, body.to_string())
+    };
+    if text.chars().count() < MIN_READABLE_CHARS {
+        return Err("🔒 No readable text found on the page, it may be paywalled or need JavaScript.".to_string());
+    }
+    info!("🌐 Extracted {} chars of text from {url}", text.len());
+    Ok((title, text.chars().take(MAX_PAGE_CHARS).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn public(ip: &str) -> bool {
+        is_public_ip(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn rejects_private_ipv4_ranges() {
+        for ip in ["127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "100.64.0.1", "0.1.2.3"] {
+            assert!(!public(ip), "{ip} should not be public");
+        }
+        assert!(public("93.184.216.34"));
+        assert!(public("100.128.0.1"));
+    }
+
+    #[test]
+    fn classifies_ipv4_embedded_in_ipv6_as_ipv4() {
+        assert!(!public("::ffff:169.254.169.254"));
+        assert!(!public("::ffff:127.0.0.1"));
+        assert!(!public("::10.0.0.1"));
+        assert!(public("::ffff:93.184.216.34"));
+    }
+
+    #[test]
+    fn rejects_private_ipv6_ranges() {
+        for ip in ["::1", "::", "fd00::1", "fe80::1", "ff02::1"] {
+            assert!(!public(ip), "{ip} should not be public");
+        }
+        assert!(public("2606:2800:220:1::"));
+    }
+
+    #[test]
+    fn rejects_local_host_names() {
+        for url in ["http://localhost/", "http://api.localhost/", "http://metadata.google.internal/", "http://[::ffff:a9fe:a9fe]/"] {
+            assert!(!is_public_host(&url::Url::parse(url).unwrap()), "{url} should not be public");
+        }
+        assert!(is_public_host(&url::Url::parse("https://example.com/").unwrap()));
+    }
+}
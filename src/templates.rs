@@ -0,0 +1,138 @@
+use log::{info, warn};
+use minijinja::{AutoEscape, Environment, Value};
+use std::sync::OnceLock;
+
+// Default wording for outbound messages, any of these can be overridden from TEMPLATES_DIR
+const BUILT_IN_TEMPLATES: &[(&str, &str)] = &[
+    ("welcome.private", "Hello! Send me a command or message.\n\n{{ commands }}"),
+    (
+        "welcome.group",
+        "Hello! You mentioned me. Send a command or message after @{{ bot_username }}.\n\n{{ commands }}",
+    ),
+    ("unknown_command", "Unknown command: {{ command }}\n\nAvailable commands:\n{{ commands }}"),
+    ("error.ai", "AI Error: {{ error }}"),
+    ("error.config", "Configuration Error: {{ error }}"),
+    ("ai.disabled", "🚫 AI features are turned off in this chat. An administrator can enable them with /groupconfig ai on."),
+    (
+        "error.rate_limited",
+        "⏳ You have used all {{ limit }} AI requests allowed per hour. Please try again in {{ minutes }} \
+         minute{% if minutes != 1 %}s{% endif %}.",
+    ),
+    (
+        "error.budget",
+        "⛔ {% if scope == 'chat' %}This chat has{% else %}You have{% endif %} used {{ used }} of the {{ limit }} \
+         AI tokens allowed this month. The budget resets on {{ resets_on }}.",
+    ),
+    (
+        "moderation.refused",
+        "🛡️ This message was not sent to the AI because it was flagged for: {{ categories }}.",
+    ),
+    (
+        "moderation.withheld",
+        "🛡️ The AI's reply was withheld because it was flagged for: {{ categories }}.",
+    ),
+    (
+        "moderation.unavailable",
+        "🛡️ Moderation is on in this chat but unavailable right now, so no AI reply can be given. Please try again later.",
+    ),
+    ("note.fallback", "ℹ️ Answered by {{ model }} because {{ preferred }} was unavailable."),
+    ("alert.generic", "📨 {{ title or 'Incoming alert' }}{% if body %}\n\n{{ body }}{% endif %}"),
+    ("footer.disclaimer", "ℹ️ {{ text }}"),
+    (
+        "alert.digest",
+        "🔔 {{ alerts | length }} alerts triggered:{% for alert in alerts %}\n\n{{ alert }}{% endfor %}",
+    ),
+];
+
+static TEMPLATES: OnceLock<Environment<'static>> = OnceLock::new();
+
+// Helper function to get the locale used when the sender's language is unknown
+pub fn get_default_locale() -> Option<String> {
+    std::env::var("BOT_LOCALE").ok().filter(|locale| !locale.trim().is_empty())
+}
+
+// Load every "<name>[.<locale>].j2" file from TEMPLATES_DIR on top of the built-in templates
+fn load_templates() -> Environment<'static> {
+    let mut env = Environment::new();
+    env.set_auto_escape_callback(|_| AutoEscape::None);
+    for (name, source) in BUILT_IN_TEMPLATES {
+        if let Err(e) = env.add_template(name, source) {
+            warn!("⚠️ Invalid built-in template {name}: {e}");
+        }
+    }
+
+    let Ok(dir) = std::env::var("TEMPLATES_DIR") else {
+        return env;
+    };
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("⚠️ Failed to read TEMPLATES_DIR {dir}: {e}");
+            return env;
+        }
+    };
+    for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        let Some(name) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".j2"))
+            .map(str::to_string)
+        else {
+            continue;
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(source) => match env.add_template_owned(name.clone(), source) {
+                Ok(()) => info!("📝 Loaded template override {name}"),
+                Err(e) => warn!("⚠️ Invalid template {}: {e}", path.display()),
+            },
+            Err(e) => warn!("⚠️ Failed to read template {}: {e}", path.display()),
+        }
+    }
+    env
+}
+
+// Most specific first: "welcome.private.pt-br", "welcome.private.pt", the default locale, then "welcome.private"
+fn candidate_names(name: &str, locales: &[Option<&str>]) -> Vec<String> {
+    let mut names = Vec::new();
+    for locale in locales.iter().flatten() {
+        let locale = locale.trim().to_lowercase();
+        if locale.is_empty() {
+            continue;
+        }
+        names.push(format!("{name}.{locale}"));
+        if let Some((language, _)) = locale.split_once(['-', '_']) {
+            names.push(format!("{name}.{language}"));
+        }
+    }
+    names.push(name.to_string());
+    names.dedup();
+    names
+}
+
+// Render a message template for the given locale, falling back to the default wording on errors
+pub fn render(name: &str, locale: Option<&str>, context: Value) -> String {
+    let env = TEMPLATES.get_or_init(load_templates);
+    let default_locale = get_default_locale();
+
+    for candidate in candidate_names(name, &[locale, default_locale.as_deref()]) {
+        let Ok(template) = env.get_template(&candidate) else {
+            continue;
+        };
+        match template.render(&context) {
+            Ok(message) => return message,
+            Err(e) => warn!("⚠️ Failed to render template {candidate}: {e}"),
+        }
+    }
+
+    // A broken override must not silence the message, so retry with the built-in wording
+    let mut fallback = Environment::new();
+    fallback.set_auto_escape_callback(|_| AutoEscape::None);
+    BUILT_IN_TEMPLATES
+        .iter()
+        .find(|(built_in, _)| *built_in == name)
+        .and_then(|(_, source)| fallback.render_str(source, &context).ok())
+        .unwrap_or_else(|| {
+            warn!("⚠️ No usable template named {name}");
+            name.to_string()
+        })
+}
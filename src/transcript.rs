@@ -0,0 +1,246 @@
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key};
+use log::{error, info, warn};
+use serde::Serialize;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+use crate::storage::load_aws_config;
+
+const DEFAULT_RETENTION_DAYS: i64 = 90;
+const RETENTION_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Where transcripts go: one object per exchange in S3, or one append-only file per chat and day
+#[derive(Debug, Clone)]
+pub enum TranscriptSink {
+    S3 { bucket: String, prefix: String },
+    Local { dir: PathBuf },
+}
+
+impl std::fmt::Display for TranscriptSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TranscriptSink::S3 { bucket, prefix } => write!(f, "s3://{bucket}/{prefix}"),
+            TranscriptSink::Local { dir } => write!(f, "{}", dir.display()),
+        }
+    }
+}
+
+// One command and the bot's response to it
+#[derive(Debug, Serialize)]
+struct TranscriptEntry<'a> {
+    chat_id: &'a str,
+    user_id: u64,
+    timestamp: String,
+    command: &'a str,
+    response: &'a str,
+}
+
+// Helper function to get the transcript sink, None keeps logging off (the default)
+pub fn get_transcript_sink() -> Option<TranscriptSink> {
+    let value = std::env::var("TRANSCRIPT_SINK").ok()?;
+    let url = match url::Url::parse(value.trim()) {
+        Ok(url) => url,
+        Err(e) => {
+            error!("❌ Invalid TRANSCRIPT_SINK '{value}', transcript logging is off: {e}");
+            return None;
+        }
+    };
+    match url.scheme() {
+        "s3" => Some(TranscriptSink::S3 {
+            bucket: url.host_str()?.to_string(),
+            prefix: url.path().trim_matches('/').to_string(),
+        }),
+        "file" => Some(TranscriptSink::Local { dir: PathBuf::from(url.path()) }),
+        other => {
+            error!("❌ Unsupported TRANSCRIPT_SINK scheme '{other}', use s3:// or file://");
+            None
+        }
+    }
+}
+
+// Helper function to get how long transcripts are kept
+pub fn get_transcript_retention_days() -> i64 {
+    std::env::var("TRANSCRIPT_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|days| *days > 0)
+        .unwrap_or(DEFAULT_RETENTION_DAYS)
+}
+
+// Transcripts are only written encrypted, a missing or malformed key leaves logging off
+fn cipher() -> Option<ChaCha20Poly1305> {
+    let value = std::env::var("TRANSCRIPT_ENCRYPTION_KEY").ok()?;
+    match hex::decode(value.trim()) {
+        Ok(key) if key.len() == 32 => Some(ChaCha20Poly1305::new(Key::from_slice(&key))),
+        _ => {
+            error!("❌ TRANSCRIPT_ENCRYPTION_KEY must be 64 hex characters, transcript logging is off");
+            None
+        }
+    }
+}
+
+// Whether exchanges are being recorded, used by /privacy
+pub fn transcript_logging_enabled() -> bool {
+    get_transcript_sink().is_some() && cipher().is_some()
+}
+
+// Encrypted record as hex of nonce followed by ciphertext, one per line in local files
+fn seal(cipher: &ChaCha20Poly1305, entry: &TranscriptEntry<'_>) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let plaintext = serde_json::to_vec(entry)?;
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_slice())
+        .map_err(|_| "Failed to encrypt transcript entry")?;
+    Ok(hex::encode([nonce.as_slice(), ciphertext.as_slice()].concat()))
+}
+
+async fn s3_client() -> aws_sdk_s3::Client {
+    aws_sdk_s3::Client::new(&load_aws_config().await)
+}
+
+async fn write_entry(sink: &TranscriptSink, chat_id: &str, sealed: String) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let now = chrono::Utc::now();
+    match sink {
+        // Objects are never overwritten, so each exchange gets its own key
+        TranscriptSink::S3 { bucket, prefix } => {
+            let key = format!(
+                "{prefix}/{chat_id}/{}/{}-{}.bin",
+                now.format("%Y-%m-%d"),
+                now.format("%H%M%S%.3f"),
+                uuid::Uuid::new_v4().simple()
+            );
+            s3_client()
+                .await
+                .put_object()
+                .bucket(bucket)
+                .key(key.trim_start_matches('/'))
+                .body(sealed.into_bytes().into())
+                .send()
+                .await?;
+        }
+        TranscriptSink::Local { dir } => {
+            let chat_dir = dir.join(chat_id);
+            tokio::fs::create_dir_all(&chat_dir).await?;
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(chat_dir.join(format!("{}.log", now.format("%Y-%m-%d"))))
+                .await?;
+            file.write_all(format!("{sealed}\n").as_bytes()).await?;
+        }
+    }
+    Ok(())
+}
+
+// Record a command and its response in the background, never holding up or failing the reply
+pub fn record_exchange(chat_id: &str, user_id: u64, command: &str, response: &str) {
+    let (Some(sink), Some(cipher)) = (get_transcript_sink(), cipher()) else {
+        return;
+    };
+    let entry = TranscriptEntry {
+        chat_id,
+        user_id,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        command,
+        response,
+    };
+    let sealed = match seal(&cipher, &entry) {
+        Ok(sealed) => sealed,
+        Err(e) => {
+            warn!("⚠️ Failed to encrypt transcript entry for chat {chat_id}: {e}");
+            return;
+        }
+    };
+    let chat_id = chat_id.to_string();
+    tokio::spawn(async move {
+        if let Err(e) = write_entry(&sink, &chat_id, sealed).await {
+            warn!("⚠️ Failed to write transcript entry for chat {chat_id} to {sink}: {e}");
+        }
+    });
+}
+
+// Delete local day files older than the retention period
+async fn expire_local(dir: &Path, cutoff: chrono::NaiveDate) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let mut removed = 0;
+    let mut chats = tokio::fs::read_dir(dir).await?;
+    while let Some(chat) = chats.next_entry().await? {
+        if !chat.file_type().await?.is_dir() {
+            continue;
+        }
+        let mut days = tokio::fs::read_dir(chat.path()).await?;
+        while let Some(day) = days.next_entry().await? {
+            let name = day.file_name();
+            let Some(date) = name
+                .to_str()
+                .and_then(|name| name.strip_suffix(".log"))
+                .and_then(|date| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+            else {
+                continue;
+            };
+            if date < cutoff {
+                tokio::fs::remove_file(day.path()).await?;
+                removed += 1;
+            }
+        }
+    }
+    Ok(removed)
+}
+
+// Delete S3 objects older than the retention period
+async fn expire_s3(bucket: &str, prefix: &str, cutoff: chrono::NaiveDate) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let client = s3_client().await;
+    let cutoff = cutoff.and_hms_opt(0, 0, 0).map(|time| time.and_utc().timestamp()).unwrap_or(0);
+    let mut removed = 0;
+    let mut continuation = None;
+    loop {
+        let page = client
+            .list_objects_v2()
+            .bucket(bucket)
+            .prefix(prefix)
+            .set_continuation_token(continuation)
+            .send()
+            .await?;
+        for object in page.contents() {
+            let expired = object.last_modified().is_some_and(|modified| modified.secs() < cutoff);
+            if let (true, Some(key)) = (expired, object.key()) {
+                client.delete_object().bucket(bucket).key(key).send().await?;
+                removed += 1;
+            }
+        }
+        continuation = page.next_continuation_token().map(str::to_string);
+        if continuation.is_none() {
+            break;
+        }
+    }
+    Ok(removed)
+}
+
+// Daily retention sweep, for long-running instances; Lambda deployments use an S3 lifecycle rule instead
+pub fn spawn_transcript_retention() {
+    let Some(sink) = get_transcript_sink() else {
+        return;
+    };
+    if cipher().is_none() {
+        return;
+    }
+    let retention_days = get_transcript_retention_days();
+    info!("🗄️ Transcript logging to {sink}, kept for {retention_days} days");
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RETENTION_INTERVAL);
+        loop {
+            interval.tick().await;
+            let cutoff = (chrono::Utc::now() - chrono::Duration::days(retention_days)).date_naive();
+            let result = match &sink {
+                TranscriptSink::S3 { bucket, prefix } => expire_s3(bucket, prefix, cutoff).await,
+                TranscriptSink::Local { dir } => expire_local(dir, cutoff).await,
+            };
+            match result {
+                Ok(removed) => info!("🗄️ Removed {removed} expired transcript files"),
+                Err(e) => warn!("⚠️ Transcript retention sweep failed: {e}"),
+            }
+        }
+    });
+}
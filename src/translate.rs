@@ -0,0 +1,60 @@
+use log::warn;
+use std::error::Error;
+
+use crate::storage::create_storage;
+
+// Target language when neither the chat nor the command names one
+pub const DEFAULT_TRANSLATE_LANGUAGE: &str = "en";
+const MAX_LANGUAGE_LEN: usize = 32;
+
+pub const TRANSLATE_PROMPT: &str = "You are a translation engine. Detect the language of the text you are given \
+    and translate it into the requested target language. Preserve meaning, tone, formatting, links and emoji. \
+    Reply with the translation only, prefixed by the detected source language in square brackets, e.g. '[French] ...'. \
+    If the text is already in the target language, return it unchanged with that prefix.";
+
+// Parse an optional leading "to:<language>" override, returning it and the remaining text
+pub fn parse_translate_args(args: &str) -> Result<(Option<String>, &str), String> {
+    let args = args.trim();
+    let (first, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    match first.strip_prefix("to:") {
+        Some(language) => Ok((Some(validate_language(language)?), rest.trim())),
+        None => Ok((None, args)),
+    }
+}
+
+// Languages are passed to the model as given, e.g. "es", "pt-BR" or "Japanese"
+pub fn validate_language(language: &str) -> Result<String, String> {
+    let language = language.trim();
+    if language.is_empty()
+        || language.len() > MAX_LANGUAGE_LEN
+        || !language.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!("'{language}' is not a valid language, use a code like es or a name like Spanish"));
+    }
+    Ok(language.to_string())
+}
+
+// Get the chat's configured target language, falling back to the default
+pub async fn get_translate_language(chat_id: &str) -> String {
+    let language = match create_storage().await {
+        Ok(storage) => match storage.get_translate_language(chat_id).await {
+            Ok(language) => language,
+            Err(e) => {
+                warn!("⚠️ Failed to get translation language from storage: {e}");
+                None
+            }
+        },
+        Err(e) => {
+            warn!("⚠️ Failed to create storage client: {e}");
+            None
+        }
+    };
+    language.unwrap_or_else(|| DEFAULT_TRANSLATE_LANGUAGE.to_string())
+}
+
+// Set or clear the chat's target language in DynamoDB
+pub async fn set_translate_language(chat_id: &str, language: Option<&str>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    storage.set_translate_language(chat_id, language).await?;
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use log::warn;
+use std::time::Duration;
+use teloxide::prelude::*;
+use teloxide::types::ChatAction;
+use tokio::task::JoinHandle;
+
+// Telegram clears a chat action after about 5 seconds
+const REFRESH_INTERVAL: Duration = Duration::from_secs(4);
+
+// Keeps "typing…" (or another chat action) visible while a long request runs, until dropped
+pub struct ChatActionKeepAlive {
+    task: JoinHandle<()>,
+}
+
+impl ChatActionKeepAlive {
+    pub fn start(bot: &Bot, chat_id: ChatId, action: ChatAction) -> Self {
+        let bot = bot.clone();
+        let task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+            loop {
+                interval.tick().await;
+                // A missed refresh only hides the indicator, so give up quietly
+                if let Err(e) = bot.send_chat_action(chat_id, action).await {
+                    warn!("⚠️ Failed to send chat action to chat {chat_id}: {e}");
+                    return;
+                }
+            }
+        });
+        Self { task }
+    }
+}
+
+impl Drop for ChatActionKeepAlive {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
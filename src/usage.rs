@@ -0,0 +1,248 @@
+use chrono::{Datelike, Timelike};
+use log::{info, warn};
+use std::error::Error;
+
+use crate::storage::{create_storage, AiBudget};
+
+// Images a single user may generate per UTC day when IMAGE_DAILY_LIMIT is unset
+const DEFAULT_IMAGE_DAILY_LIMIT: u64 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaStatus {
+    Allowed { used: u64, limit: u64 },
+    Exceeded { limit: u64 },
+    Unlimited,
+}
+
+// Helper function to get the per-user daily image limit, 0 disables the quota
+pub fn get_image_daily_limit() -> u64 {
+    std::env::var("IMAGE_DAILY_LIMIT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_IMAGE_DAILY_LIMIT)
+}
+
+fn daily_image_key(user_id: u64) -> String {
+    format!("image#{user_id}#{}", chrono::Utc::now().format("%Y-%m-%d"))
+}
+
+// Counters only need to outlive the day they count
+fn end_of_day_expiry() -> i64 {
+    chrono::Utc::now().timestamp() + 2 * 24 * 60 * 60
+}
+
+// Reserve one image generation for the user, failing open when usage storage is unavailable
+pub async fn consume_daily_image_quota(user_id: u64) -> QuotaStatus {
+    let limit = get_image_daily_limit();
+    if limit == 0 {
+        return QuotaStatus::Unlimited;
+    }
+
+    let key = daily_image_key(user_id);
+    let result = match create_storage().await {
+        Ok(storage) => storage.try_increment_counter(&key, limit, end_of_day_expiry()).await,
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(Some(used)) => {
+            info!("🎨 User {user_id} image quota: {used}/{limit}");
+            QuotaStatus::Allowed { used, limit }
+        }
+        Ok(None) => {
+            info!("⛔ User {user_id} exceeded daily image quota of {limit}");
+            QuotaStatus::Exceeded { limit }
+        }
+        Err(e) => {
+            warn!("⚠️ Failed to check image quota, allowing request: {e}");
+            QuotaStatus::Unlimited
+        }
+    }
+}
+
+// Give back a reserved generation after the image request failed
+pub async fn refund_daily_image_quota(user_id: u64) {
+    let key = daily_image_key(user_id);
+    let result = match create_storage().await {
+        Ok(storage) => storage.decrement_counter(&key).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        warn!("⚠️ Failed to refund image quota for user {user_id}: {e}");
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitStatus {
+    Allowed,
+    Exceeded { limit: u64, retry_in_minutes: i64 },
+}
+
+// Helper function to get the per-user hourly limit on AI requests, 0 (the default) disables it
+pub fn get_ai_hourly_limit() -> u64 {
+    std::env::var("AI_HOURLY_LIMIT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0)
+}
+
+fn hourly_ai_key(user_id: u64) -> String {
+    format!("ai_calls#{user_id}#{}", chrono::Utc::now().format("%Y-%m-%dT%H"))
+}
+
+// The hourly window resets at the top of the next UTC hour
+fn minutes_until_next_hour() -> i64 {
+    60 - chrono::Utc::now().minute() as i64
+}
+
+// Reserve one AI request for the user in the current hour, failing open when usage storage is unavailable
+pub async fn consume_ai_rate_limit(user_id: u64) -> RateLimitStatus {
+    let limit = get_ai_hourly_limit();
+    if limit == 0 {
+        return RateLimitStatus::Allowed;
+    }
+
+    // Hourly counters are kept a little past the hour they count
+    let expires_at = chrono::Utc::now().timestamp() + 2 * 60 * 60;
+    let result = match create_storage().await {
+        Ok(storage) => storage.try_increment_counter(&hourly_ai_key(user_id), limit, expires_at).await,
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(Some(used)) => {
+            info!("🚦 User {user_id} AI requests this hour: {used}/{limit}");
+            RateLimitStatus::Allowed
+        }
+        Ok(None) => {
+            info!("⛔ User {user_id} exceeded the hourly AI limit of {limit}");
+            RateLimitStatus::Exceeded { limit, retry_in_minutes: minutes_until_next_hour() }
+        }
+        Err(e) => {
+            warn!("⚠️ Failed to check AI rate limit, allowing request: {e}");
+            RateLimitStatus::Allowed
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetScope {
+    Chat,
+    User,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetStatus {
+    Within,
+    Exceeded { scope: BudgetScope, used: u64, limit: u64 },
+}
+
+// Token usage of a chat and of one user in it for the current month
+#[derive(Debug, Clone, Copy)]
+pub struct BudgetReport {
+    pub budget: AiBudget,
+    pub chat_used: u64,
+    pub user_used: u64,
+}
+
+fn current_month() -> String {
+    chrono::Utc::now().format("%Y-%m").to_string()
+}
+
+fn chat_tokens_key(chat_id: &str) -> String {
+    format!("ai_tokens#{chat_id}#{}", current_month())
+}
+
+fn user_tokens_key(chat_id: &str, user_id: u64) -> String {
+    format!("ai_tokens#{chat_id}#{user_id}#{}", current_month())
+}
+
+// Monthly counters are kept a little past the month they count
+fn end_of_month_expiry() -> i64 {
+    chrono::Utc::now().timestamp() + 62 * 24 * 60 * 60
+}
+
+// Budgets reset on the first day of the next UTC month
+pub fn next_budget_reset() -> chrono::NaiveDate {
+    let today = chrono::Utc::now().date_naive();
+    let (year, month) = if today.month() == 12 {
+        (today.year() + 1, 1)
+    } else {
+        (today.year(), today.month() + 1)
+    };
+    chrono::NaiveDate::from_ymd_opt(year, month, 1).unwrap_or(today)
+}
+
+pub async fn get_budget_report(chat_id: &str, user_id: u64) -> Result<BudgetReport, Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    let budget = storage.get_ai_budget(chat_id).await?;
+    let chat_used = storage.get_counter(&chat_tokens_key(chat_id)).await?;
+    let user_used = storage.get_counter(&user_tokens_key(chat_id, user_id)).await?;
+    Ok(BudgetReport {
+        budget,
+        chat_used,
+        user_used,
+    })
+}
+
+pub async fn set_ai_budget(chat_id: &str, budget: &AiBudget) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    storage.set_ai_budget(chat_id, budget).await?;
+    Ok(())
+}
+
+pub async fn get_ai_budget(chat_id: &str) -> Result<AiBudget, Box<dyn Error + Send + Sync>> {
+    let storage = create_storage().await?;
+    Ok(storage.get_ai_budget(chat_id).await?)
+}
+
+// Check the chat and per-user monthly token caps, failing open when storage is unavailable
+pub async fn check_ai_budget(chat_id: &str, user_id: u64) -> BudgetStatus {
+    // Most chats have no budget, so only read the counters when one is set
+    match get_ai_budget(chat_id).await {
+        Ok(budget) if budget.chat_tokens.is_none() && budget.user_tokens.is_none() => return BudgetStatus::Within,
+        Ok(_) => {}
+        Err(e) => {
+            warn!("⚠️ Failed to load AI budget, allowing request: {e}");
+            return BudgetStatus::Within;
+        }
+    }
+    let report = match get_budget_report(chat_id, user_id).await {
+        Ok(report) => report,
+        Err(e) => {
+            warn!("⚠️ Failed to check AI budget, allowing request: {e}");
+            return BudgetStatus::Within;
+        }
+    };
+
+    if let Some(limit) = report.budget.chat_tokens
+        && report.chat_used >= limit
+    {
+        info!("⛔ Chat {chat_id} exceeded its monthly AI budget of {limit} tokens");
+        return BudgetStatus::Exceeded { scope: BudgetScope::Chat, used: report.chat_used, limit };
+    }
+    if let Some(limit) = report.budget.user_tokens
+        && report.user_used >= limit
+    {
+        info!("⛔ User {user_id} exceeded the monthly AI budget of {limit} tokens in chat {chat_id}");
+        return BudgetStatus::Exceeded { scope: BudgetScope::User, used: report.user_used, limit };
+    }
+    BudgetStatus::Within
+}
+
+// Add the tokens of a completed AI request to the chat and user monthly totals
+pub async fn record_ai_tokens(chat_id: &str, user_id: u64, tokens: u64) {
+    let storage = match create_storage().await {
+        Ok(storage) => storage,
+        Err(e) => {
+            warn!("⚠️ Failed to record AI token usage: {e}");
+            return;
+        }
+    };
+    let expires_at = end_of_month_expiry();
+    for key in [chat_tokens_key(chat_id), user_tokens_key(chat_id, user_id)] {
+        if let Err(e) = storage.add_to_counter(&key, tokens, expires_at).await {
+            warn!("⚠️ Failed to record AI token usage for {key}: {e}");
+        }
+    }
+}
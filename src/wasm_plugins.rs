@@ -0,0 +1,354 @@
+use async_trait::async_trait;
+use log::{info, warn};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use teloxide::prelude::*;
+use wasmtime::{Caller, Config, Engine, Extern, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use crate::plugins::{CommandPlugin, PluginContext};
+use crate::storage::create_storage;
+
+// Defaults when a plugin has no <name>.json next to its module
+const DEFAULT_FUEL: u64 = 50_000_000;
+const DEFAULT_MEMORY_MB: usize = 16;
+const DEFAULT_MAX_MESSAGES: usize = 5;
+const DEFAULT_MAX_KV_BYTES: usize = 64 * 1024;
+// Largest string a plugin may pass to or read from the host in one call
+const MAX_HOST_STRING_BYTES: usize = 64 * 1024;
+
+// Per-plugin settings read from an optional <name>.json next to <name>.wasm
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+struct PluginManifest {
+    description: String,
+    fuel: u64,
+    memory_mb: usize,
+    max_messages: usize,
+    max_kv_bytes: usize,
+}
+
+impl Default for PluginManifest {
+    fn default() -> Self {
+        Self {
+            description: "sandboxed plugin command.".to_string(),
+            fuel: DEFAULT_FUEL,
+            memory_mb: DEFAULT_MEMORY_MB,
+            max_messages: DEFAULT_MAX_MESSAGES,
+            max_kv_bytes: DEFAULT_MAX_KV_BYTES,
+        }
+    }
+}
+
+// State the host API works on during one plugin call
+struct HostState {
+    limits: StoreLimits,
+    max_messages: usize,
+    max_kv_bytes: usize,
+    outbox: Vec<String>,
+    kv: Option<BTreeMap<String, String>>, // None when storage is unavailable
+    kv_dirty: bool,
+}
+
+// A command backed by an operator-installed .wasm module
+pub struct WasmPlugin {
+    name: String,
+    manifest: PluginManifest,
+    module: Module,
+}
+
+static ENGINE: OnceLock<Engine> = OnceLock::new();
+
+fn engine() -> &'static Engine {
+    ENGINE.get_or_init(|| {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        Engine::new(&config).expect("wasmtime engine configuration is valid")
+    })
+}
+
+// Helper function to get the directory plugin modules are loaded from
+pub fn get_wasm_plugins_dir() -> Option<String> {
+    std::env::var("WASM_PLUGINS_DIR").ok().filter(|dir| !dir.trim().is_empty())
+}
+
+fn load_manifest(wasm_path: &Path) -> PluginManifest {
+    let path = wasm_path.with_extension("json");
+    match std::fs::read_to_string(&path) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            warn!("⚠️ Invalid plugin manifest {}, using defaults: {e}", path.display());
+            PluginManifest::default()
+        }),
+        Err(_) => PluginManifest::default(),
+    }
+}
+
+// Compile every <name>.wasm in WASM_PLUGINS_DIR into a plugin command named after the file
+pub fn load_wasm_plugins() -> Vec<Arc<dyn CommandPlugin>> {
+    let Some(dir) = get_wasm_plugins_dir() else {
+        return Vec::new();
+    };
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("⚠️ Failed to read WASM_PLUGINS_DIR {dir}: {e}");
+            return Vec::new();
+        }
+    };
+
+    let mut plugins: Vec<Arc<dyn CommandPlugin>> = Vec::new();
+    for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        let Some(name) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".wasm"))
+            .map(str::to_lowercase)
+        else {
+            continue;
+        };
+        match Module::from_file(engine(), &path) {
+            Ok(module) => {
+                info!("🧩 Loaded WASM plugin {name} from {}", path.display());
+                plugins.push(Arc::new(WasmPlugin {
+                    manifest: load_manifest(&path),
+                    name,
+                    module,
+                }));
+            }
+            Err(e) => warn!("⚠️ Failed to compile WASM plugin {}: {e}", path.display()),
+        }
+    }
+    plugins
+}
+
+fn guest_memory(caller: &mut Caller<'_, HostState>) -> wasmtime::Result<wasmtime::Memory> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmtime::Error::msg("plugin does not export its memory"))
+}
+
+fn read_guest_string(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> wasmtime::Result<String> {
+    let len = len as u32 as usize;
+    if len > MAX_HOST_STRING_BYTES {
+        return Err(wasmtime::Error::msg(format!("host call argument exceeds {MAX_HOST_STRING_BYTES} bytes")));
+    }
+    let memory = guest_memory(caller)?;
+    let mut buffer = vec![0; len];
+    memory.read(&*caller, ptr as u32 as usize, &mut buffer)?;
+    String::from_utf8(buffer).map_err(wasmtime::Error::msg)
+}
+
+// Copy bytes into guest memory through its `alloc` export, returning (ptr << 32) | len
+fn write_guest_bytes(caller: &mut Caller<'_, HostState>, bytes: &[u8]) -> wasmtime::Result<i64> {
+    let alloc = caller
+        .get_export("alloc")
+        .and_then(Extern::into_func)
+        .ok_or_else(|| wasmtime::Error::msg("plugin does not export alloc"))?
+        .typed::<i32, i32>(&*caller)?;
+    let ptr = alloc.call(&mut *caller, bytes.len() as i32)?;
+    let memory = guest_memory(caller)?;
+    memory.write(&mut *caller, ptr as u32 as usize, bytes)?;
+    Ok((i64::from(ptr as u32) << 32) | bytes.len() as i64)
+}
+
+fn kv_size(entries: &BTreeMap<String, String>) -> usize {
+    entries.iter().map(|(key, value)| key.len() + value.len()).sum()
+}
+
+// The host API available to plugins as imports from the "bot" module
+fn build_linker(engine: &Engine) -> wasmtime::Result<Linker<HostState>> {
+    let mut linker = Linker::new(engine);
+
+    // Queue a reply to the chat, returns -1 once the plugin's message limit is reached
+    linker.func_wrap(
+        "bot",
+        "send_message",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> wasmtime::Result<i32> {
+            let text = read_guest_string(&mut caller, ptr, len)?;
+            let state = caller.data_mut();
+            if state.outbox.len() >= state.max_messages || text.trim().is_empty() {
+                return Ok(-1);
+            }
+            state.outbox.push(text);
+            Ok(0)
+        },
+    )?;
+
+    linker.func_wrap(
+        "bot",
+        "log",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> wasmtime::Result<()> {
+            let text = read_guest_string(&mut caller, ptr, len)?;
+            info!("🧩 Plugin log: {text}");
+            Ok(())
+        },
+    )?;
+
+    // Returns the value as (ptr << 32) | len in guest memory, or -1 when the key is missing
+    linker.func_wrap(
+        "bot",
+        "kv_get",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> wasmtime::Result<i64> {
+            let key = read_guest_string(&mut caller, ptr, len)?;
+            let value = caller.data().kv.as_ref().and_then(|kv| kv.get(&key).cloned());
+            match value {
+                Some(value) => write_guest_bytes(&mut caller, value.as_bytes()),
+                None => Ok(-1),
+            }
+        },
+    )?;
+
+    // Returns -1 when storage is unavailable or the namespace would exceed its size limit
+    linker.func_wrap(
+        "bot",
+        "kv_set",
+        |mut caller: Caller<'_, HostState>, key_ptr: i32, key_len: i32, value_ptr: i32, value_len: i32| -> wasmtime::Result<i32> {
+            let key = read_guest_string(&mut caller, key_ptr, key_len)?;
+            let value = read_guest_string(&mut caller, value_ptr, value_len)?;
+            let state = caller.data_mut();
+            let max_kv_bytes = state.max_kv_bytes;
+            let Some(kv) = state.kv.as_mut() else {
+                return Ok(-1);
+            };
+            let previous = kv.get(&key).map_or(0, |old| key.len() + old.len());
+            if kv_size(kv) - previous + key.len() + value.len() > max_kv_bytes {
+                return Ok(-1);
+            }
+            kv.insert(key, value);
+            state.kv_dirty = true;
+            Ok(0)
+        },
+    )?;
+
+    linker.func_wrap(
+        "bot",
+        "kv_delete",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> wasmtime::Result<i32> {
+            let key = read_guest_string(&mut caller, ptr, len)?;
+            let state = caller.data_mut();
+            let Some(kv) = state.kv.as_mut() else {
+                return Ok(-1);
+            };
+            if kv.remove(&key).is_some() {
+                state.kv_dirty = true;
+            }
+            Ok(0)
+        },
+    )?;
+
+    Ok(linker)
+}
+
+// Instantiate the module in a fresh store and call handle(ptr, len) with the update JSON
+fn run_guest(
+    module: &Module,
+    manifest: &PluginManifest,
+    kv: Option<BTreeMap<String, String>>,
+    input: &[u8],
+) -> wasmtime::Result<HostState> {
+    let state = HostState {
+        limits: StoreLimitsBuilder::new()
+            .memory_size(manifest.memory_mb * 1024 * 1024)
+            .instances(1)
+            .build(),
+        max_messages: manifest.max_messages,
+        max_kv_bytes: manifest.max_kv_bytes,
+        outbox: Vec::new(),
+        kv,
+        kv_dirty: false,
+    };
+    let mut store = Store::new(module.engine(), state);
+    store.limiter(|state| &mut state.limits);
+    store.set_fuel(manifest.fuel)?;
+
+    let instance = build_linker(module.engine())?.instantiate(&mut store, module)?;
+    let memory = instance
+        .get_memory(&mut store, "memory")
+        .ok_or_else(|| wasmtime::Error::msg("plugin does not export its memory"))?;
+    let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+    let handle = instance.get_typed_func::<(i32, i32), i32>(&mut store, "handle")?;
+
+    let ptr = alloc.call(&mut store, input.len() as i32)?;
+    memory.write(&mut store, ptr as u32 as usize, input)?;
+    let status = handle.call(&mut store, (ptr, input.len() as i32))?;
+    if status != 0 {
+        return Err(wasmtime::Error::msg(format!("handle returned error code {status}")));
+    }
+    Ok(store.into_data())
+}
+
+async fn load_plugin_kv(namespace: &str) -> Option<BTreeMap<String, String>> {
+    let result = match create_storage().await {
+        Ok(storage) => storage.get_plugin_kv(namespace).await,
+        Err(e) => Err(e),
+    };
+    result
+        .map_err(|e| warn!("⚠️ Plugin storage unavailable for {namespace}, running without it: {e}"))
+        .ok()
+}
+
+async fn save_plugin_kv(namespace: &str, entries: &BTreeMap<String, String>) {
+    let result = match create_storage().await {
+        Ok(storage) => storage.set_plugin_kv(namespace, entries).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        warn!("⚠️ Failed to save plugin storage for {namespace}: {e}");
+    }
+}
+
+#[async_trait]
+impl CommandPlugin for WasmPlugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.manifest.description
+    }
+
+    async fn handle(&self, context: PluginContext, args: String) -> ResponseResult<()> {
+        // Each plugin gets its own key/value namespace per chat
+        let namespace = format!("{}#{}", self.name, context.chat_id());
+        let kv = load_plugin_kv(&namespace).await;
+        let input = serde_json::json!({
+            "command": self.name,
+            "args": args,
+            "message": context.message,
+        })
+        .to_string();
+
+        let module = self.module.clone();
+        let manifest = self.manifest.clone();
+        let result = tokio::task::spawn_blocking(move || run_guest(&module, &manifest, kv, input.as_bytes()))
+            .await
+            .map_err(wasmtime::Error::msg)
+            .and_then(|result| result);
+
+        match result {
+            Ok(state) => {
+                info!(
+                    "🧩 WASM plugin /{} finished with {} messages for chat {}",
+                    self.name,
+                    state.outbox.len(),
+                    context.chat_id()
+                );
+                if state.kv_dirty
+                    && let Some(kv) = &state.kv
+                {
+                    save_plugin_kv(&namespace, kv).await;
+                }
+                for text in state.outbox {
+                    context.reply(text).await?;
+                }
+            }
+            Err(e) => {
+                warn!("❌ WASM plugin /{} failed in chat {}: {e}", self.name, context.chat_id());
+                context.reply(format!("❌ Plugin /{} failed: {e}", self.name)).await?;
+            }
+        }
+        Ok(())
+    }
+}
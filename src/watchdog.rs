@@ -0,0 +1,392 @@
+use log::{info, warn};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::time::Duration;
+use teloxide::prelude::*;
+
+use crate::setup::get_owner_id;
+use crate::storage::{create_storage, DynamoDbStorage, StorageError};
+
+// How often the last complete hour is compared against the baseline
+const CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+// Hours before the checked one that form the baseline
+const BASELINE_HOURS: i64 = 24;
+// Below this many updates per hour on average, a silent hour is normal
+const MIN_BASELINE_UPDATES: f64 = 5.0;
+// AI requests needed in an hour before its error rate is judged
+const MIN_AI_REQUESTS: u64 = 5;
+// Hourly counters only need to outlive the baseline
+const COUNTER_TTL_SECS: i64 = 3 * 24 * 60 * 60;
+// Events are counted in memory and written at most this often, or sooner once this many are waiting,
+// instead of one storage write per update
+const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+const FLUSH_THRESHOLD: u64 = 50;
+// How often Telegram's view of the webhook is compared with the configured one
+#[cfg(any(feature = "axum-server", feature = "lambda"))]
+const WEBHOOK_CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+// Pending updates above this, and still growing, mean Telegram cannot deliver to the webhook
+#[cfg(any(feature = "axum-server", feature = "lambda"))]
+const MAX_PENDING_UPDATES: u32 = 100;
+
+// Events counted per UTC hour in the usage table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchEvent {
+    Update,
+    AiRequest,
+    AiError,
+    DeliveryFailure,
+}
+
+impl WatchEvent {
+    const ALL: [WatchEvent; 4] = [
+        WatchEvent::Update,
+        WatchEvent::AiRequest,
+        WatchEvent::AiError,
+        WatchEvent::DeliveryFailure,
+    ];
+
+    fn key(&self, hour: chrono::DateTime<chrono::Utc>) -> String {
+        let name = match self {
+            WatchEvent::Update => "updates",
+            WatchEvent::AiRequest => "ai_requests",
+            WatchEvent::AiError => "ai_errors",
+            WatchEvent::DeliveryFailure => "delivery_failures",
+        };
+        format!("watch#{name}#{}", hour.format("%Y-%m-%dT%H"))
+    }
+
+    fn pending(&self) -> &'static AtomicU64 {
+        &PENDING_EVENTS[*self as usize]
+    }
+}
+
+// Counts not written to storage yet. Losing a few to a crash only makes an hour look a little quieter
+static PENDING_EVENTS: [AtomicU64; 4] = [const { AtomicU64::new(0) }; 4];
+static LAST_FLUSH: AtomicI64 = AtomicI64::new(0);
+
+// Helper function to get the chat anomaly alerts go to, the owner's private chat unless ADMIN_CHAT_ID is set
+pub fn get_admin_chat_id() -> Option<ChatId> {
+    std::env::var("ADMIN_CHAT_ID")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .map(ChatId)
+        .or_else(|| get_owner_id().map(|owner| ChatId(owner as i64)))
+}
+
+// Count an event for the current hour. Nothing is counted when there is no admin chat to alert
+pub async fn count_event(event: WatchEvent) {
+    if get_admin_chat_id().is_none() {
+        return;
+    }
+    let waiting = event.pending().fetch_add(1, Ordering::Relaxed) + 1;
+    if waiting >= FLUSH_THRESHOLD {
+        flush_events().await;
+    } else {
+        flush_events_if_due().await;
+    }
+}
+
+// Write the counted events when the last write is older than the flush interval
+pub async fn flush_events_if_due() {
+    let now = chrono::Utc::now().timestamp();
+    if now - LAST_FLUSH.load(Ordering::Relaxed) >= FLUSH_INTERVAL.as_secs() as i64 {
+        flush_events().await;
+    }
+}
+
+// Add the counted events to the current hour's counters, one write per kind of event
+async fn flush_events() {
+    let now = chrono::Utc::now();
+    LAST_FLUSH.store(now.timestamp(), Ordering::Relaxed);
+    let counts: Vec<(WatchEvent, u64)> = WatchEvent::ALL
+        .into_iter()
+        .map(|event| (event, event.pending().swap(0, Ordering::Relaxed)))
+        .filter(|(_, count)| *count > 0)
+        .collect();
+    if counts.is_empty() {
+        return;
+    }
+    let storage = match create_storage().await {
+        Ok(storage) => storage,
+        Err(e) => {
+            warn!("⚠️ Failed to count events for the watchdog: {e}");
+            // Keep them for the next flush
+            for (event, count) in counts {
+                event.pending().fetch_add(count, Ordering::Relaxed);
+            }
+            return;
+        }
+    };
+    for (event, count) in counts {
+        if let Err(e) = storage
+            .add_to_counter(&event.key(now), count, now.timestamp() + COUNTER_TTL_SECS)
+            .await
+        {
+            warn!("⚠️ Failed to count {count} {event:?} events for the watchdog: {e}");
+            event.pending().fetch_add(count, Ordering::Relaxed);
+        }
+    }
+}
+
+// Value of the counter for the hour and the average over the baseline hours before it
+async fn hour_and_baseline(
+    storage: &DynamoDbStorage,
+    event: WatchEvent,
+    hour: chrono::DateTime<chrono::Utc>,
+) -> Result<(u64, f64), StorageError> {
+    let current = storage.get_counter(&event.key(hour)).await?;
+    let mut total = 0;
+    for offset in 1..=BASELINE_HOURS {
+        total += storage
+            .get_counter(&event.key(hour - chrono::Duration::hours(offset)))
+            .await?;
+    }
+    Ok((current, total as f64 / BASELINE_HOURS as f64))
+}
+
+// Describe what looks wrong about the hour, one line per anomaly
+async fn find_anomalies(
+    storage: &DynamoDbStorage,
+    hour: chrono::DateTime<chrono::Utc>,
+) -> Result<Vec<(&'static str, String)>, StorageError> {
+    let mut anomalies = Vec::new();
+
+    let (updates, usual_updates) = hour_and_baseline(storage, WatchEvent::Update, hour).await?;
+    if updates == 0 && usual_updates >= MIN_BASELINE_UPDATES {
+        anomalies.push((
+            "silence",
+            format!(
+                "📉 No updates received (usually ~{usual_updates:.0}/hour). In webhook mode this often means the webhook stopped working."
+            ),
+        ));
+    } else if updates as f64 > usual_updates * 3.0 + 20.0 {
+        anomalies.push(("spike", format!("📈 {updates} updates received (usually ~{usual_updates:.0}/hour).")));
+    }
+
+    let (requests, usual_requests) = hour_and_baseline(storage, WatchEvent::AiRequest, hour).await?;
+    let (errors, usual_errors) = hour_and_baseline(storage, WatchEvent::AiError, hour).await?;
+    let error_rate = if requests == 0 { 0.0 } else { errors as f64 / requests as f64 };
+    let usual_error_rate = if usual_requests == 0.0 { 0.0 } else { usual_errors / usual_requests };
+    if requests >= MIN_AI_REQUESTS && error_rate >= 0.5 && error_rate > usual_error_rate * 2.0 {
+        anomalies.push((
+            "ai_errors",
+            format!(
+                "🤖 {errors} of {requests} AI requests failed ({:.0}%, usually {:.0}%). Check /aistatus.",
+                error_rate * 100.0,
+                usual_error_rate * 100.0
+            ),
+        ));
+    }
+
+    let (failures, usual_failures) = hour_and_baseline(storage, WatchEvent::DeliveryFailure, hour).await?;
+    if failures >= 3 && failures as f64 > usual_failures * 3.0 {
+        anomalies.push((
+            "delivery_failures",
+            format!("📭 {failures} alerts could not be delivered (usually ~{usual_failures:.1}/hour)."),
+        ));
+    }
+
+    Ok(anomalies)
+}
+
+// Check the last complete hour and alert the admin chat once per anomaly, even with several replicas running
+async fn check_last_hour(bot: &Bot, admin_chat: ChatId) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let now = chrono::Utc::now();
+    let hour = now - chrono::Duration::hours(1);
+    let storage = create_storage().await?;
+
+    for (kind, description) in find_anomalies(&storage, hour).await? {
+        let claim = format!("watch#alerted#{kind}#{}", hour.format("%Y-%m-%dT%H"));
+        if storage
+            .try_increment_counter(&claim, 1, now.timestamp() + COUNTER_TTL_SECS)
+            .await?
+            .is_none()
+        {
+            continue;
+        }
+        info!("🚨 Watchdog anomaly '{kind}' for {}", hour.format("%Y-%m-%d %H:00"));
+        let text = format!(
+            "🚨 Bot watchdog, {} UTC:\n\n{description}",
+            hour.format("%Y-%m-%d %H:00-%H:59")
+        );
+        bot.send_message(admin_chat, text).await?;
+    }
+    Ok(())
+}
+
+// One watchdog check from a scheduled Lambda invocation. Schedules may fire more often than the check
+// interval, so only the first invocation in each interval checks
+#[cfg(feature = "lambda")]
+pub async fn run_scheduled_watchdog(bot: &Bot) {
+    let Some(admin_chat) = get_admin_chat_id() else {
+        return;
+    };
+    let now = chrono::Utc::now().timestamp();
+    let window = now / CHECK_INTERVAL.as_secs() as i64;
+    let claimed = match create_storage().await {
+        Ok(storage) => storage
+            .try_increment_counter(&format!("watch#checked#{window}"), 1, now + 3600)
+            .await
+            .map(|claim| claim.is_some()),
+        Err(e) => Err(e),
+    };
+    match claimed {
+        Ok(true) => {
+            if let Err(e) = check_last_hour(bot, admin_chat).await {
+                warn!("⚠️ Watchdog check failed: {e}");
+            }
+        }
+        Ok(false) => {}
+        Err(e) => warn!("⚠️ Failed to claim the watchdog check: {e}"),
+    }
+}
+
+// Compare the bot's hourly activity against its recent baseline in the background.
+// Needs a long-running process, so Lambda runs it from scheduled invocations instead
+pub fn spawn_watchdog(bot: Bot) {
+    let Some(admin_chat) = get_admin_chat_id() else {
+        info!("🐕 Watchdog disabled, set ADMIN_CHAT_ID or BOT_OWNER_ID to enable it");
+        return;
+    };
+    info!("🐕 Watchdog started, anomalies are reported to chat {admin_chat}");
+    // Quiet periods still write their counts, so an hour with a few updates never looks silent
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+        loop {
+            interval.tick().await;
+            flush_events_if_due().await;
+        }
+    });
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = check_last_hour(&bot, admin_chat).await {
+                warn!("⚠️ Watchdog check failed: {e}");
+            }
+        }
+    });
+}
+
+// Why the webhook Telegram reports needs to be registered again, None when it looks healthy
+#[cfg(any(feature = "axum-server", feature = "lambda"))]
+fn webhook_problem(
+    info: &teloxide::types::WebhookInfo,
+    expected: &url::Url,
+    previous_pending: u32,
+    checked_since: chrono::DateTime<chrono::Utc>,
+) -> Option<String> {
+    if info.url.as_ref() != Some(expected) {
+        let registered = info.url.as_ref().map_or("nothing".to_string(), |url| url.to_string());
+        return Some(format!("Telegram had {registered} registered instead of {expected}"));
+    }
+    if info.pending_update_count > MAX_PENDING_UPDATES && info.pending_update_count > previous_pending {
+        return Some(format!("{} updates are waiting for delivery", info.pending_update_count));
+    }
+    if let Some(error_date) = info.last_error_date
+        && error_date > checked_since
+    {
+        let message = info.last_error_message.as_deref().unwrap_or("unknown error");
+        return Some(format!("Telegram failed to deliver an update: {message}"));
+    }
+    None
+}
+
+// Register the webhook again and tell the admin chat. Replicas see the same webhook info, so only the first
+// one in each check interval acts on it
+#[cfg(any(feature = "axum-server", feature = "lambda"))]
+async fn repair_webhook(bot: &Bot, webhook_url: &url::Url, problem: &str) {
+    let now = chrono::Utc::now().timestamp();
+    let window = now / WEBHOOK_CHECK_INTERVAL.as_secs() as i64;
+    let claimed = match create_storage().await {
+        Ok(storage) => storage
+            .try_increment_counter(&format!("watch#webhook#{window}"), 1, now + 3600)
+            .await
+            .map(|claim| claim.is_some())
+            .unwrap_or(true),
+        Err(_) => true,
+    };
+    if !claimed {
+        return;
+    }
+
+    warn!("🔧 Webhook problem detected, registering it again: {problem}");
+    let outcome = match bot.set_webhook(webhook_url.clone()).await {
+        Ok(_) => format!("Registered {webhook_url} again."),
+        Err(e) => format!("Registering {webhook_url} again failed: {e}"),
+    };
+    info!("🔧 {outcome}");
+    if let Some(admin_chat) = get_admin_chat_id() {
+        let text = format!("🔧 Webhook watchdog: {problem}.\n\n{outcome}");
+        if let Err(e) = bot.send_message(admin_chat, text).await {
+            warn!("⚠️ Failed to notify the admin chat about the webhook: {e}");
+        }
+    }
+}
+
+// Periodically compare getWebhookInfo with WEBHOOK_URL and register the webhook again when it drifted
+// or deliveries fail, so infrastructure changes do not cause silent outages
+#[cfg(feature = "axum-server")]
+pub fn spawn_webhook_watchdog(bot: Bot, webhook_url: url::Url) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(WEBHOOK_CHECK_INTERVAL);
+        // The first tick fires immediately, right after the webhook was set
+        interval.tick().await;
+        let mut previous_pending = 0;
+        let mut checked_since = chrono::Utc::now();
+        loop {
+            interval.tick().await;
+            let info = match bot.get_webhook_info().await {
+                Ok(info) => info,
+                Err(e) => {
+                    warn!("⚠️ Failed to get webhook info: {e}");
+                    continue;
+                }
+            };
+            let problem = webhook_problem(&info, &webhook_url, previous_pending, checked_since);
+            previous_pending = info.pending_update_count;
+            checked_since = chrono::Utc::now();
+            if let Some(problem) = problem {
+                repair_webhook(&bot, &webhook_url, &problem).await;
+            }
+        }
+    });
+}
+
+// One webhook check from a scheduled Lambda invocation, for the first invocation in each check interval.
+// Nothing survives between invocations, so any backlog above the limit counts and errors are judged
+// over the last interval
+#[cfg(feature = "lambda")]
+pub async fn run_scheduled_webhook_check(bot: &Bot) {
+    let Some(webhook_url) = std::env::var("WEBHOOK_URL").ok().and_then(|url| url.parse::<url::Url>().ok()) else {
+        return;
+    };
+    let now = chrono::Utc::now();
+    let window = now.timestamp() / WEBHOOK_CHECK_INTERVAL.as_secs() as i64;
+    let claimed = match create_storage().await {
+        Ok(storage) => storage
+            .try_increment_counter(&format!("watch#webhook#checked#{window}"), 1, now.timestamp() + 3600)
+            .await
+            .map(|claim| claim.is_some()),
+        Err(e) => Err(e),
+    };
+    match claimed {
+        Ok(true) => {}
+        Ok(false) => return,
+        Err(e) => {
+            warn!("⚠️ Failed to claim the webhook check: {e}");
+            return;
+        }
+    }
+
+    let info = match bot.get_webhook_info().await {
+        Ok(info) => info,
+        Err(e) => {
+            warn!("⚠️ Failed to get webhook info: {e}");
+            return;
+        }
+    };
+    let checked_since = now - chrono::Duration::from_std(WEBHOOK_CHECK_INTERVAL).unwrap_or_default();
+    if let Some(problem) = webhook_problem(&info, &webhook_url, 0, checked_since) {
+        repair_webhook(bot, &webhook_url, &problem).await;
+    }
+}